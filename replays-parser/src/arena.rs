@@ -0,0 +1,241 @@
+use crate::bigworld::ArgReader;
+use crate::entities::{MethodCall, PropertyUpdate};
+use crate::events::Event;
+use crate::pickle::{self, PickleValue};
+use anyhow::{anyhow, Context, Result};
+use flate2::read::ZlibDecoder;
+use serde::{Serialize, Serializer};
+use std::io::Read;
+
+// ARENA_UPDATE codes from the client's constants.py
+pub const VEHICLE_LIST: u8 = 1;
+pub const VEHICLE_ADDED: u8 = 2;
+pub const PERIOD: u8 = 3;
+pub const STATISTICS: u8 = 4;
+pub const VEHICLE_STATISTICS: u8 = 5;
+pub const VEHICLE_KILLED: u8 = 6;
+pub const BASE_POINTS: u8 = 8;
+pub const BASE_CAPTURED: u8 = 9;
+pub const VEHICLE_UPDATED: u8 = 11;
+
+/// ARENA_PERIOD
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Idle,
+    Waiting,
+    Prebattle,
+    Battle,
+    AfterBattle,
+    Other(u8),
+}
+
+impl From<i64> for Period {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Period::Idle,
+            1 => Period::Waiting,
+            2 => Period::Prebattle,
+            3 => Period::Battle,
+            4 => Period::AfterBattle,
+            other => Period::Other(other as u8),
+        }
+    }
+}
+
+impl Serialize for Period {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Period::Idle => serializer.serialize_str("idle"),
+            Period::Waiting => serializer.serialize_str("waiting"),
+            Period::Prebattle => serializer.serialize_str("prebattle"),
+            Period::Battle => serializer.serialize_str("battle"),
+            Period::AfterBattle => serializer.serialize_str("after_battle"),
+            Period::Other(code) => serializer.serialize_str(&format!("period_{}", code)),
+        }
+    }
+}
+
+/// ATTACK_REASON indices as sent in VEHICLE_KILLED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathReason {
+    Shot,
+    Fire,
+    Ramming,
+    WorldCollision,
+    DeathZone,
+    Drowning,
+    Overturn,
+    /// Strikes called in by the map or by equipment (artillery_protection, artillery_sector,
+    /// artillery_eq), not shells fired by an SPG, which count as `Shot`.
+    ArtilleryStrike,
+    Bomber,
+    Other(u8),
+}
+
+impl From<i64> for DeathReason {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => DeathReason::Shot,
+            1 => DeathReason::Fire,
+            2 => DeathReason::Ramming,
+            3 => DeathReason::WorldCollision,
+            4 => DeathReason::DeathZone,
+            5 => DeathReason::Drowning,
+            7 => DeathReason::Overturn,
+            9 | 10 | 13 => DeathReason::ArtilleryStrike,
+            11 | 14 => DeathReason::Bomber,
+            other => DeathReason::Other(other as u8),
+        }
+    }
+}
+
+impl Serialize for DeathReason {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let name = match self {
+            DeathReason::Shot => "shot",
+            DeathReason::Fire => "fire",
+            DeathReason::Ramming => "ramming",
+            DeathReason::WorldCollision => "world_collision",
+            DeathReason::DeathZone => "death_zone",
+            DeathReason::Drowning => "drowning",
+            DeathReason::Overturn => "overturn",
+            DeathReason::ArtilleryStrike => "artillery_strike",
+            DeathReason::Bomber => "bomber",
+            DeathReason::Other(code) => return serializer.serialize_str(&format!("reason_{}", code)),
+        };
+        serializer.serialize_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleListChange {
+    /// The full roster (VEHICLE_LIST), replacing anything known before.
+    List,
+    Added,
+    Updated,
+}
+
+/// One roster entry from a vehicle list update.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArenaVehicle {
+    pub vehicle: u32,
+    pub name: Option<String>,
+    pub team: Option<u8>,
+    pub is_alive: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VehicleFrags {
+    pub vehicle: u32,
+    pub frags: u32,
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    if (call.entity, call.method) != ("Avatar", "updateArena") {
+        return Ok(None);
+    }
+    // updateArena(updateType, argStr)
+    let mut args = ArgReader::new(call.args);
+    let update_type = args.read_u8()?;
+    let payload = args.read_blob()?;
+    decode_update(update_type, payload)
+}
+
+pub(crate) fn decode_property(update: &PropertyUpdate) -> Result<Option<Event>> {
+    match (update.entity, update.property) {
+        ("Vehicle" | "Avatar", "arenaUniqueID") => {
+            Ok(Some(Event::ArenaUniqueId { arena_unique_id: ArgReader::new(update.value).read_u64()? }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Decodes one arena update. The argument is a cPickle, zlib-compressed for the roster updates.
+pub fn decode_update(update_type: u8, payload: &[u8]) -> Result<Option<Event>> {
+    let known = [
+        VEHICLE_LIST, VEHICLE_ADDED, PERIOD, STATISTICS, VEHICLE_STATISTICS,
+        VEHICLE_KILLED, BASE_POINTS, BASE_CAPTURED, VEHICLE_UPDATED,
+    ];
+    if !known.contains(&update_type) {
+        return Ok(None);
+    }
+    let value = unpickle(payload).with_context(|| format!("Failed to unpickle arena update {}", update_type))?;
+    let items = value.as_seq().unwrap_or_default();
+    let int = |i: usize| items.get(i).and_then(|v| v.as_i64()).ok_or_else(|| anyhow!("Arena update {} is missing field {}", update_type, i));
+
+    let event = match update_type {
+        VEHICLE_LIST => Event::VehicleListUpdate {
+            change: VehicleListChange::List,
+            vehicles: items.iter().filter_map(arena_vehicle).collect(),
+        },
+        VEHICLE_ADDED | VEHICLE_UPDATED => Event::VehicleListUpdate {
+            change: if update_type == VEHICLE_ADDED { VehicleListChange::Added } else { VehicleListChange::Updated },
+            vehicles: arena_vehicle(&value).into_iter().collect(),
+        },
+        // (period, periodEndTime, periodLength, periodAdditionalInfo)
+        PERIOD => Event::ArenaPeriod {
+            period: Period::from(int(0)?),
+            end_time: items.get(1).and_then(|v| v.as_f64()).unwrap_or_default(),
+            length: items.get(2).and_then(|v| v.as_f64()).unwrap_or_default(),
+        },
+        // [(vehicleID, frags), ...]
+        STATISTICS => Event::VehicleStatistics {
+            stats: items.iter().filter_map(vehicle_frags).collect(),
+        },
+        // (vehicleID, frags)
+        VEHICLE_STATISTICS => Event::VehicleStatistics {
+            stats: vehicle_frags(&value).into_iter().collect(),
+        },
+        // (victimID, killerID, equipmentID, reason, numVehiclesAffected)
+        VEHICLE_KILLED => Event::VehicleKilled {
+            victim: int(0)? as u32,
+            killer: int(1)? as u32,
+            reason: DeathReason::from(int(3)?),
+        },
+        // (team, baseID, points, timeLeft, invadersCnt, capturingStopped)
+        BASE_POINTS => Event::BasePoints {
+            team: int(0)? as u8,
+            base_id: int(1)? as u32,
+            points: int(2)? as u32,
+            invaders: items.get(4).and_then(|v| v.as_i64()).unwrap_or_default() as u32,
+            capturing_stopped: items.get(5).and_then(|v| v.as_bool()).unwrap_or_default(),
+        },
+        // (team, baseID)
+        BASE_CAPTURED => Event::BaseCaptured {
+            team: int(0)? as u8,
+            base_id: int(1)? as u32,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+pub(crate) fn unpickle(payload: &[u8]) -> Result<PickleValue> {
+    // cPickle protocol 2 starts with PROTO (0x80); anything else should be zlib
+    if payload.first() == Some(&0x80) {
+        return pickle::loads(payload);
+    }
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(payload).read_to_end(&mut inflated)?;
+    pickle::loads(&inflated)
+}
+
+// (vehicleID, vehicleCompDescr, name, team, isAlive, ...)
+fn arena_vehicle(value: &PickleValue) -> Option<ArenaVehicle> {
+    let items = value.as_seq()?;
+    Some(ArenaVehicle {
+        vehicle: items.first()?.as_i64()? as u32,
+        name: items.get(2).and_then(|v| v.as_str()).map(str::to_string),
+        team: items.get(3).and_then(|v| v.as_i64()).map(|t| t as u8),
+        is_alive: items.get(4).and_then(|v| v.as_bool()),
+    })
+}
+
+fn vehicle_frags(value: &PickleValue) -> Option<VehicleFrags> {
+    let items = value.as_seq()?;
+    Some(VehicleFrags {
+        vehicle: items.first()?.as_i64()? as u32,
+        frags: items.get(1)?.as_i64()? as u32,
+    })
+}
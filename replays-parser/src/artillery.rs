@@ -0,0 +1,261 @@
+use crate::arena::Period;
+use crate::bigworld::ArgReader;
+use crate::entities::{EntityMessage, MethodCall, PropertyUpdate};
+use crate::events::{Event, EventLog, TimedEvent};
+use crate::positions::PositionSource;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Explosion damage of one shell reaches every victim within this many seconds
+const SPLASH_WINDOW: f32 = 0.5;
+// How far apart a stun or an enemySPGHit may be from the explosion it belongs to
+const STUN_WINDOW: f32 = 1.0;
+const SPG_HIT_RADIUS: f32 = 15.0;
+// A vehicle that moved less than this (meters) in the last `STATIONARY_WINDOW` seconds stood still
+const STATIONARY_DISTANCE: f32 = 1.0;
+const STATIONARY_WINDOW: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrikeKind {
+    /// Avatar.showHittingArea: artillery strike consumable / artillery fort salvo.
+    Artillery,
+    /// Avatar.showCarpetBombing.
+    Bomber,
+    /// An AttackArtilleryFort entity entered the client's view.
+    ArtilleryFort,
+    /// An AttackBomber entity entered the client's view.
+    BomberFlight,
+}
+
+/// A period during which `vehicle` was stunned.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StunInterval {
+    pub vehicle: u32,
+    pub from: f32,
+    pub to: f32,
+}
+
+impl StunInterval {
+    pub fn duration(&self) -> f32 {
+        self.to - self.from
+    }
+}
+
+/// One artillery shell, correlated from explosion damage, stuns and enemySPGHit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtyShot {
+    pub time: f32,
+    /// Unknown for enemySPGHit without splash damage.
+    pub shooter: Option<u32>,
+    pub impact_point: [f32; 3],
+    /// Vehicles that took splash damage.
+    pub victims: Vec<u32>,
+    /// Longest stun caused by the shell.
+    pub stun_duration: Option<f32>,
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    let mut args = ArgReader::new(call.args);
+    let event = match (call.entity, call.method) {
+        // showDamageFromExplosion(attackerID, center, effectsIndex, damageFactor)
+        ("Vehicle", "showDamageFromExplosion") => {
+            let attacker = args.read_entity_id()?;
+            let center = args.read_vec3()?;
+            let _effects_index = args.read_u8()?;
+            Event::DamageFromExplosion {
+                target: call.entity_id,
+                attacker,
+                center,
+                damage_factor: args.read_u8()?,
+            }
+        }
+        ("Avatar", "enemySPGHit") => Event::EnemySpgHit { hit_point: args.read_vec3()? },
+        // enemySPGShotSound(shooterPosition, targetPosition)
+        ("Avatar", "enemySPGShotSound") => Event::EnemySpgShot {
+            shooter_position: args.read_vec3()?,
+            target_position: args.read_vec3()?,
+        },
+        // showHittingArea / showCarpetBombing(equipmentID, position, direction, time)
+        ("Avatar", "showHittingArea") | ("Avatar", "showCarpetBombing") => Event::StrikeArea {
+            kind: if call.method == "showHittingArea" { StrikeKind::Artillery } else { StrikeKind::Bomber },
+            equipment_id: Some(args.read_u16()?),
+            position: Some(args.read_vec3()?),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+pub(crate) fn decode_property(update: &PropertyUpdate) -> Result<Option<Event>> {
+    if (update.entity, update.property) != ("Vehicle", "stunInfo") {
+        return Ok(None);
+    }
+    // STUN_INFO: [stunFinishTime (server time, f64)] and, on newer clients, [stunDuration (f32)]
+    let mut value = ArgReader::new(update.value);
+    let finish_time = value.read_f64()?;
+    let duration = if value.remaining() >= 4 { Some(value.read_f32()?) } else { None };
+    Ok(Some(Event::StunInfo { vehicle: update.entity_id, finish_time, duration }))
+}
+
+pub(crate) fn decode_presence(message: &EntityMessage) -> Option<Event> {
+    let EntityMessage::Entered(presence) = message else { return None };
+    let kind = match presence.entity {
+        "AttackArtilleryFort" => StrikeKind::ArtilleryFort,
+        "AttackBomber" => StrikeKind::BomberFlight,
+        _ => return None,
+    };
+    Some(Event::StrikeArea { kind, equipment_id: None, position: None })
+}
+
+/// Stun intervals per vehicle, in order of the stun starting.
+///
+/// `stunFinishTime` is server time; it is mapped onto replay time through the
+/// last arena period update. A stun update with a finish time in the past (or 0)
+/// ends the current stun early, e.g. after a medkit.
+pub fn stun_intervals(log: &EventLog) -> Vec<StunInterval> {
+    let mut server_offset: Option<f64> = None;
+    let mut open: HashMap<u32, StunInterval> = HashMap::new();
+    let mut intervals = Vec::new();
+
+    for TimedEvent { time, event } in &log.events {
+        let time = *time;
+        match event {
+            // The period update arrives as the period starts
+            Event::ArenaPeriod { period, end_time, length } if *period != Period::Idle && *end_time > 0.0 => {
+                server_offset = Some(end_time - length - time as f64);
+            }
+            Event::StunInfo { vehicle, finish_time, duration } => {
+                let end = match (duration, server_offset) {
+                    (Some(duration), _) if *finish_time > 0.0 => time + duration,
+                    (_, Some(offset)) => (finish_time - offset) as f32,
+                    (None, None) if *finish_time > 0.0 => f32::INFINITY,
+                    _ => time,
+                };
+                match open.remove(vehicle) {
+                    Some(mut current) if current.to >= time => {
+                        if end > time {
+                            // Restunned while stunned: one continuous interval
+                            current.to = end;
+                            open.insert(*vehicle, current);
+                        } else {
+                            current.to = time;
+                            intervals.push(current);
+                        }
+                    }
+                    expired => {
+                        intervals.extend(expired);
+                        if end > time {
+                            open.insert(*vehicle, StunInterval { vehicle: *vehicle, from: time, to: end });
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    intervals.extend(open.into_values().map(|mut stun| {
+        stun.to = stun.to.min(log.end_time);
+        stun
+    }));
+
+    intervals.sort_by(|a, b| a.from.total_cmp(&b.from).then(a.vehicle.cmp(&b.vehicle)));
+    intervals
+}
+
+/// Correlates artillery shells in one replay.
+///
+/// Splash damage of one attacker within `SPLASH_WINDOW` counts as one shell. It
+/// is an artillery shell if it stunned a victim or the client reported an
+/// enemySPGHit close to it; enemySPGHit on its own is a shell that dealt no damage.
+pub fn arty_shots(log: &EventLog) -> Vec<ArtyShot> {
+    let stuns = stun_intervals(log);
+    let mut shots: Vec<ArtyShot> = Vec::new();
+    let mut confirmed: Vec<bool> = Vec::new();
+
+    for TimedEvent { time, event } in &log.events {
+        let time = *time;
+        match event {
+            Event::DamageFromExplosion { target, attacker, center, .. } => {
+                let same_shell = shots.iter().rposition(|s| {
+                    s.shooter == Some(*attacker) && time - s.time <= SPLASH_WINDOW
+                });
+                let index = same_shell.unwrap_or_else(|| {
+                    shots.push(ArtyShot {
+                        time,
+                        shooter: Some(*attacker),
+                        impact_point: *center,
+                        victims: Vec::new(),
+                        stun_duration: None,
+                    });
+                    confirmed.push(false);
+                    shots.len() - 1
+                });
+                let shot = &mut shots[index];
+                shot.victims.push(*target);
+                let stun = stuns
+                    .iter()
+                    .filter(|s| s.vehicle == *target && (s.from - time).abs() <= STUN_WINDOW)
+                    .map(StunInterval::duration)
+                    .max_by(f32::total_cmp);
+                if let Some(stun) = stun {
+                    shot.stun_duration = Some(shot.stun_duration.map_or(stun, |s| s.max(stun)));
+                    confirmed[index] = true;
+                }
+            }
+            Event::EnemySpgHit { hit_point } => {
+                let near = shots.iter().rposition(|s| {
+                    (time - s.time).abs() <= STUN_WINDOW && distance_xz(&s.impact_point, hit_point) <= SPG_HIT_RADIUS
+                });
+                match near {
+                    Some(index) => confirmed[index] = true,
+                    None => {
+                        shots.push(ArtyShot {
+                            time,
+                            shooter: None,
+                            impact_point: *hit_point,
+                            victims: Vec::new(),
+                            stun_duration: None,
+                        });
+                        confirmed.push(true);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    shots.into_iter().zip(confirmed).filter_map(|(shot, arty)| arty.then_some(shot)).collect()
+}
+
+/// How many artillery shells hit `vehicle` while it stood still.
+pub fn spg_hits_while_stationary(log: &EventLog, shots: &[ArtyShot], vehicle: u32) -> u32 {
+    let track: Vec<(f32, [f32; 3])> = log
+        .events
+        .iter()
+        .filter_map(|e| match &e.event {
+            Event::Position(p) if p.vehicle == vehicle && p.source == PositionSource::EntityMove => {
+                Some((e.time, [p.x, p.y.unwrap_or_default(), p.z]))
+            }
+            _ => None,
+        })
+        .collect();
+    let position_at = |time: f32| {
+        let end = track.partition_point(|(t, _)| *t <= time);
+        end.checked_sub(1).map(|index| track[index].1)
+    };
+
+    shots
+        .iter()
+        .filter(|shot| shot.victims.contains(&vehicle))
+        .filter(|shot| match (position_at(shot.time - STATIONARY_WINDOW), position_at(shot.time)) {
+            (Some(before), Some(now)) => distance_xz(&before, &now) < STATIONARY_DISTANCE,
+            _ => false,
+        })
+        .count() as u32
+}
+
+fn distance_xz(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
@@ -0,0 +1,166 @@
+use crate::arena::DeathReason;
+use crate::bigworld::ArgReader;
+use crate::entities::MethodCall;
+use crate::events::{Event, TimedEvent};
+use anyhow::Result;
+use serde::Serialize;
+
+// BATTLE_EVENT_TYPE from the client's constants.py
+pub const SPOTTED: u8 = 0;
+pub const RADIO_ASSIST: u8 = 1;
+pub const TRACK_ASSIST: u8 = 2;
+pub const BASE_CAPTURE_POINTS: u8 = 3;
+pub const BASE_CAPTURE_DROPPED: u8 = 4;
+pub const BASE_CAPTURE_BLOCKED: u8 = 5;
+pub const TANKING: u8 = 6;
+pub const CRIT: u8 = 7;
+pub const DAMAGE: u8 = 8;
+pub const KILL: u8 = 9;
+pub const RECEIVED_CRIT: u8 = 10;
+pub const RECEIVED_DAMAGE: u8 = 11;
+pub const STUN_ASSIST: u8 = 12;
+
+/// One ribbon / damage log entry from Avatar.onBattleEvents.
+///
+/// `target` is the other vehicle involved: the victim for outgoing events and
+/// the attacker for `Blocked`, `ReceivedDamage` and `ReceivedCrit`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BattleEvent {
+    Spotted { target: u32 },
+    RadioAssist { target: u32, damage: u32 },
+    TrackAssist { target: u32, damage: u32 },
+    StunAssist { target: u32, damage: u32 },
+    BaseCapturePoints { points: u32 },
+    BaseCaptureDropped { points: u32 },
+    BaseCaptureBlocked { points: u32 },
+    /// TANKING: damage bounced or absorbed by the recording player's armor.
+    Blocked { target: u32, damage: u32 },
+    Crit { target: u32, count: u32 },
+    Damage { target: u32, damage: u32, reason: DeathReason },
+    Kill { target: u32 },
+    ReceivedCrit { target: u32, count: u32 },
+    ReceivedDamage { target: u32, damage: u32, reason: DeathReason },
+    /// Event types this decoder does not know, with the raw packed details.
+    Other { code: u8, target: u32, details: u64, count: u16 },
+}
+
+/// Avatar.battleEventsSummary: the running totals shown in the post-death panel.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BattleEventsSummary {
+    pub damage: u32,
+    pub track_assist: u32,
+    pub radio_assist: u32,
+    pub blocked: u32,
+    pub last_killer: Option<u32>,
+    pub last_death_reason: Option<DeathReason>,
+    pub stun_assist: u32,
+}
+
+/// Per-replay sums of the ribbons, e.g. for assist or defense oriented goals.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BattleEventTotals {
+    pub spotted: u32,
+    pub kills: u32,
+    pub crits: u32,
+    pub damage: u32,
+    pub radio_assist: u32,
+    pub track_assist: u32,
+    pub stun_assist: u32,
+    pub blocked: u32,
+    pub received_damage: u32,
+}
+
+impl BattleEventTotals {
+    pub fn add(&mut self, event: &BattleEvent) {
+        match event {
+            BattleEvent::Spotted { .. } => self.spotted = self.spotted.saturating_add(1),
+            BattleEvent::Kill { .. } => self.kills = self.kills.saturating_add(1),
+            BattleEvent::Crit { count, .. } => self.crits = self.crits.saturating_add(*count),
+            BattleEvent::Damage { damage, .. } => self.damage = self.damage.saturating_add(*damage),
+            BattleEvent::RadioAssist { damage, .. } => self.radio_assist = self.radio_assist.saturating_add(*damage),
+            BattleEvent::TrackAssist { damage, .. } => self.track_assist = self.track_assist.saturating_add(*damage),
+            BattleEvent::StunAssist { damage, .. } => self.stun_assist = self.stun_assist.saturating_add(*damage),
+            BattleEvent::Blocked { damage, .. } => self.blocked = self.blocked.saturating_add(*damage),
+            BattleEvent::ReceivedDamage { damage, .. } => self.received_damage = self.received_damage.saturating_add(*damage),
+            _ => {}
+        }
+    }
+
+    /// Radio, track and stun assist combined.
+    pub fn assist(&self) -> u32 {
+        self.radio_assist.saturating_add(self.track_assist).saturating_add(self.stun_assist)
+    }
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    let mut args = ArgReader::new(call.args);
+    let event = match (call.entity, call.method) {
+        // onBattleEvents(ARRAY<BATTLE_EVENT>)
+        ("Avatar", "onBattleEvents") => Event::BattleEvents {
+            events: args.read_array(read_battle_event)?,
+        },
+        // battleEventsSummary(BATTLE_EVENTS_SUMMARY)
+        ("Avatar", "battleEventsSummary") => {
+            let damage = args.read_u32()?;
+            let track_assist = args.read_u32()?;
+            let radio_assist = args.read_u32()?;
+            let blocked = args.read_u32()?;
+            let last_killer = args.read_entity_id()?;
+            let last_death_reason = args.read_u8()?;
+            // Older clients end the summary before the stun assist
+            let stun_assist = if args.remaining() >= 4 { args.read_u32()? } else { 0 };
+            Event::BattleEventsSummary(BattleEventsSummary {
+                damage,
+                track_assist,
+                radio_assist,
+                blocked,
+                last_killer: (last_killer != 0).then_some(last_killer),
+                last_death_reason: (last_death_reason != u8::MAX).then(|| DeathReason::from(last_death_reason as i64)),
+                stun_assist,
+            })
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+// BATTLE_EVENT: [eventType (1)] [targetID (4)] [count (2)] [details (8)]
+fn read_battle_event(args: &mut ArgReader) -> Result<BattleEvent> {
+    let code = args.read_u8()?;
+    let target = args.read_entity_id()?;
+    let count = args.read_u16()?;
+    let details = args.read_u64()?;
+
+    // Damage-like details pack the amount into the high 32 bits and the attack reason into the next byte
+    let amount = (details >> 32) as u32;
+    let reason = DeathReason::from(((details >> 24) & 0xFF) as i64);
+    let event = match code {
+        SPOTTED => BattleEvent::Spotted { target },
+        RADIO_ASSIST => BattleEvent::RadioAssist { target, damage: amount },
+        TRACK_ASSIST => BattleEvent::TrackAssist { target, damage: amount },
+        STUN_ASSIST => BattleEvent::StunAssist { target, damage: amount },
+        BASE_CAPTURE_POINTS => BattleEvent::BaseCapturePoints { points: details as u32 },
+        BASE_CAPTURE_DROPPED => BattleEvent::BaseCaptureDropped { points: details as u32 },
+        BASE_CAPTURE_BLOCKED => BattleEvent::BaseCaptureBlocked { points: details as u32 },
+        TANKING => BattleEvent::Blocked { target, damage: amount },
+        CRIT => BattleEvent::Crit { target, count: count as u32 },
+        DAMAGE => BattleEvent::Damage { target, damage: amount, reason },
+        KILL => BattleEvent::Kill { target },
+        RECEIVED_CRIT => BattleEvent::ReceivedCrit { target, count: count as u32 },
+        RECEIVED_DAMAGE => BattleEvent::ReceivedDamage { target, damage: amount, reason },
+        code => BattleEvent::Other { code, target, details, count },
+    };
+    Ok(event)
+}
+
+/// Sums every decoded ribbon of one replay.
+pub fn battle_event_totals(events: &[TimedEvent]) -> BattleEventTotals {
+    let mut totals = BattleEventTotals::default();
+    for timed in events {
+        if let Event::BattleEvents { events } = &timed.event {
+            events.iter().for_each(|event| totals.add(event));
+        }
+    }
+    totals
+}
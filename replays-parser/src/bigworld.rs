@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Result};
+use byteorder::{ReadBytesExt, LittleEndian};
+use std::io::{Cursor, Read};
+
+/// Reader for BigWorld-serialized method arguments and property values.
+///
+/// All primitives are little-endian. Variable-length values (ARRAY, STRING,
+/// BLOB, PYTHON) are prefixed with a packed length: one byte if it is below
+/// 0xFF, otherwise 0xFF followed by a 3-byte length.
+pub struct ArgReader<'a> {
+    cursor: Cursor<&'a [u8]>,
+}
+
+impl<'a> ArgReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { cursor: Cursor::new(data) }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cursor.get_ref().len().saturating_sub(self.cursor.position() as usize)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.cursor.read_u8()?)
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8> {
+        Ok(self.cursor.read_i8()?)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(self.cursor.read_u16::<LittleEndian>()?)
+    }
+
+    pub fn read_i16(&mut self) -> Result<i16> {
+        Ok(self.cursor.read_i16::<LittleEndian>()?)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(self.cursor.read_u32::<LittleEndian>()?)
+    }
+
+    pub fn read_i32(&mut self) -> Result<i32> {
+        Ok(self.cursor.read_i32::<LittleEndian>()?)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        Ok(self.cursor.read_u64::<LittleEndian>()?)
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        Ok(self.cursor.read_f32::<LittleEndian>()?)
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64> {
+        Ok(self.cursor.read_f64::<LittleEndian>()?)
+    }
+
+    /// OBJECT_ID (entity ID) is an INT32 on the wire, but entity IDs are never negative.
+    pub fn read_entity_id(&mut self) -> Result<u32> {
+        Ok(self.read_i32()? as u32)
+    }
+
+    pub fn read_vec3(&mut self) -> Result<[f32; 3]> {
+        Ok([self.read_f32()?, self.read_f32()?, self.read_f32()?])
+    }
+
+    /// Reads the packed length prefix used by ARRAY, STRING and BLOB.
+    pub fn read_length(&mut self) -> Result<usize> {
+        let short = self.read_u8()?;
+        if short != 0xFF {
+            return Ok(short as usize);
+        }
+        let mut long = [0u8; 4];
+        self.cursor.read_exact(&mut long[..3])?;
+        Ok(u32::from_le_bytes(long) as usize)
+    }
+
+    pub fn read_blob(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_length()?;
+        if len > self.remaining() {
+            return Err(anyhow!("Blob length {} exceeds remaining {} bytes", len, self.remaining()));
+        }
+        let start = self.cursor.position() as usize;
+        self.cursor.set_position((start + len) as u64);
+        Ok(&self.cursor.get_ref()[start..start + len])
+    }
+
+    pub fn read_string(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(self.read_blob()?).into_owned())
+    }
+
+    /// Reads an ARRAY of fixed-size elements using `read` for each element.
+    pub fn read_array<T>(&mut self, mut read: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let len = self.read_length()?;
+        // Guard against garbage lengths before allocating
+        if len > self.remaining() {
+            return Err(anyhow!("Array length {} exceeds remaining {} bytes", len, self.remaining()));
+        }
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(read(self)?);
+        }
+        Ok(items)
+    }
+}
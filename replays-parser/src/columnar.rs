@@ -0,0 +1,369 @@
+use crate::dedupe::BattleId;
+use crate::events::{Event, TimedEvent};
+use crate::labels::Labels;
+use crate::summary::{ReplaySummary, METRIC_NAMES};
+use crate::ticks::Tick;
+use anyhow::{Context, Result};
+use arrow::array::{
+    ArrayRef, BooleanBuilder, FixedSizeListBuilder, Float32Builder, Float64Builder, ListBuilder, StringBuilder,
+    UInt32Builder,
+};
+use arrow::compute::concat_batches;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One record of a replay, as it goes into a table.
+#[derive(Debug, Clone)]
+pub struct Row<T> {
+    /// Replay file name, the key shared by all tables.
+    pub replay: String,
+    /// Set with `--dedupe`: the battle shared by all perspectives of it.
+    pub battle_id: Option<BattleId>,
+    pub record: T,
+    pub labels: Option<Labels>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnarFormat {
+    Parquet,
+    /// Arrow IPC file.
+    Arrow,
+}
+
+enum Sink {
+    Parquet(ArrowWriter<File>),
+    Arrow(FileWriter<File>),
+}
+
+/// Rows buffered before they are written as one Parquet row group / Arrow record batch.
+pub const ROW_GROUP_ROWS: usize = 128 * 1024;
+
+/// Writes record batches to one Parquet or Arrow IPC file.
+///
+/// Batches are built by the caller (e.g. one per group of replays on a worker
+/// thread) and buffered until `ROW_GROUP_ROWS` rows are pending, so files split
+/// into many shards do not end up with a row group per replay.
+///
+/// A writer dropped before `finish` (e.g. on an error) removes its file, which
+/// would otherwise be left without a footer.
+pub struct ColumnarWriter {
+    /// Taken by `finish`.
+    sink: Option<Sink>,
+    path: PathBuf,
+    schema: SchemaRef,
+    pending: Vec<RecordBatch>,
+    pending_rows: usize,
+}
+
+impl ColumnarWriter {
+    pub fn create(path: &Path, format: ColumnarFormat, schema: SchemaRef) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let sink = match format {
+            ColumnarFormat::Parquet => {
+                let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                Sink::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(properties))?)
+            }
+            ColumnarFormat::Arrow => Sink::Arrow(FileWriter::try_new(file, &schema)?),
+        };
+        Ok(Self { sink: Some(sink), path: path.to_path_buf(), schema, pending: Vec::new(), pending_rows: 0 })
+    }
+
+    /// Appends `batch`, writing the pending rows once there are `ROW_GROUP_ROWS` of them.
+    pub fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        self.pending.push(batch.clone());
+        self.pending_rows += batch.num_rows();
+        if self.pending_rows >= ROW_GROUP_ROWS {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending rows as one row group / record batch.
+    fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let batch = concat_batches(&self.schema, &self.pending)?;
+        self.pending.clear();
+        self.pending_rows = 0;
+        match &mut self.sink {
+            Some(Sink::Parquet(writer)) => {
+                writer.write(&batch)?;
+                writer.flush()?;
+            }
+            Some(Sink::Arrow(writer)) => writer.write(&batch)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.flush()?;
+        match &mut self.sink {
+            Some(Sink::Parquet(writer)) => {
+                writer.finish()?;
+            }
+            Some(Sink::Arrow(writer)) => writer.finish()?,
+            None => {}
+        }
+        // Complete: kept on drop
+        self.sink = None;
+        Ok(())
+    }
+}
+
+impl Drop for ColumnarWriter {
+    fn drop(&mut self) {
+        // Closed before removing it, for platforms that cannot remove open files
+        if self.sink.take().is_some() {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn label_fields() -> Vec<Field> {
+    vec![
+        Field::new("outcome", DataType::Utf8, true),
+        Field::new("survived", DataType::Boolean, true),
+        Field::new("final_damage", DataType::UInt32, true),
+        Field::new("incomplete", DataType::Boolean, true),
+        Field::new("time_to_death", DataType::Float32, true),
+    ]
+}
+
+fn position_type() -> DataType {
+    DataType::FixedSizeList(Arc::new(Field::new("item", DataType::Float32, true)), 3)
+}
+
+/// `replay, battle_id, tick, time, spotted` plus the label columns (null without `--labels`);
+/// `battle_id` is null without `--dedupe`.
+pub fn tick_schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("replay", DataType::Utf8, false),
+        Field::new("battle_id", DataType::Utf8, true),
+        Field::new("tick", DataType::UInt32, false),
+        Field::new("time", DataType::Float32, false),
+        Field::new("spotted", DataType::Boolean, false),
+    ];
+    fields.extend(label_fields());
+    Arc::new(Schema::new(fields))
+}
+
+/// Events keyed by `replay` and `tick`. `event` is the event name, `vehicle` the
+/// vehicle it is about (if any), `position` an `[x, y, z]` world position for
+/// position events, and `data` the remaining fields as JSON.
+pub fn event_schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("replay", DataType::Utf8, false),
+        Field::new("battle_id", DataType::Utf8, true),
+        Field::new("tick", DataType::UInt32, false),
+        Field::new("time", DataType::Float32, false),
+        Field::new("event", DataType::Utf8, false),
+        Field::new("vehicle", DataType::UInt32, true),
+        Field::new("position", position_type(), true),
+        Field::new("data", DataType::Utf8, false),
+    ];
+    fields.extend(label_fields());
+    Arc::new(Schema::new(fields))
+}
+
+/// One column per metric value, plus `<metric>_battle_results` and `<metric>_discrepancy`.
+pub fn summary_schema() -> SchemaRef {
+    let mut fields = vec![
+        Field::new("replay", DataType::Utf8, false),
+        Field::new("battle_id", DataType::Utf8, true),
+        Field::new("player", DataType::Utf8, false),
+        Field::new("vehicle", DataType::Utf8, false),
+        Field::new("map", DataType::Utf8, false),
+        Field::new("has_battle_results", DataType::Boolean, false),
+    ];
+    for name in METRIC_NAMES {
+        fields.push(Field::new(name, DataType::Float64, false));
+        fields.push(Field::new(format!("{}_battle_results", name), DataType::Float64, true));
+        fields.push(Field::new(format!("{}_discrepancy", name), DataType::Float64, true));
+    }
+    fields.push(Field::new("survived", DataType::Boolean, false));
+    fields.push(Field::new("first_death", DataType::Boolean, false));
+    fields.push(Field::new("spg_hits_while_stationary", DataType::UInt32, false));
+    fields.push(Field::new(
+        "discrepancies",
+        DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+        false,
+    ));
+    Arc::new(Schema::new(fields))
+}
+
+fn tick_index(time: f32, interval: f32) -> u32 {
+    if interval > 0.0 { (time / interval).floor().max(0.0) as u32 } else { 0 }
+}
+
+fn battle_id_column<T>(rows: &[Row<T>]) -> ArrayRef {
+    let mut column = StringBuilder::new();
+    for row in rows {
+        column.append_option(row.battle_id.map(|id| id.to_string()));
+    }
+    Arc::new(column.finish())
+}
+
+fn label_columns<T>(rows: &[Row<T>]) -> Vec<ArrayRef> {
+    let mut outcome = StringBuilder::new();
+    let mut survived = BooleanBuilder::new();
+    let mut final_damage = UInt32Builder::new();
+    let mut incomplete = BooleanBuilder::new();
+    let mut time_to_death = Float32Builder::new();
+    for row in rows {
+        let labels = row.labels.as_ref();
+        let episode = labels.map(|l| &l.episode);
+        let outcome_name = episode.and_then(|e| e.outcome).map(|o| serde_json::to_value(o).unwrap());
+        outcome.append_option(outcome_name.as_ref().and_then(|o| o.as_str()));
+        survived.append_option(episode.and_then(|e| e.survived));
+        final_damage.append_option(episode.and_then(|e| e.final_damage));
+        incomplete.append_option(episode.map(|e| e.incomplete));
+        time_to_death.append_option(labels.and_then(|l| l.time_to_death));
+    }
+    vec![
+        Arc::new(outcome.finish()),
+        Arc::new(survived.finish()),
+        Arc::new(final_damage.finish()),
+        Arc::new(incomplete.finish()),
+        Arc::new(time_to_death.finish()),
+    ]
+}
+
+pub fn ticks_batch(rows: &[Row<Tick>], interval: f32) -> Result<RecordBatch> {
+    let mut replay = StringBuilder::new();
+    let mut tick = UInt32Builder::new();
+    let mut time = Float32Builder::new();
+    let mut spotted = BooleanBuilder::new();
+    for row in rows {
+        replay.append_value(&row.replay);
+        tick.append_value(tick_index(row.record.time, interval));
+        time.append_value(row.record.time);
+        spotted.append_value(row.record.spotted);
+    }
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(replay.finish()),
+        battle_id_column(rows),
+        Arc::new(tick.finish()),
+        Arc::new(time.finish()),
+        Arc::new(spotted.finish()),
+    ];
+    columns.extend(label_columns(rows));
+    Ok(RecordBatch::try_new(tick_schema(), columns)?)
+}
+
+// Fields naming the vehicle an event is about, in order of preference
+const VEHICLE_FIELDS: &[&str] = &["vehicle", "target", "victim", "shooter"];
+
+pub fn events_batch(rows: &[Row<TimedEvent>], interval: f32) -> Result<RecordBatch> {
+    let mut replay = StringBuilder::new();
+    let mut tick = UInt32Builder::new();
+    let mut time = Float32Builder::new();
+    let mut event = StringBuilder::new();
+    let mut vehicle = UInt32Builder::new();
+    let mut position = FixedSizeListBuilder::new(Float32Builder::new(), 3);
+    let mut data = StringBuilder::new();
+    for row in rows {
+        let TimedEvent { time: at, event: record } = &row.record;
+        let mut fields = match serde_json::to_value(record)? {
+            serde_json::Value::Object(fields) => fields,
+            _ => serde_json::Map::new(),
+        };
+        let name = fields.remove("event");
+        replay.append_value(&row.replay);
+        tick.append_value(tick_index(*at, interval));
+        time.append_value(*at);
+        event.append_value(name.as_ref().and_then(|n| n.as_str()).unwrap_or_default());
+        vehicle.append_option(
+            VEHICLE_FIELDS
+                .iter()
+                .find_map(|key| fields.get(*key).and_then(|v| v.as_u64()))
+                .map(|id| id as u32),
+        );
+        match record {
+            Event::Position(sample) => {
+                let values = position.values();
+                values.append_value(sample.x);
+                values.append_option(sample.y);
+                values.append_value(sample.z);
+                position.append(true);
+            }
+            _ => {
+                position.values().append_nulls(3);
+                position.append(false);
+            }
+        }
+        data.append_value(serde_json::to_string(&fields)?);
+    }
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(replay.finish()),
+        battle_id_column(rows),
+        Arc::new(tick.finish()),
+        Arc::new(time.finish()),
+        Arc::new(event.finish()),
+        Arc::new(vehicle.finish()),
+        Arc::new(position.finish()),
+        Arc::new(data.finish()),
+    ];
+    columns.extend(label_columns(rows));
+    Ok(RecordBatch::try_new(event_schema(), columns)?)
+}
+
+pub fn summaries_batch(rows: &[Row<ReplaySummary>]) -> Result<RecordBatch> {
+    let mut replay = StringBuilder::new();
+    let mut player = StringBuilder::new();
+    let mut vehicle = StringBuilder::new();
+    let mut map = StringBuilder::new();
+    let mut has_battle_results = BooleanBuilder::new();
+    let mut metrics: Vec<[Float64Builder; 3]> = METRIC_NAMES.iter().map(|_| Default::default()).collect();
+    let mut survived = BooleanBuilder::new();
+    let mut first_death = BooleanBuilder::new();
+    let mut spg_hits_while_stationary = UInt32Builder::new();
+    let mut discrepancies = ListBuilder::new(StringBuilder::new());
+    for row in rows {
+        let summary = &row.record;
+        replay.append_value(&row.replay);
+        player.append_value(&summary.player);
+        vehicle.append_value(&summary.vehicle);
+        map.append_value(&summary.map);
+        has_battle_results.append_value(summary.has_battle_results);
+        for ((_, metric), [value, battle_results, discrepancy]) in summary.metrics().into_iter().zip(&mut metrics) {
+            value.append_value(metric.value);
+            battle_results.append_option(metric.battle_results);
+            discrepancy.append_option(metric.discrepancy);
+        }
+        survived.append_value(summary.survived);
+        first_death.append_value(summary.first_death);
+        spg_hits_while_stationary.append_value(summary.spg_hits_while_stationary);
+        for name in &summary.discrepancies {
+            discrepancies.values().append_value(name);
+        }
+        discrepancies.append(true);
+    }
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(replay.finish()),
+        battle_id_column(rows),
+        Arc::new(player.finish()),
+        Arc::new(vehicle.finish()),
+        Arc::new(map.finish()),
+        Arc::new(has_battle_results.finish()),
+    ];
+    for builders in &mut metrics {
+        columns.extend(builders.iter_mut().map(|b| Arc::new(b.finish()) as ArrayRef));
+    }
+    columns.push(Arc::new(survived.finish()));
+    columns.push(Arc::new(first_death.finish()));
+    columns.push(Arc::new(spg_hits_while_stationary.finish()));
+    columns.push(Arc::new(discrepancies.finish()));
+    Ok(RecordBatch::try_new(summary_schema(), columns)?)
+}
@@ -0,0 +1,88 @@
+//! Battle identity, for recognizing replays of the same battle recorded by different players.
+
+use crate::events::{Event, EventLog};
+use crate::shards::fnv1a;
+use crate::types::Replay;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Identity of the battle a replay was recorded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BattleId {
+    /// The server's `arenaUniqueID`, from the battle results or the Vehicle / Avatar property.
+    Arena(u64),
+    /// Hash of map, start time and roster, for replays without an `arenaUniqueID`.
+    Fingerprint(u64),
+}
+
+impl BattleId {
+    /// Shard key (see `shards::shard_key`): equal for every perspective of the battle.
+    pub fn shard_key(&self) -> u64 {
+        match self {
+            BattleId::Arena(id) => fnv1a(&id.to_le_bytes()),
+            BattleId::Fingerprint(hash) => *hash,
+        }
+    }
+}
+
+/// `3850942432522416` for arena IDs, `fp-<16 hex digits>` for fingerprints.
+impl fmt::Display for BattleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BattleId::Arena(id) => write!(f, "{}", id),
+            BattleId::Fingerprint(hash) => write!(f, "fp-{:016x}", hash),
+        }
+    }
+}
+
+impl Serialize for BattleId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl FromStr for BattleId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.strip_prefix("fp-") {
+            Some(hash) => Ok(BattleId::Fingerprint(u64::from_str_radix(hash, 16)?)),
+            None => Ok(BattleId::Arena(s.parse()?)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BattleId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The battle of `replay`: its `arenaUniqueID` from the battle results or a decoded
+/// property, else a fingerprint of the map, `dateTime` and roster.
+///
+/// `dateTime` is the recording client's clock, so fingerprints only match for
+/// perspectives recorded in the same time zone that started loading in the same second.
+pub fn battle_id(replay: &Replay, log: &EventLog) -> BattleId {
+    let property = log.events.iter().find_map(|e| match e.event {
+        Event::ArenaUniqueId { arena_unique_id } if arena_unique_id != 0 => Some(arena_unique_id),
+        _ => None,
+    });
+    match replay.arena_unique_id().or(property) {
+        Some(id) => BattleId::Arena(id),
+        None => BattleId::Fingerprint(fingerprint(replay)),
+    }
+}
+
+fn fingerprint(replay: &Replay) -> u64 {
+    let config = &replay.battle_config;
+    let mut roster: Vec<_> = config.vehicles.iter().collect();
+    roster.sort_by_key(|(id, _)| (id.parse::<u32>().ok(), id.as_str()));
+
+    let mut text = format!("{}\n{}\n", config.map_name, config.date_time);
+    for (id, vehicle) in roster {
+        text.push_str(&format!("{}:{}:{}:{}\n", id, vehicle.name, vehicle.vehicle_type, vehicle.team));
+    }
+    fnv1a(text.as_bytes())
+}
@@ -7,8 +7,10 @@ include!(concat!(env!("OUT_DIR"), "/generated_ids.rs"));
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Definitions {
-    #[serde(rename = "packetTypes")]
+    // The message_codes defaults only carry packet types
+    #[serde(rename = "packetTypes", default)]
     pub packet_types: HashMap<String, serde_json::Value>, 
+    #[serde(default)]
     pub entities: HashMap<String, EntityDef>,
 }
 
@@ -39,6 +41,31 @@ pub struct PropertyDef {
     pub r#type: String, // 'type' is a reserved keyword
 }
 
+impl EntityDef {
+    pub fn client_method(&self, method_id: u32) -> Option<&MethodDef> {
+        self.client_methods.get(&method_id.to_string())
+    }
+
+    pub fn property(&self, property_id: u32) -> Option<&PropertyDef> {
+        self.properties.get(&property_id.to_string())
+    }
+}
+
+impl Definitions {
+    /// Name the `packetTypes` table gives message `message_id` of `packet_type`,
+    /// e.g. `MODULE_DAMAGE` for 0x0B of 0x08.
+    pub fn subtype(&self, packet_type: u32, message_id: u32) -> Option<&str> {
+        let subtypes = self.packet_types.get(&format!("0x{:02X}", packet_type))?.get("subtypes")?;
+        subtypes.get(format!("0x{:02X}", message_id))?.as_str()
+    }
+}
+
+impl Default for Definitions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Definitions {
     pub fn new() -> Self {
         Self {
@@ -130,6 +157,21 @@ impl Definitions {
         }
     }
 
+    /// Hash of the definitions' contents, independent of map order: changes whenever
+    /// a rebuild or an `ids_<version>.json` override changes what gets decoded.
+    pub fn fingerprint(&self) -> u64 {
+        // serde_json's maps are sorted, which makes the encoding canonical
+        let canonical = serde_json::to_value(self).map(|v| v.to_string()).unwrap_or_default();
+        crate::shards::fnv1a(canonical.as_bytes())
+    }
+
+    /// Looks up an entity definition by the type ID found in entity create packets.
+    /// Packet type IDs are 1-based while the definition keys start at 0.
+    pub fn entity_by_type(&self, type_id: u16) -> Option<&EntityDef> {
+        let index = type_id.checked_sub(1)?;
+        self.entities.get(&index.to_string())
+    }
+
     /// Loads definitions for a specific version from the embedded JSON.
     /// Returns None if version not found.
     pub fn load_embedded(version: &str) -> Option<Self> {
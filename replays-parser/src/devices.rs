@@ -0,0 +1,244 @@
+use crate::bigworld::ArgReader;
+use crate::entities::{MethodCall, PropertyUpdate};
+use crate::events::{Event, EventLog, TimedEvent};
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeSet, HashMap};
+
+/// Vehicle extras in the order of the client's extras table; the damaged and
+/// destroyed lists of Avatar.showOtherVehicleDamagedDevices index into it.
+const EXTRAS: &[&str] = &[
+    "shot",
+    "fire",
+    "ammoBayHealth",
+    "fuelTankHealth",
+    "engineHealth",
+    "radioHealth",
+    "leftTrackHealth",
+    "rightTrackHealth",
+    "gunHealth",
+    "turretRotatorHealth",
+    "surveyingDeviceHealth",
+    "commanderHealth",
+    "driverHealth",
+    "radiomanHealth",
+    "radioman2Health",
+    "gunnerHealth",
+    "gunner2Health",
+    "loaderHealth",
+    "loader2Health",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Module {
+    Engine,
+    AmmoBay,
+    Gun,
+    TurretRing,
+    LeftTrack,
+    RightTrack,
+    FuelTank,
+    Radio,
+    ObservationDevice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrewRole {
+    Commander,
+    Driver,
+    Radioman,
+    Gunner,
+    Loader,
+}
+
+/// One entry of the vehicle extras table that can be damaged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Device {
+    Module(Module),
+    Crew(CrewRole),
+    /// An extras index outside the known table.
+    Other(u8),
+}
+
+impl From<u8> for Device {
+    fn from(index: u8) -> Self {
+        let name = EXTRAS.get(index as usize).copied().unwrap_or_default();
+        match name {
+            "ammoBayHealth" => Device::Module(Module::AmmoBay),
+            "fuelTankHealth" => Device::Module(Module::FuelTank),
+            "engineHealth" => Device::Module(Module::Engine),
+            "radioHealth" => Device::Module(Module::Radio),
+            "leftTrackHealth" => Device::Module(Module::LeftTrack),
+            "rightTrackHealth" => Device::Module(Module::RightTrack),
+            "gunHealth" => Device::Module(Module::Gun),
+            "turretRotatorHealth" => Device::Module(Module::TurretRing),
+            "surveyingDeviceHealth" => Device::Module(Module::ObservationDevice),
+            "commanderHealth" => Device::Crew(CrewRole::Commander),
+            "driverHealth" => Device::Crew(CrewRole::Driver),
+            "radiomanHealth" | "radioman2Health" => Device::Crew(CrewRole::Radioman),
+            "gunnerHealth" | "gunner2Health" => Device::Crew(CrewRole::Gunner),
+            "loaderHealth" | "loader2Health" => Device::Crew(CrewRole::Loader),
+            _ => Device::Other(index),
+        }
+    }
+}
+
+impl Serialize for Device {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Device::Module(module) => module.serialize(serializer),
+            Device::Crew(role) => role.serialize(serializer),
+            Device::Other(index) => serializer.serialize_str(&format!("extra_{}", index)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleState {
+    Damaged,
+    Destroyed,
+    Repaired,
+}
+
+/// A period during which a module of `vehicle` was destroyed (e.g. de-tracked).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DestroyedInterval {
+    pub vehicle: u32,
+    pub module: Module,
+    pub from: f32,
+    pub to: f32,
+}
+
+impl DestroyedInterval {
+    pub fn duration(&self) -> f32 {
+        self.to - self.from
+    }
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    let mut args = ArgReader::new(call.args);
+    let event = match (call.entity, call.method) {
+        // showOtherVehicleDamagedDevices(vehicleID, damagedExtras, destroyedExtras)
+        ("Avatar", "showOtherVehicleDamagedDevices") => Event::DamagedDevices {
+            vehicle: args.read_entity_id()?,
+            damaged: args.read_array(|r| r.read_u8().map(Device::from))?,
+            destroyed: args.read_array(|r| r.read_u8().map(Device::from))?,
+        },
+        // The ammo rack detonated; the vehicle is gone with it
+        ("Vehicle", "showAmmoBayEffect") => Event::ModuleDamaged {
+            vehicle: call.entity_id,
+            module: Module::AmmoBay,
+            state: ModuleState::Destroyed,
+        },
+        // MODULE_DAMAGE: [Target (4)] [? (2)] [Source (4)], as read by the wotreplay-parser
+        // reference; the target comes from the args, the calling entity is not reliably it
+        ("Vehicle", _) if call.subtype == Some("MODULE_DAMAGE") => {
+            let vehicle = args.read_entity_id()?;
+            args.read_u16()?;
+            Event::ModuleHit { vehicle, attacker: args.read_entity_id()? }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+// Track IDs of TRACK_DESTROYED (from the wotreplay-parser reference)
+const NOT_TRACKED: u8 = 0x00;
+const LEFT_TRACK: u8 = 0x1D;
+const RIGHT_TRACK: u8 = 0x1E;
+
+pub(crate) fn decode_property(update: &PropertyUpdate) -> Result<Option<Event>> {
+    // [? (4)] [TrackID (1)]; the reference only reads values of exactly that length
+    if update.entity != "Vehicle" || update.subtype != Some("TRACK_DESTROYED") || update.value.len() != 5 {
+        return Ok(None);
+    }
+    let mut value = ArgReader::new(update.value);
+    value.read_u32()?;
+    let track = match value.read_u8()? {
+        NOT_TRACKED => None,
+        LEFT_TRACK => Some(Module::LeftTrack),
+        RIGHT_TRACK => Some(Module::RightTrack),
+        _ => return Ok(None),
+    };
+    Ok(Some(Event::DestroyedTrack { vehicle: update.entity_id, track }))
+}
+
+/// Turns the damaged-device snapshots of one replay into `ModuleDamaged` and
+/// `CrewInjured` events, one per state change, in stream order.
+///
+/// Each snapshot is the complete list for that vehicle, so a device missing
+/// from a later snapshot has been repaired (or the crew member healed).
+/// `DestroyedTrack` updates are snapshots of the two tracks only.
+pub fn device_changes(log: &EventLog) -> Vec<TimedEvent> {
+    let mut known: HashMap<u32, HashMap<Device, ModuleState>> = HashMap::new();
+    let mut changes = Vec::new();
+
+    for TimedEvent { time, event } in &log.events {
+        let (vehicle, now) = match event {
+            Event::DamagedDevices { vehicle, damaged, destroyed } => {
+                let now: HashMap<Device, ModuleState> = damaged
+                    .iter()
+                    .map(|d| (*d, ModuleState::Damaged))
+                    .chain(destroyed.iter().map(|d| (*d, ModuleState::Destroyed)))
+                    .collect();
+                (vehicle, now)
+            }
+            Event::DestroyedTrack { vehicle, track } => {
+                let mut now = known.get(vehicle).cloned().unwrap_or_default();
+                now.retain(|device, _| !matches!(device, Device::Module(Module::LeftTrack | Module::RightTrack)));
+                now.extend(track.map(|track| (Device::Module(track), ModuleState::Destroyed)));
+                (vehicle, now)
+            }
+            _ => continue,
+        };
+        let before = known.remove(vehicle).unwrap_or_default();
+
+        let devices: BTreeSet<Device> = before.keys().chain(now.keys()).copied().collect();
+        for device in devices {
+            let state = match (before.get(&device), now.get(&device)) {
+                (old, Some(new)) if old != Some(new) => *new,
+                (Some(_), None) => ModuleState::Repaired,
+                _ => continue,
+            };
+            let event = match device {
+                Device::Module(module) => Event::ModuleDamaged { vehicle: *vehicle, module, state },
+                Device::Crew(crew) if state != ModuleState::Repaired => Event::CrewInjured { vehicle: *vehicle, crew },
+                _ => continue,
+            };
+            changes.push(TimedEvent { time: *time, event });
+        }
+        known.insert(*vehicle, now);
+    }
+    changes
+}
+
+/// Periods each module spent destroyed, from `ModuleDamaged` events such as the
+/// output of `device_changes`. Modules still destroyed at the end are closed at `end_time`.
+pub fn destroyed_intervals(events: &[TimedEvent], end_time: f32) -> Vec<DestroyedInterval> {
+    let mut open: HashMap<(u32, Module), f32> = HashMap::new();
+    let mut intervals = Vec::new();
+
+    for TimedEvent { time, event } in events {
+        let Event::ModuleDamaged { vehicle, module, state } = event else { continue };
+        let key = (*vehicle, *module);
+        match state {
+            ModuleState::Destroyed => {
+                open.entry(key).or_insert(*time);
+            }
+            ModuleState::Damaged | ModuleState::Repaired => {
+                if let Some(from) = open.remove(&key) {
+                    intervals.push(DestroyedInterval { vehicle: *vehicle, module: *module, from, to: *time });
+                }
+            }
+        }
+    }
+    for ((vehicle, module), from) in open {
+        intervals.push(DestroyedInterval { vehicle, module, from, to: end_time });
+    }
+
+    intervals.sort_by(|a, b| a.from.total_cmp(&b.from).then(a.vehicle.cmp(&b.vehicle)));
+    intervals
+}
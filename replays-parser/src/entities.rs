@@ -0,0 +1,185 @@
+use crate::definitions::Definitions;
+use crate::packet_stream::Packet;
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+
+// Packet types carrying entity traffic
+pub const BASE_PLAYER_CREATE: u32 = 0x00;
+pub const ENTITY_LEAVE: u32 = 0x04;
+pub const ENTITY_CREATE: u32 = 0x05;
+pub const ENTITY_PROPERTY_UPDATE: u32 = 0x07;
+pub const ENTITY_METHOD_CALL: u32 = 0x08;
+pub const ENTITY_MOVE: u32 = 0x0A;
+
+/// A client method call (0x08) resolved against the entity definitions.
+#[derive(Debug, Clone, Copy)]
+pub struct MethodCall<'a> {
+    pub time: f32,
+    pub entity_id: u32,
+    pub entity: &'a str,
+    pub method: &'a str,
+    /// Name the `packetTypes` table gives the raw method ID (MODULE_DAMAGE, ...), whatever the entity calls it.
+    pub subtype: Option<&'a str>,
+    pub args: &'a [u8],
+}
+
+/// A property update (0x07) resolved against the entity definitions.
+#[derive(Debug, Clone, Copy)]
+pub struct PropertyUpdate<'a> {
+    pub time: f32,
+    pub entity_id: u32,
+    pub entity: &'a str,
+    pub property: &'a str,
+    /// Name the `packetTypes` table gives the raw property ID (TRACK_DESTROYED, ...), whatever the entity calls it.
+    pub subtype: Option<&'a str>,
+    pub value: &'a [u8],
+}
+
+/// An entity entering (ENTITY_CREATE) or leaving (ENTITY_LEAVE) the recording client's area of interest.
+#[derive(Debug, Clone, Copy)]
+pub struct EntityPresence<'a> {
+    pub time: f32,
+    pub entity_id: u32,
+    pub entity: &'a str,
+}
+
+/// A position update (ENTITY_MOVE) of an entity in the recording client's area of interest.
+#[derive(Debug, Clone, Copy)]
+pub struct EntityMove<'a> {
+    pub time: f32,
+    pub entity_id: u32,
+    pub entity: &'a str,
+    pub position: [f32; 3],
+    /// Yaw, pitch, roll in radians.
+    pub rotation: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EntityMessage<'a> {
+    Method(MethodCall<'a>),
+    Property(PropertyUpdate<'a>),
+    Entered(EntityPresence<'a>),
+    Left(EntityPresence<'a>),
+    Moved(EntityMove<'a>),
+}
+
+/// Tracks which entity type every entity ID belongs to, so that method and
+/// property IDs in later packets can be resolved to names.
+///
+/// Entity types are announced by BASE_PLAYER_CREATE (the recording player's
+/// Avatar) and ENTITY_CREATE, both starting with `[EntityID (4)] [TypeID (2)]`.
+/// ENTITY_CREATE is sent every time an entity enters the client's view.
+pub struct EntityTracker<'d> {
+    defs: &'d Definitions,
+    types: HashMap<u32, u16>,
+    player_id: Option<u32>,
+}
+
+impl<'d> EntityTracker<'d> {
+    pub fn new(defs: &'d Definitions) -> Self {
+        Self {
+            defs,
+            types: HashMap::new(),
+            player_id: None,
+        }
+    }
+
+    /// Entity ID of the recording player's Avatar, once it has been created.
+    pub fn player_id(&self) -> Option<u32> {
+        self.player_id
+    }
+
+    pub fn entity_type(&self, entity_id: u32) -> Option<&'d str> {
+        let type_id = self.types.get(&entity_id)?;
+        self.defs.entity_by_type(*type_id).map(|e| e.name.as_str())
+    }
+
+    /// Feeds a packet through the tracker. Returns the resolved message for
+    /// method calls, property updates and view changes of known entities.
+    pub fn handle<'a>(&mut self, packet: &'a Packet) -> Option<EntityMessage<'a>>
+    where
+        'd: 'a,
+    {
+        let payload = &packet.payload;
+        match packet.packet_type {
+            BASE_PLAYER_CREATE | ENTITY_CREATE if payload.len() >= 6 => {
+                let entity_id = LittleEndian::read_u32(&payload[0..4]);
+                let type_id = LittleEndian::read_u16(&payload[4..6]);
+                self.types.insert(entity_id, type_id);
+                if packet.packet_type == BASE_PLAYER_CREATE {
+                    self.player_id = Some(entity_id);
+                    return None;
+                }
+                let entity_def = self.defs.entity_by_type(type_id)?;
+                Some(EntityMessage::Entered(EntityPresence {
+                    time: packet.time,
+                    entity_id,
+                    entity: &entity_def.name,
+                }))
+            }
+            // Structure: [EntityID (4)]. The type stays known in case the entity re-enters.
+            ENTITY_LEAVE if payload.len() >= 4 => {
+                let entity_id = LittleEndian::read_u32(&payload[0..4]);
+                let entity_def = self.defs.entity_by_type(*self.types.get(&entity_id)?)?;
+                Some(EntityMessage::Left(EntityPresence {
+                    time: packet.time,
+                    entity_id,
+                    entity: &entity_def.name,
+                }))
+            }
+            // Structure: [EntityID (4)] [MessageID (4)] [Length (4)] [Data]
+            ENTITY_METHOD_CALL | ENTITY_PROPERTY_UPDATE if payload.len() >= 12 => {
+                let entity_id = LittleEndian::read_u32(&payload[0..4]);
+                let message_id = LittleEndian::read_u32(&payload[4..8]);
+                let length = LittleEndian::read_u32(&payload[8..12]) as usize;
+                let data = payload.get(12..12 + length)?;
+
+                // The packet subtypes (MODULE_DAMAGE, TRACK_DESTROYED, ...) go by the raw IDs,
+                // even where the entity definitions name the same ID differently
+                let subtype = self.defs.subtype(packet.packet_type, message_id);
+                let entity_def = self.defs.entity_by_type(*self.types.get(&entity_id)?)?;
+                if packet.packet_type == ENTITY_METHOD_CALL {
+                    let method = entity_def.client_method(message_id).map(|m| m.name.as_str()).or(subtype)?;
+                    Some(EntityMessage::Method(MethodCall {
+                        time: packet.time,
+                        entity_id,
+                        entity: &entity_def.name,
+                        method,
+                        subtype,
+                        args: data,
+                    }))
+                } else {
+                    let property = entity_def.property(message_id).map(|p| p.name.as_str()).or(subtype)?;
+                    Some(EntityMessage::Property(PropertyUpdate {
+                        time: packet.time,
+                        entity_id,
+                        entity: &entity_def.name,
+                        property,
+                        subtype,
+                        value: data,
+                    }))
+                }
+            }
+            // Structure: [EntityID (4)] [SpaceID (4)] [Position (12)] [PositionError (12)] [Rotation (12)] [IsError (1)]
+            ENTITY_MOVE if payload.len() >= 44 => {
+                let entity_id = LittleEndian::read_u32(&payload[0..4]);
+                let entity_def = self.defs.entity_by_type(*self.types.get(&entity_id)?)?;
+                let vec3 = |offset: usize| {
+                    [
+                        LittleEndian::read_f32(&payload[offset..]),
+                        LittleEndian::read_f32(&payload[offset + 4..]),
+                        LittleEndian::read_f32(&payload[offset + 8..]),
+                    ]
+                };
+                Some(EntityMessage::Moved(EntityMove {
+                    time: packet.time,
+                    entity_id,
+                    entity: &entity_def.name,
+                    position: vec3(8),
+                    rotation: vec3(32),
+                }))
+            }
+            _ => None,
+        }
+    }
+}
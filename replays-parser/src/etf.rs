@@ -0,0 +1,545 @@
+//! Erlang External Term Format encoding for `--format etf`.
+//!
+//! Values map onto terms the way Elixir code expects them:
+//!
+//! * structs (including `#[serde(flatten)]`ed ones) become maps with atom keys;
+//!   maps that hold data (battle results, the vehicle roster) keep binary keys, so
+//!   replay contents can never fill the BEAM's atom table
+//! * strings become binaries, `None` and `()` the atom `nil`, `bool` the atoms
+//!   `true` / `false`
+//! * sequences become lists, tuples tuples, unit variants binaries (as in JSON)
+//! * non-finite floats become `nil`, which `binary_to_term/1` would otherwise reject
+//!
+//! `decode` reads the subset of the format written here back into a `serde_json::Value`.
+
+use serde::ser::{self, Serialize};
+use std::fmt;
+
+const VERSION: u8 = 131;
+const NEW_FLOAT_EXT: u8 = 70;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct Error(String);
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Appends `value` to `out` as one versioned term, ready for `binary_to_term/1`.
+pub fn encode<T: Serialize + ?Sized>(out: &mut Vec<u8>, value: &T) -> Result<()> {
+    out.push(VERSION);
+    value.serialize(&mut Serializer { out, atom_strings: false })
+}
+
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    encode(&mut out, value)?;
+    Ok(out)
+}
+
+struct Serializer<'a> {
+    out: &'a mut Vec<u8>,
+    /// Set while writing the key of a struct-like map: strings become atoms.
+    atom_strings: bool,
+}
+
+impl Serializer<'_> {
+    /// Callers keep `name` within the 255 characters the BEAM allows for an atom.
+    fn atom(&mut self, name: &str) {
+        match u8::try_from(name.len()) {
+            Ok(len) => {
+                self.out.push(SMALL_ATOM_UTF8_EXT);
+                self.out.push(len);
+            }
+            Err(_) => {
+                self.out.push(ATOM_UTF8_EXT);
+                self.out.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            }
+        }
+        self.out.extend_from_slice(name.as_bytes());
+    }
+
+    fn binary(&mut self, bytes: &[u8]) -> Result<()> {
+        let len = u32::try_from(bytes.len()).map_err(|_| Error("Binary larger than 4 GiB".to_string()))?;
+        self.out.push(BINARY_EXT);
+        self.out.extend_from_slice(&len.to_be_bytes());
+        self.out.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn integer(&mut self, negative: bool, magnitude: u64) {
+        if !negative && magnitude <= u8::MAX as u64 {
+            self.out.push(SMALL_INTEGER_EXT);
+            self.out.push(magnitude as u8);
+        } else if magnitude <= i32::MAX as u64 || (negative && magnitude == 1 << 31) {
+            let value = if negative { (magnitude as i64).wrapping_neg() } else { magnitude as i64 };
+            self.out.push(INTEGER_EXT);
+            self.out.extend_from_slice(&(value as i32).to_be_bytes());
+        } else {
+            let digits: Vec<u8> = magnitude.to_le_bytes().into_iter().rev().skip_while(|&b| b == 0).collect();
+            self.out.push(SMALL_BIG_EXT);
+            self.out.push(digits.len() as u8);
+            self.out.push(negative as u8);
+            self.out.extend(digits.into_iter().rev());
+        }
+    }
+
+    /// Starts a list, tuple or map whose element count is patched in by `Compound::end`.
+    fn compound(&mut self, kind: Kind) -> Compound<'_> {
+        let start = self.out.len();
+        match kind {
+            Kind::List => self.out.push(LIST_EXT),
+            Kind::Map { .. } => self.out.push(MAP_EXT),
+            Kind::Tuple => self.out.push(LARGE_TUPLE_EXT),
+        }
+        self.out.extend_from_slice(&[0; 4]);
+        Compound { ser: Serializer { out: self.out, atom_strings: false }, kind, start, count: 0 }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    List,
+    Tuple,
+    Map { atom_keys: bool },
+}
+
+#[doc(hidden)]
+pub struct Compound<'a> {
+    ser: Serializer<'a>,
+    kind: Kind,
+    start: usize,
+    count: u32,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.count += 1;
+        value.serialize(&mut self.ser)
+    }
+
+    fn key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.count += 1;
+        self.ser.atom_strings = matches!(self.kind, Kind::Map { atom_keys: true });
+        let result = key.serialize(&mut self.ser);
+        self.ser.atom_strings = false;
+        result
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<()> {
+        self.count += 1;
+        self.ser.atom(name);
+        value.serialize(&mut self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        let Compound { ser, kind, start, count } = self;
+        match kind {
+            Kind::List if count == 0 => {
+                ser.out.truncate(start);
+                ser.out.push(NIL_EXT);
+            }
+            Kind::List => {
+                ser.out[start + 1..start + 5].copy_from_slice(&count.to_be_bytes());
+                ser.out.push(NIL_EXT);
+            }
+            Kind::Tuple if count <= u8::MAX as u32 => {
+                // Shrink the header to the small form: tag + one-byte arity
+                ser.out[start] = SMALL_TUPLE_EXT;
+                ser.out[start + 1] = count as u8;
+                ser.out.drain(start + 2..start + 5);
+            }
+            Kind::Tuple | Kind::Map { .. } => {
+                ser.out[start + 1..start + 5].copy_from_slice(&count.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> ser::Serializer for &'a mut Serializer<'b> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = VariantCompound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = VariantCompound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.atom(if v { "true" } else { "false" });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.integer(v < 0, v.unsigned_abs());
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.integer(false, v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        // Widen through the shortest decimal form, so 0.1f32 arrives as 0.1 as in JSON
+        self.serialize_f64(v.to_string().parse().unwrap_or(f64::NAN))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        if !v.is_finite() {
+            return self.serialize_unit();
+        }
+        self.out.push(NEW_FLOAT_EXT);
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        if self.atom_strings && v.chars().count() <= 255 {
+            self.atom(v);
+            Ok(())
+        } else {
+            self.binary(v.as_bytes())
+        }
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.binary(v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.atom("nil");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let mut map = self.compound(Kind::Map { atom_keys: true });
+        map.field(variant, value)?;
+        map.end()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.compound(Kind::List))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound(Kind::Tuple))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound(Kind::Tuple))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<VariantCompound<'a>> {
+        VariantCompound::start(self, variant, Kind::List)
+    }
+
+    // serde only opens maps without a length for `#[serde(flatten)]`, i.e. for
+    // structs: their keys are field names. Maps of data always know their length.
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.compound(Kind::Map { atom_keys: len.is_none() }))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound(Kind::Map { atom_keys: true }))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<VariantCompound<'a>> {
+        VariantCompound::start(self, variant, Kind::Map { atom_keys: true })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+/// An externally tagged tuple or struct variant: `%{variant: [...]}` or `%{variant: %{...}}`.
+#[doc(hidden)]
+pub struct VariantCompound<'a> {
+    inner: Compound<'a>,
+}
+
+impl<'a> VariantCompound<'a> {
+    fn start(ser: &'a mut Serializer<'_>, variant: &'static str, kind: Kind) -> Result<Self> {
+        ser.out.push(MAP_EXT);
+        ser.out.extend_from_slice(&1u32.to_be_bytes());
+        ser.atom(variant);
+        Ok(Self { inner: ser.compound(kind) })
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantCompound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.inner.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.inner.end()
+    }
+}
+
+impl ser::SerializeStructVariant for VariantCompound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.inner.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.inner.end()
+    }
+}
+
+/// Decodes one versioned term written by `encode` into JSON: atoms become strings
+/// (`nil`, `true` and `false` become null and booleans), tuples arrays.
+pub fn decode(bytes: &[u8]) -> anyhow::Result<serde_json::Value> {
+    let mut reader = Reader { bytes, pos: 0 };
+    anyhow::ensure!(reader.u8()? == VERSION, "Not an external term (missing version byte)");
+    let value = reader.term()?;
+    anyhow::ensure!(reader.pos == bytes.len(), "Trailing bytes after term");
+    Ok(value)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| anyhow::anyhow!("Truncated term"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn text(&mut self, len: usize) -> anyhow::Result<String> {
+        Ok(std::str::from_utf8(self.take(len)?)?.to_string())
+    }
+
+    fn items(&mut self, count: usize) -> anyhow::Result<Vec<serde_json::Value>> {
+        (0..count).map(|_| self.term()).collect()
+    }
+
+    fn term(&mut self) -> anyhow::Result<serde_json::Value> {
+        use serde_json::Value;
+
+        let tag = self.u8()?;
+        Ok(match tag {
+            SMALL_INTEGER_EXT => Value::from(self.u8()?),
+            INTEGER_EXT => Value::from(self.u32()? as i32),
+            SMALL_BIG_EXT => {
+                let len = self.u8()? as usize;
+                let negative = self.u8()? != 0;
+                anyhow::ensure!(len <= 8, "Integer wider than 64 bits");
+                let magnitude = self.take(len)?.iter().rev().fold(0u64, |acc, &b| acc << 8 | b as u64);
+                if negative { Value::from((magnitude as i64).wrapping_neg()) } else { Value::from(magnitude) }
+            }
+            NEW_FLOAT_EXT => Value::from(f64::from_be_bytes(self.take(8)?.try_into()?)),
+            SMALL_ATOM_UTF8_EXT | ATOM_UTF8_EXT => {
+                let len = if tag == SMALL_ATOM_UTF8_EXT { self.u8()? as usize } else { u16::from_be_bytes(self.take(2)?.try_into()?) as usize };
+                match self.text(len)?.as_str() {
+                    "nil" => Value::Null,
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    atom => Value::from(atom),
+                }
+            }
+            BINARY_EXT => {
+                let len = self.u32()? as usize;
+                Value::from(self.text(len)?)
+            }
+            NIL_EXT => Value::Array(Vec::new()),
+            LIST_EXT => {
+                let len = self.u32()? as usize;
+                let items = self.items(len)?;
+                anyhow::ensure!(self.u8()? == NIL_EXT, "Improper list");
+                Value::Array(items)
+            }
+            SMALL_TUPLE_EXT => {
+                let arity = self.u8()? as usize;
+                Value::Array(self.items(arity)?)
+            }
+            LARGE_TUPLE_EXT => {
+                let arity = self.u32()? as usize;
+                Value::Array(self.items(arity)?)
+            }
+            MAP_EXT => {
+                let arity = self.u32()?;
+                let mut map = serde_json::Map::new();
+                for _ in 0..arity {
+                    let key = match self.term()? {
+                        Value::String(key) => key,
+                        other => other.to_string(),
+                    };
+                    map.insert(key, self.term()?);
+                }
+                Value::Object(map)
+            }
+            other => anyhow::bail!("Unsupported term tag {}", other),
+        })
+    }
+}
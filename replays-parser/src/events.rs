@@ -0,0 +1,207 @@
+use crate::artillery::{self, ArtyShot, StrikeKind, StunInterval};
+use crate::arena::{self, ArenaVehicle, DeathReason, Period, VehicleFrags, VehicleListChange};
+use crate::battle_events::{self, BattleEvent, BattleEventsSummary};
+use crate::definitions::Definitions;
+use crate::devices::{self, CrewRole, Device, Module, ModuleState};
+use crate::entities::{EntityMessage, EntityTracker, MethodCall, PropertyUpdate};
+use crate::own_vehicle::{self, ShellSlot, TargetingInfo};
+use crate::packet_filter::PacketFilter;
+use crate::packet_stream::PacketStream;
+use crate::positions::{self, MinimapEntry, PositionSample};
+use crate::shots::{self, ShotHit};
+use crate::spotting::{self, SpottedInterval};
+use crate::types::Replay;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Cursor;
+
+/// A decoded gameplay event with the replay time (seconds) it occurred at.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimedEvent {
+    pub time: f32,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Typed events decoded from entity method calls and property updates.
+/// Vehicle and entity references are entity IDs (keys of `BattleConfig.vehicles`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Vehicle.showShooting: the gun of `shooter` fired.
+    ShotFired { shooter: u32, burst_count: u8, gun_index: u8 },
+    /// Avatar.showTracer: a projectile became visible to the recording client.
+    TracerStarted {
+        shooter: u32,
+        shot_id: i32,
+        is_ricochet: bool,
+        start: [f32; 3],
+        velocity: [f32; 3],
+    },
+    /// Avatar.stopTracer: the projectile hit something or left the simulation.
+    TracerStopped { shot_id: i32, end_point: [f32; 3] },
+    /// Avatar.explodeProjectile: the projectile detonated (HE / ground hit).
+    ProjectileExploded { shot_id: i32, end_point: [f32; 3] },
+    /// Avatar.showShotResults: server verdict for the recording player's own shots.
+    ShotResults { results: Vec<ShotHit> },
+    /// Vehicle.showDamageFromShot: `target` was hit by a shell from `attacker`.
+    DamageFromShot { target: u32, attacker: u32, damage_factor: u8 },
+    /// A vehicle entered the recording client's view (ENTITY_CREATE).
+    VehicleEntered { vehicle: u32 },
+    /// A vehicle left the recording client's view (ENTITY_LEAVE).
+    VehicleLeft { vehicle: u32 },
+    /// Vehicle.detectedVehicles: the full set of vehicles `vehicle` currently detects.
+    DetectedVehicles { vehicle: u32, detected: Vec<u32> },
+    /// Vehicle.isObservedByEnemy changed.
+    ObservedByEnemy { vehicle: u32, observed: bool },
+    /// Vehicle.sixthSenseState changed (the "you have been spotted" lamp).
+    SixthSense { vehicle: u32, active: bool },
+    /// Derived from the events above by `spotting::spotted_intervals`, never decoded directly.
+    SpottedInterval(SpottedInterval),
+    /// Avatar.updateArena VEHICLE_KILLED: the kill feed.
+    VehicleKilled { victim: u32, killer: u32, reason: DeathReason },
+    /// Avatar.updateArena PERIOD: battle phase change. `end_time` is server time.
+    ArenaPeriod { period: Period, end_time: f64, length: f64 },
+    /// Vehicle.arenaUniqueID / Avatar.arenaUniqueID: the server's ID of the battle.
+    ArenaUniqueId { arena_unique_id: u64 },
+    /// Avatar.updateArena BASE_POINTS: capture progress of `team` on `base_id`.
+    BasePoints { team: u8, base_id: u32, points: u32, invaders: u32, capturing_stopped: bool },
+    /// Avatar.updateArena BASE_CAPTURED.
+    BaseCaptured { team: u8, base_id: u32 },
+    /// Avatar.updateArena STATISTICS / VEHICLE_STATISTICS: frag counts.
+    VehicleStatistics { stats: Vec<VehicleFrags> },
+    /// Avatar.updateArena VEHICLE_LIST / VEHICLE_ADDED / VEHICLE_UPDATED: roster changes.
+    VehicleListUpdate { change: VehicleListChange, vehicles: Vec<ArenaVehicle> },
+    /// Avatar.onBattleEvents: ribbons and damage log entries of the recording player.
+    BattleEvents { events: Vec<BattleEvent> },
+    /// Avatar.battleEventsSummary: the player's running damage / assist / blocked totals.
+    BattleEventsSummary(BattleEventsSummary),
+    /// Avatar.updatePositions: raw minimap markers, resolved by `positions::minimap_positions`.
+    MinimapPositions { entries: Vec<MinimapEntry> },
+    /// A vehicle position, from ENTITY_MOVE or (derived) from the minimap; see `PositionSample::source`.
+    Position(PositionSample),
+    /// Avatar.updateVehicleAmmo: shells of type `shell` (compact descriptor) left on the player's vehicle.
+    VehicleAmmo { vehicle: u32, shell: u32, quantity: u16, quantity_in_clip: u8 },
+    /// Avatar.updateVehicleSetting CURRENT_SHELLS / NEXT_SHELLS.
+    ShellSelected { vehicle: u32, slot: ShellSlot, shell: u32 },
+    /// Avatar.updateVehicleHealth: the player's vehicle health as shown in the HUD.
+    VehicleHealth { vehicle: u32, health: i16, death_reason: Option<DeathReason>, is_crew_active: bool },
+    /// Avatar.updateTargetingInfo: gun angles and dispersion factors of the player's vehicle.
+    TargetingInfo(TargetingInfo),
+    /// Avatar.updateTargetVehicleID: auto-aim lock changes.
+    TargetLock { target: Option<u32> },
+    /// Avatar.updateAvatarPrivateStats: unpickled stats dict.
+    PrivateStats { stats: serde_json::Value },
+    /// Avatar.updateVehicleQuickShellChanger.
+    QuickShellChanger { vehicle: u32, active: bool },
+    /// Avatar.showOtherVehicleDamagedDevices: the full damaged / destroyed device lists of `vehicle`.
+    DamagedDevices { vehicle: u32, damaged: Vec<Device>, destroyed: Vec<Device> },
+    /// A module changed state; derived by `devices::device_changes`, or decoded from
+    /// Vehicle.showAmmoBayEffect.
+    ModuleDamaged { vehicle: u32, module: Module, state: ModuleState },
+    /// A crew member of `vehicle` was injured; derived by `devices::device_changes`.
+    CrewInjured { vehicle: u32, crew: CrewRole },
+    /// TRACK_DESTROYED: the track of `vehicle` still destroyed after a repair, `None` once neither is.
+    DestroyedTrack { vehicle: u32, track: Option<Module> },
+    /// MODULE_DAMAGE: `attacker` damaged a module of `vehicle`; the packet does not say which.
+    ModuleHit { vehicle: u32, attacker: u32 },
+    /// Vehicle.showDamageFromExplosion: `target` took splash damage from a shell of `attacker`.
+    DamageFromExplosion { target: u32, attacker: u32, center: [f32; 3], damage_factor: u8 },
+    /// Vehicle.stunInfo changed; `finish_time` is server time, 0 when not stunned.
+    StunInfo { vehicle: u32, finish_time: f64, duration: Option<f32> },
+    /// Avatar.enemySPGHit: an enemy artillery shell landed near the recording player.
+    EnemySpgHit { hit_point: [f32; 3] },
+    /// Avatar.enemySPGShotSound: an enemy artillery piece fired at the recording player's area.
+    EnemySpgShot { shooter_position: [f32; 3], target_position: [f32; 3] },
+    /// Avatar.showHittingArea / showCarpetBombing, or an AttackArtilleryFort / AttackBomber entering view.
+    StrikeArea { kind: StrikeKind, equipment_id: Option<u16>, position: Option<[f32; 3]> },
+    /// Derived by `artillery::stun_intervals`, never decoded directly.
+    StunInterval(StunInterval),
+    /// Derived by `artillery::arty_shots`, never decoded directly.
+    ArtyShot(ArtyShot),
+}
+
+type MethodDecoder = fn(&MethodCall) -> Result<Option<Event>>;
+
+type PropertyDecoder = fn(&PropertyUpdate) -> Result<Option<Event>>;
+
+// Each decoder only recognizes its own entity/method pairs
+const METHOD_DECODERS: &[MethodDecoder] = &[shots::decode, arena::decode, battle_events::decode, positions::decode, own_vehicle::decode, devices::decode, artillery::decode];
+const PROPERTY_DECODERS: &[PropertyDecoder] = &[spotting::decode_property, artillery::decode_property, arena::decode_property, devices::decode_property];
+
+/// All events decoded from one replay.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    pub events: Vec<TimedEvent>,
+    /// Time of the last packet, i.e. the length of the recording.
+    pub end_time: f32,
+}
+
+impl EventLog {
+    /// Replay time the battle period started at, or 0 for replays recorded mid-battle.
+    pub fn battle_start(&self) -> f32 {
+        self.events
+            .iter()
+            .find(|e| matches!(e.event, Event::ArenaPeriod { period: Period::Battle, .. }))
+            .map_or(0.0, |e| e.time)
+    }
+}
+
+/// Decodes a single resolved entity message into a typed event.
+/// Returns `Ok(None)` for messages no decoder handles.
+pub fn decode_message(message: &EntityMessage) -> Result<Option<Event>> {
+    match message {
+        EntityMessage::Method(call) => {
+            for decode in METHOD_DECODERS {
+                if let Some(event) = decode(call)? {
+                    return Ok(Some(event));
+                }
+            }
+            Ok(None)
+        }
+        EntityMessage::Property(update) => {
+            for decode in PROPERTY_DECODERS {
+                if let Some(event) = decode(update)? {
+                    return Ok(Some(event));
+                }
+            }
+            Ok(None)
+        }
+        EntityMessage::Entered(_) | EntityMessage::Left(_) => {
+            Ok(spotting::decode_presence(message).or_else(|| artillery::decode_presence(message)))
+        }
+        EntityMessage::Moved(movement) => Ok(positions::decode_move(movement)),
+    }
+}
+
+/// Runs every decoder over the replay's packet stream.
+///
+/// Decoding stops quietly at the first packet stream error (usually a truncated
+/// replay), and malformed method arguments are skipped, so an incomplete replay
+/// still yields everything up to that point.
+pub fn decode_events(replay: &Replay, defs: &Definitions) -> EventLog {
+    decode_events_filtered(replay, defs, &PacketFilter::default())
+}
+
+/// `decode_events` over only the packets passing `filter`, so every decoder (and
+/// what is derived from the log) sees just those. `end_time` still covers the whole stream.
+pub fn decode_events_filtered(replay: &Replay, defs: &Definitions, filter: &PacketFilter) -> EventLog {
+    let mut cursor = Cursor::new(replay.packets_buffer.clone());
+    let mut packet_stream = PacketStream::with_filter(&mut cursor, filter);
+    let mut tracker = EntityTracker::new(defs);
+    let mut log = EventLog::default();
+
+    for packet in packet_stream.by_ref() {
+        let Ok(packet) = packet else { break };
+        let message = tracker.handle(&packet);
+        if let Some(message) = message
+            && filter.matches(&packet, Some(&message))
+            && let Ok(Some(event)) = decode_message(&message)
+        {
+            log.events.push(TimedEvent { time: packet.time, event });
+        }
+    }
+    log.end_time = packet_stream.end_time();
+
+    log
+}
@@ -0,0 +1,118 @@
+//! Length-delimited binary records for `--format msgpack|cbor|etf`.
+//!
+//! A stream is a sequence of frames, each a 4-byte big-endian payload length
+//! followed by one MessagePack map, CBOR map or Erlang term (see `etf`). The
+//! maps are the records of the JSON lines output (`{"type": ..., "replay": ...,
+//! <fields>}`), so consumers can switch encodings without changing how they read
+//! records. The prefix matches Erlang's `{packet, 4}` port option.
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
+
+/// Frames larger than this are rejected when reading: a corrupt length
+/// should not make the reader allocate gigabytes.
+pub const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    MessagePack,
+    Cbor,
+    /// Erlang External Term Format, for `:erlang.binary_to_term/1`.
+    Etf,
+}
+
+/// Encodes `value` as one frame and appends it to `out`.
+///
+/// MessagePack structs are written as maps with field names, never as arrays.
+pub fn encode_frame<T: Serialize>(out: &mut Vec<u8>, format: FrameFormat, value: &T) -> Result<()> {
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    match format {
+        FrameFormat::MessagePack => rmp_serde::encode::write_named(out, value)?,
+        FrameFormat::Cbor => ciborium::ser::into_writer(value, &mut *out)?,
+        FrameFormat::Etf => crate::etf::encode(out, value)?,
+    }
+    let len = u32::try_from(out.len() - start - 4).context("Frame larger than 4 GiB")?;
+    out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+/// Encodes `value` as one frame and writes it to `writer`.
+pub fn write_frame<W: Write, T: Serialize>(writer: &mut W, format: FrameFormat, value: &T) -> Result<()> {
+    let mut buf = Vec::new();
+    encode_frame(&mut buf, format, value)?;
+    writer.write_all(&buf)?;
+    Ok(())
+}
+
+/// Writes an already encoded payload (from `FrameReader::read_payload`) as one frame.
+pub fn write_payload<W: Write>(writer: &mut W, payload: &[u8]) -> Result<()> {
+    let len = u32::try_from(payload.len()).context("Frame larger than 4 GiB")?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(payload)?;
+    Ok(())
+}
+
+/// Decodes one frame's payload into `T`.
+pub fn decode_payload<T: DeserializeOwned>(payload: &[u8], format: FrameFormat) -> Result<T> {
+    Ok(match format {
+        FrameFormat::MessagePack => rmp_serde::from_slice(payload)?,
+        FrameFormat::Cbor => ciborium::de::from_reader(payload)?,
+        FrameFormat::Etf => serde_json::from_value(crate::etf::decode(payload)?)?,
+    })
+}
+
+/// Reads frames back from a stream written by `--format msgpack|cbor`.
+///
+/// As an iterator it yields each record as a `serde_json::Value`, the same
+/// value the JSON line would parse to.
+pub struct FrameReader<R: Read> {
+    reader: R,
+    format: FrameFormat,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub fn new(reader: R, format: FrameFormat) -> Self {
+        Self { reader, format }
+    }
+
+    /// Next frame's payload, or `None` at the end of the stream.
+    pub fn read_payload(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut prefix = [0u8; 4];
+        let mut filled = 0;
+        while filled < prefix.len() {
+            match self.reader.read(&mut prefix[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => bail!("Truncated frame length"),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let len = u32::from_be_bytes(prefix);
+        if len > MAX_FRAME_LEN {
+            bail!("Frame of {} bytes exceeds the {} byte limit", len, MAX_FRAME_LEN);
+        }
+        let mut payload = vec![0; len as usize];
+        self.reader.read_exact(&mut payload).context("Truncated frame")?;
+        Ok(Some(payload))
+    }
+
+    /// Decodes the next frame into `T`, or `None` at the end of the stream.
+    pub fn read<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        let Some(payload) = self.read_payload()? else {
+            return Ok(None);
+        };
+        decode_payload(&payload, self.format).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for FrameReader<R> {
+    type Item = Result<serde_json::Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
@@ -0,0 +1,242 @@
+use crate::events::{Event, EventLog, TimedEvent};
+use crate::maps::MapGeometry;
+use crate::positions::minimap_positions;
+use crate::types::BattleConfig;
+use crate::vehicles::{VehicleCatalog, VehicleClass};
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// A position older than this (seconds) no longer says where the vehicle is
+const STALE_POSITION: f32 = 5.0;
+// Output pixels per heatmap cell
+const PNG_CELL_SIZE: usize = 8;
+
+/// Whose positions go into the heatmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Who {
+    /// The recording player's vehicle.
+    Player,
+    /// Every vehicle on the recording player's team.
+    Allies,
+    #[default]
+    All,
+}
+
+/// Battle outcome from the point of view of the sampled vehicle's team.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Loss,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HeatmapFilter {
+    pub who: Who,
+    /// Vehicles of unknown class never match a class filter.
+    pub class: Option<VehicleClass>,
+    /// Team side (1 or 2) of the sampled vehicle.
+    pub team: Option<u8>,
+    /// Needs the battle results; replays without them never match.
+    pub outcome: Option<Outcome>,
+    /// Phase window in seconds since the battle period started.
+    pub from: Option<f32>,
+    pub to: Option<f32>,
+}
+
+/// Time spent per minimap cell, `size` x `size`, row-major with row 0 at the north edge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    pub size: usize,
+    pub cells: Vec<f32>,
+    /// Number of positions accumulated.
+    pub samples: u64,
+}
+
+impl Heatmap {
+    pub fn new(size: usize) -> Result<Self> {
+        if size == 0 {
+            bail!("Heatmap size must be at least 1");
+        }
+        Ok(Self { size, cells: vec![0.0; size * size], samples: 0 })
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.cells[row * self.size + column]
+    }
+
+    pub fn merge(&mut self, other: &Heatmap) {
+        for (cell, value) in self.cells.iter_mut().zip(&other.cells) {
+            *cell += value;
+        }
+        self.samples += other.samples;
+    }
+
+    /// Adds the positions of one replay, sampling every vehicle matching `filter` each
+    /// `interval` seconds at its latest known position (ENTITY_MOVE or minimap).
+    /// Dead vehicles and positions older than `STALE_POSITION` are skipped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn accumulate(
+        &mut self,
+        log: &EventLog,
+        config: &BattleConfig,
+        winner_team: Option<u8>,
+        map: &MapGeometry,
+        vehicles: &VehicleCatalog,
+        filter: &HeatmapFilter,
+        interval: f32,
+    ) {
+        let selected = select_vehicles(config, winner_team, vehicles, filter);
+        if selected.is_empty() || interval <= 0.0 {
+            return;
+        }
+
+        let minimap = minimap_positions(log, config);
+        let mut track: Vec<(f32, u32, f32, f32)> = log
+            .events
+            .iter()
+            .chain(&minimap)
+            .filter_map(|TimedEvent { time, event }| match event {
+                Event::Position(p) if selected.contains(&p.vehicle) => Some((*time, p.vehicle, p.x, p.z)),
+                _ => None,
+            })
+            .collect();
+        track.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let deaths: HashMap<u32, f32> = log
+            .events
+            .iter()
+            .filter_map(|e| match e.event {
+                Event::VehicleKilled { victim, .. } => Some((victim, e.time)),
+                _ => None,
+            })
+            .collect();
+
+        let start = log.battle_start();
+        let from = start + filter.from.unwrap_or(0.0).max(0.0);
+        let to = filter.to.map_or(log.end_time, |to| (start + to).min(log.end_time));
+
+        let mut latest: HashMap<u32, (f32, f32, f32)> = HashMap::new();
+        let mut next = 0;
+        let steps = if to >= from { ((to - from) / interval).floor() as usize + 1 } else { 0 };
+        for step in 0..steps {
+            let time = from + step as f32 * interval;
+            while next < track.len() && track[next].0 <= time {
+                let (t, vehicle, x, z) = track[next];
+                latest.insert(vehicle, (t, x, z));
+                next += 1;
+            }
+            for (vehicle, &(t, x, z)) in &latest {
+                if time - t > STALE_POSITION || deaths.get(vehicle).is_some_and(|&death| death <= time) {
+                    continue;
+                }
+                let position = map.normalize(x, z);
+                let cell = |v: f32| ((v * self.size as f32) as usize).min(self.size - 1);
+                self.cells[cell(position.y) * self.size + cell(position.x)] += interval;
+                self.samples += 1;
+            }
+        }
+    }
+
+    /// Writes the grid as a little-endian float32 NumPy array of shape `(size, size)`.
+    pub fn write_npy(&self, path: &Path) -> Result<()> {
+        let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}", self.size, self.size);
+        // Magic, version and header length take 10 bytes; the header ends in '\n' on a 64-byte boundary
+        let padding = 63 - (10 + header.len()) % 64;
+        header.extend(std::iter::repeat_n(' ', padding));
+        header.push('\n');
+
+        let mut out = BufWriter::new(File::create(path).with_context(|| format!("Failed to create {}", path.display()))?);
+        out.write_all(b"\x93NUMPY\x01\x00")?;
+        out.write_all(&(header.len() as u16).to_le_bytes())?;
+        out.write_all(header.as_bytes())?;
+        for value in &self.cells {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Writes one CSV line per grid row, north first.
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut out = BufWriter::new(File::create(path).with_context(|| format!("Failed to create {}", path.display()))?);
+        for row in self.cells.chunks(self.size) {
+            let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            writeln!(out, "{}", line.join(","))?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Renders the grid as a PNG with the 10x10 minimap squares drawn over it.
+    pub fn write_png(&self, path: &Path) -> Result<()> {
+        if self.size == 0 {
+            bail!("Cannot render an empty heatmap");
+        }
+        let side = self.size * PNG_CELL_SIZE;
+        let max = self.cells.iter().copied().fold(0.0, f32::max);
+        let mut pixels = vec![0u8; side * side * 3];
+        for py in 0..side {
+            for px in 0..side {
+                let value = self.get(py / PNG_CELL_SIZE, px / PNG_CELL_SIZE);
+                let on_square_edge = (px * 10) % side < 10 || (py * 10) % side < 10;
+                let rgb = if on_square_edge {
+                    [96, 96, 96]
+                } else if value > 0.0 {
+                    heat_color(value / max)
+                } else {
+                    [24, 24, 24]
+                };
+                let offset = (py * side + px) * 3;
+                pixels[offset..offset + 3].copy_from_slice(&rgb);
+            }
+        }
+
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), side as u32, side as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+fn select_vehicles(
+    config: &BattleConfig,
+    winner_team: Option<u8>,
+    vehicles: &VehicleCatalog,
+    filter: &HeatmapFilter,
+) -> HashSet<u32> {
+    let player = config.player_vehicle_id();
+    let player_team = player.and_then(|id| config.vehicle(id)).map(|v| v.team);
+
+    config
+        .vehicles
+        .iter()
+        .filter_map(|(id, info)| Some((id.parse::<u32>().ok()?, info)))
+        .filter(|(id, info)| match filter.who {
+            Who::Player => Some(*id) == player,
+            Who::Allies => Some(info.team) == player_team,
+            Who::All => true,
+        })
+        .filter(|(_, info)| filter.class.is_none() || vehicles.class_of(&info.vehicle_type) == filter.class)
+        .filter(|(_, info)| filter.team.is_none_or(|team| team == info.team))
+        .filter(|(_, info)| match (filter.outcome, winner_team) {
+            (None, _) => true,
+            (Some(Outcome::Win), Some(winner)) => winner == info.team,
+            (Some(Outcome::Loss), Some(winner)) => winner != 0 && winner != info.team,
+            (Some(_), None) => false,
+        })
+        .map(|(id, _)| id)
+        .collect()
+}
+
+// Dark red -> red -> yellow -> white; starts above black so rare cells stay visible
+fn heat_color(t: f32) -> [u8; 3] {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0) as u8;
+    let t = (0.2 + 0.8 * t.clamp(0.0, 1.0)) * 3.0;
+    [channel(t), channel(t - 1.0), channel(t - 2.0)]
+}
@@ -0,0 +1,328 @@
+//! Input discovery: replay files, directories walked recursively, `.zip` / `.tar.gz`
+//! archives and stdin.
+//!
+//! Every replay found becomes an `Input` with a path (the file path, or the archive
+//! path joined with the member name: `uploads/2025-02-19.zip/a.wotreplay`) and a
+//! name, the same path relative to the input directory (`2025-02-19.zip/a.wotreplay`),
+//! which keys the replay in output. Zip members are read on demand. A gzip stream
+//! cannot be seeked into, so tar members are extracted to a temporary directory,
+//! removed once the last of its inputs is dropped; stdin is held in memory.
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use memmap2::Mmap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::ZipArchive;
+
+/// Extensions of replay files: World of Tanks and Mir Tankov (Lesta).
+pub const REPLAY_EXTENSIONS: [&str; 2] = ["wotreplay", "mtreplay"];
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Archive bytes shared by all members' readers, mapped from a file or read from stdin.
+#[derive(Clone)]
+struct Shared(Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+impl AsRef<[u8]> for Shared {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
+
+type Archive = ZipArchive<Cursor<Shared>>;
+
+/// Directory the members of one tar archive are extracted to, removed on drop.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn create() -> Result<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("replays-parser-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        std::fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(TempDir(path))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[derive(Clone)]
+enum Source {
+    File,
+    Zip { archive: Archive, index: usize, size: u64, mtime_ns: u64 },
+    Extracted { file: PathBuf, _dir: Arc<TempDir>, mtime_ns: u64 },
+    Memory { bytes: Arc<Vec<u8>>, mtime_ns: u64 },
+}
+
+/// One replay to parse.
+#[derive(Clone)]
+pub struct Input {
+    path: PathBuf,
+    name: PathBuf,
+    source: Source,
+}
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Input").field(&self.path).finish()
+    }
+}
+
+impl Input {
+    /// A replay file on disk, named by its file name.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Input { name: file_name(&path), path, source: Source::File }
+    }
+
+    /// A replay already in memory, e.g. read from stdin (named `-`).
+    pub fn memory(path: impl Into<PathBuf>, bytes: Vec<u8>) -> Self {
+        let path = path.into();
+        Input { name: file_name(&path), path, source: Source::Memory { bytes: Arc::new(bytes), mtime_ns: 0 } }
+    }
+
+    /// Path of the replay in errors and `--state`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Key of the replay in output and manifests: its path relative to the input
+    /// directory, or the file name of a replay named directly.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+
+    /// The file on disk holding the replay: the file itself, or the extracted tar member.
+    fn disk_path(&self) -> &Path {
+        match &self.source {
+            Source::Extracted { file, .. } => file,
+            _ => &self.path,
+        }
+    }
+
+    /// The replay file's contents.
+    pub fn read(&self) -> Result<Vec<u8>> {
+        match &self.source {
+            Source::File | Source::Extracted { .. } => {
+                std::fs::read(self.disk_path()).with_context(|| format!("Failed to read {}", self.path.display()))
+            }
+            Source::Zip { archive, index, .. } => {
+                let mut archive = archive.clone();
+                let mut member = archive.by_index(*index).with_context(|| format!("Failed to open {}", self.path.display()))?;
+                let mut bytes = Vec::new();
+                member.read_to_end(&mut bytes).with_context(|| format!("Failed to extract {}", self.path.display()))?;
+                Ok(bytes)
+            }
+            Source::Memory { bytes, .. } => Ok(bytes.to_vec()),
+        }
+    }
+
+    /// A reader over the replay file, for reading only its start (`Parser::parse_header`).
+    /// Files and in-memory replays are read lazily; zip members are extracted whole.
+    pub fn open(&self) -> Result<Box<dyn Read + Send>> {
+        match &self.source {
+            Source::File | Source::Extracted { .. } => {
+                let file = File::open(self.disk_path()).with_context(|| format!("Failed to open {}", self.path.display()))?;
+                Ok(Box::new(BufReader::new(file)))
+            }
+            Source::Zip { .. } => Ok(Box::new(Cursor::new(self.read()?))),
+            Source::Memory { bytes, .. } => Ok(Box::new(Cursor::new(Shared(bytes.clone())))),
+        }
+    }
+
+    /// Size and modification time in nanoseconds since the Unix epoch. Archive members
+    /// report the archive's modification time; stdin has none (0).
+    pub fn stat(&self) -> Result<(u64, u64)> {
+        match &self.source {
+            Source::File | Source::Extracted { .. } => {
+                let metadata = std::fs::metadata(self.disk_path()).with_context(|| format!("Failed to stat {}", self.path.display()))?;
+                match &self.source {
+                    Source::Extracted { mtime_ns, .. } => Ok((metadata.len(), *mtime_ns)),
+                    _ => Ok((metadata.len(), mtime_ns(metadata.modified()?))),
+                }
+            }
+            Source::Zip { size, mtime_ns, .. } => Ok((*size, *mtime_ns)),
+            Source::Memory { bytes, mtime_ns } => Ok((bytes.len() as u64, *mtime_ns)),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> PathBuf {
+    path.file_name().map_or_else(|| path.to_path_buf(), PathBuf::from)
+}
+
+fn mtime_ns(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// `--include` / `--exclude` globs, matched against paths relative to the input
+/// directory (archive members as `<archive>/<member>`). `*` also matches `/`.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+/// One set matching any of `globs`, or `None` without any.
+pub fn glob_set(globs: &[String]) -> Result<Option<GlobSet>> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+    let mut set = GlobSetBuilder::new();
+    for glob in globs {
+        set.add(Glob::new(glob).with_context(|| format!("Invalid glob '{}'", glob))?);
+    }
+    Ok(Some(set.build()?))
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Filter { include: glob_set(include)?, exclude: glob_set(exclude)? })
+    }
+
+    fn excludes(&self, path: &Path) -> bool {
+        self.exclude.as_ref().is_some_and(|set| set.is_match(path))
+    }
+
+    /// Whether the replay at `path` is taken. Without `--include`, every replay is.
+    fn accepts(&self, path: &Path) -> bool {
+        !self.excludes(path) && self.include.as_ref().is_none_or(|set| set.is_match(path))
+    }
+}
+
+fn is_replay(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| REPLAY_EXTENSIONS.iter().any(|replay| ext.eq_ignore_ascii_case(replay)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    TarGz,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// The replays at `input`: a replay file, an archive, a directory (walked
+/// recursively, archives included) or `-` for stdin. Stdin holds a single replay
+/// or a zip / tar.gz archive, told apart by its first bytes.
+///
+/// A file or archive named directly is taken whatever its extension; inside
+/// directories and archives only `.wotreplay` / `.mtreplay` files are, filtered by
+/// `filter`. Directories are walked in name order; unreadable archives in them are
+/// skipped with a warning.
+pub fn discover(input: &Path, filter: &Filter) -> Result<Vec<Input>> {
+    let mut inputs = Vec::new();
+    if input == Path::new("-") {
+        let mut bytes = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut bytes).context("Failed to read stdin")?;
+        if bytes.starts_with(ZIP_MAGIC) {
+            read_zip(input, Path::new(""), Shared(Arc::new(bytes)), 0, filter, &mut inputs)?;
+        } else if bytes.starts_with(GZIP_MAGIC) {
+            read_tar_gz(input, Path::new(""), bytes.as_slice(), 0, filter, &mut inputs)?;
+        } else {
+            inputs.push(Input::memory(input, bytes));
+        }
+    } else if input.is_dir() {
+        for entry in walkdir::WalkDir::new(input).sort_by_file_name() {
+            let entry = entry.with_context(|| format!("Failed to walk {}", input.display()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(input).unwrap_or(entry.path());
+            match archive_kind(entry.path()) {
+                Some(kind) if !filter.excludes(relative) => {
+                    // One corrupt upload should not stop the walk
+                    if let Err(e) = read_archive(entry.path(), relative, kind, filter, &mut inputs) {
+                        eprintln!("Skipping {}: {:#}", entry.path().display(), e);
+                    }
+                }
+                Some(_) => {}
+                None if is_replay(entry.path()) && filter.accepts(relative) => {
+                    inputs.push(Input { path: entry.path().to_path_buf(), name: relative.to_path_buf(), source: Source::File })
+                }
+                None => {}
+            }
+        }
+    } else {
+        match archive_kind(input) {
+            Some(kind) => read_archive(input, Path::new(""), kind, filter, &mut inputs)?,
+            None => inputs.push(Input::file(input)),
+        }
+    }
+    Ok(inputs)
+}
+
+fn read_archive(path: &Path, relative: &Path, kind: ArchiveKind, filter: &Filter, inputs: &mut Vec<Input>) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mtime_ns = mtime_ns(file.metadata()?.modified()?);
+    match kind {
+        ArchiveKind::Zip => {
+            // SAFETY: archives are not expected to change while the parser runs; a
+            // truncated one makes reads fail rather than return stale members.
+            let map = unsafe { Mmap::map(&file) }.with_context(|| format!("Failed to map {}", path.display()))?;
+            read_zip(path, relative, Shared(Arc::new(map)), mtime_ns, filter, inputs)
+        }
+        ArchiveKind::TarGz => read_tar_gz(path, relative, file, mtime_ns, filter, inputs),
+    }
+}
+
+fn read_zip(path: &Path, relative: &Path, bytes: Shared, mtime_ns: u64, filter: &Filter, inputs: &mut Vec<Input>) -> Result<()> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).with_context(|| format!("Invalid zip archive {}", path.display()))?;
+    for index in 0..archive.len() {
+        let member = archive.by_index_raw(index)?;
+        let (name, is_file, size) = (member.enclosed_name(), member.is_file(), member.size());
+        drop(member);
+        let Some(name) = name else {
+            continue;
+        };
+        if is_file && is_replay(&name) && filter.accepts(&relative.join(&name)) {
+            let source = Source::Zip { archive: archive.clone(), index, size, mtime_ns };
+            inputs.push(Input { path: path.join(&name), name: relative.join(name), source });
+        }
+    }
+    Ok(())
+}
+
+fn read_tar_gz(path: &Path, relative: &Path, reader: impl Read, mtime_ns: u64, filter: &Filter, inputs: &mut Vec<Input>) -> Result<()> {
+    let context = || format!("Invalid tar.gz archive {}", path.display());
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+    let mut dir = None;
+    for (index, entry) in archive.entries().with_context(context)?.enumerate() {
+        let mut entry = entry.with_context(context)?;
+        let name = entry.path().with_context(context)?.into_owned();
+        if !entry.header().entry_type().is_file() || !is_replay(&name) || !filter.accepts(&relative.join(&name)) {
+            continue;
+        }
+        let dir = match &dir {
+            Some(dir) => dir,
+            None => dir.insert(Arc::new(TempDir::create()?)),
+        };
+        // Members are stored by index, so member names never become paths on disk
+        let file = dir.0.join(index.to_string());
+        let mut out = File::create(&file).with_context(|| format!("Failed to create {}", file.display()))?;
+        std::io::copy(&mut entry, &mut out).with_context(context)?;
+        let source = Source::Extracted { file, _dir: dir.clone(), mtime_ns };
+        inputs.push(Input { path: path.join(&name), name: relative.join(name), source });
+    }
+    Ok(())
+}
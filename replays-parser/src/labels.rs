@@ -0,0 +1,78 @@
+use crate::events::{Event, EventLog};
+use crate::types::Replay;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleOutcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// Episode-level labels of one replay, seen from the recording player.
+///
+/// Fields that need the battle results are `None` when the replay has none
+/// (e.g. the client quit before the battle ended); `incomplete` flags those.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EpisodeLabels {
+    pub outcome: Option<BattleOutcome>,
+    /// From the battle results, or `false` when the kill feed shows the player's death.
+    pub survived: Option<bool>,
+    pub final_damage: Option<u32>,
+    /// The replay has no battle results.
+    pub incomplete: bool,
+    #[serde(skip)]
+    pub death_time: Option<f32>,
+}
+
+/// Labels attached to one tick or event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Labels {
+    #[serde(flatten)]
+    pub episode: EpisodeLabels,
+    /// Seconds until the player's vehicle is destroyed; `None` if it survives or the death was not recorded.
+    pub time_to_death: Option<f32>,
+}
+
+impl EpisodeLabels {
+    pub fn at(&self, time: f32) -> Labels {
+        Labels {
+            episode: self.clone(),
+            time_to_death: self.death_time.map(|death| (death - time).max(0.0)),
+        }
+    }
+}
+
+/// Looks ahead at the battle results and the kill feed of one replay.
+pub fn episode_labels(replay: &Replay, log: &EventLog) -> EpisodeLabels {
+    let config = &replay.battle_config;
+    let player = config.player_vehicle_id();
+    let player_team = player.and_then(|id| config.vehicle(id)).map(|v| v.team);
+    let personal = replay.personal_results();
+
+    let death_time = log.events.iter().find_map(|e| match e.event {
+        Event::VehicleKilled { victim, .. } if Some(victim) == player => Some(e.time),
+        _ => None,
+    });
+    let outcome = match (replay.winner_team(), player_team) {
+        (Some(0), _) => Some(BattleOutcome::Draw),
+        (Some(winner), Some(team)) if winner == team => Some(BattleOutcome::Win),
+        (Some(_), Some(_)) => Some(BattleOutcome::Loss),
+        _ => None,
+    };
+    let deaths = personal.and_then(|p| p.get("deathCount")).and_then(|v| v.as_u64());
+    let survived = match (deaths, death_time) {
+        (Some(deaths), _) => Some(deaths == 0),
+        (None, Some(_)) => Some(false),
+        (None, None) => None,
+    };
+
+    EpisodeLabels {
+        outcome,
+        survived,
+        final_damage: personal.and_then(|p| p.get("damageDealt")).and_then(|v| v.as_u64()).map(|d| d as u32),
+        incomplete: personal.is_none(),
+        death_time,
+    }
+}
@@ -2,7 +2,37 @@ pub mod parser;
 pub mod types;
 pub mod encryption;
 pub mod packet_stream;
+pub mod packet_filter;
 pub mod definitions;
+pub mod bigworld;
+pub mod entities;
+pub mod events;
+pub mod shots;
+pub mod pickle;
+pub mod arena;
+pub mod spotting;
+pub mod ticks;
+pub mod battle_events;
+pub mod positions;
+pub mod own_vehicle;
+pub mod devices;
+pub mod artillery;
+pub mod maps;
+pub mod vehicles;
+pub mod heatmap;
+pub mod render;
+pub mod summary;
+pub mod validate;
+pub mod labels;
+pub mod merge;
+pub mod columnar;
+pub mod dedupe;
+pub mod etf;
+pub mod frames;
+pub mod shards;
+pub mod state;
+pub mod inputs;
+pub mod selection;
 
 pub use parser::Parser;
 pub use types::Replay;
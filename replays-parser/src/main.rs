@@ -1,17 +1,101 @@
-use clap::Parser as ClapParser;
+use clap::{Parser as ClapParser, Subcommand, ValueEnum};
 use rayon::prelude::*;
-use replays_parser::Parser;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use replays_parser::columnar::{ColumnarFormat, ColumnarWriter, Row};
+use replays_parser::dedupe::BattleId;
+use replays_parser::definitions::Definitions;
+use replays_parser::events::TimedEvent;
+use replays_parser::frames::{encode_frame, FrameFormat};
+use replays_parser::inputs::{discover, glob_set, Filter, Input};
+use replays_parser::heatmap::{Heatmap, HeatmapFilter, Outcome, Who};
+use replays_parser::maps::MapCatalog;
+use replays_parser::packet_filter::{parse_packet_type, PacketFilter, TimeRange};
+use replays_parser::selection::{ClientVersion, DateBound, Selection};
+use replays_parser::summary::ReplaySummary;
+use replays_parser::ticks::Tick;
+use replays_parser::vehicles::{VehicleCatalog, VehicleClass};
+use replays_parser::{Parser, Replay};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::Mutex;
 
 #[derive(ClapParser, Debug)]
-#[command(author, version, about, long_about = None)]
+// No generated --version flag: --version selects the game definitions
+#[command(author, about, long_about = None)]
 struct Args {
-    /// Path to the .wotreplay file or directory containing replays
+    /// Replay file (.wotreplay / .mtreplay), .zip or .tar.gz archive, directory searched
+    /// recursively (archives included), or `-` to read a replay or archive from stdin
     #[arg(long, required = true)]
     input: PathBuf,
 
+    /// Only take replays whose path relative to the input directory matches one of
+    /// these globs (archive members as `<archive>/<member>`)
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Skip replays and archives whose relative path matches one of these globs
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Only replays on this map (`mapName`, e.g. "05_prohorovka"); repeatable
+    #[arg(long = "map")]
+    map_names: Vec<String>,
+
+    /// Only replays whose `playerVehicle` matches this glob (e.g. "ussr-*"); repeatable
+    #[arg(long = "vehicle")]
+    vehicle_globs: Vec<String>,
+
+    /// Only replays recorded by this player; repeatable
+    #[arg(long = "player")]
+    player_names: Vec<String>,
+
+    /// Only replays of this `battleType` (1 is a random battle); repeatable
+    #[arg(long = "battle-type")]
+    battle_types: Vec<u32>,
+
+    /// Only replays of this `gameplayID` (ctf, domination, assault, ...); repeatable
+    #[arg(long = "gameplay")]
+    gameplays: Vec<String>,
+
+    /// Only replays of this client version or later (e.g. "1.32")
+    #[arg(long)]
+    min_version: Option<ClientVersion>,
+
+    /// Only replays of this client version or earlier
+    #[arg(long)]
+    max_version: Option<ClientVersion>,
+
+    /// Only battles started on or after this date (YYYY-MM-DD[THH:MM[:SS]], the recorder's local time)
+    #[arg(long)]
+    date_from: Option<DateBound>,
+
+    /// Only battles started on or before this date; a date alone includes the whole day
+    #[arg(long)]
+    date_to: Option<DateBound>,
+
+    /// Only replays with battle results
+    #[arg(long, default_value_t = false)]
+    has_results: bool,
+
+    /// Only packets of these types, hexadecimal or decimal (e.g. "0x08,0x07");
+    /// applies to `--emit`, `--stats` and the packet listing
+    #[arg(long, value_delimiter = ',', value_parser = parse_packet_type)]
+    packet_types: Vec<u32>,
+
+    /// Only packets about entities of these types (e.g. "Vehicle,Avatar")
+    #[arg(long, value_delimiter = ',')]
+    entity_types: Vec<String>,
+
+    /// Only method calls whose `<Entity>.<method>` matches one of these globs (e.g. "Vehicle.*Damage*")
+    #[arg(long, value_delimiter = ',')]
+    methods: Vec<String>,
+
+    /// Only packets in this replay-clock window, in seconds (e.g. "120..180", "120..", "..180")
+    #[arg(long)]
+    time: Option<TimeRange>,
+
     /// Game version (e.g. "1_25_0" or "wot_eu_1_25_0")
     /// Required to load the correct entity definitions.
     #[arg(long, required = true)]
@@ -24,21 +108,1232 @@ struct Args {
     /// Print statistics about message types (for debugging/analysis)
     #[arg(short, long, default_value_t = false)]
     stats: bool,
+
+    /// Emit decoded records as JSON lines instead of the replay overview
+    #[arg(long, value_enum)]
+    emit: Option<Emit>,
+
+    /// Seconds between ticks for `--emit ticks`
+    #[arg(long, default_value_t = 1.0)]
+    tick_interval: f32,
+
+    /// Add episode outcome labels (win/loss, survival, final damage, time to death)
+    /// to `--emit ticks` and `--emit events` records
+    #[arg(long, default_value_t = false)]
+    labels: bool,
+
+    /// Encoding of the `--emit` records
+    #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+    format: Format,
+
+    /// Output file for `--emit` records (required for `--format parquet` / `--format arrow`)
+    #[arg(long, conflicts_with = "out_dir")]
+    output: Option<PathBuf>,
+
+    /// Write `--emit` records to one file per shard in this directory, plus a `manifest.json`
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Number of shards for `--out-dir`; replays are assigned by a hash of their
+    /// arenaUniqueID (or file contents), so the split is the same on every run
+    #[arg(long, default_value_t = 1, requires = "out_dir")]
+    shards: usize,
+
+    /// Recognize replays of the same battle (by arenaUniqueID, else map, start time and
+    /// roster): keep only the first, or keep all under a shared `battle_id`
+    #[arg(long, value_enum)]
+    dedupe: Option<Dedupe>,
+
+    /// Checkpoint file for incremental `--emit` runs: files already processed with the
+    /// same definitions are skipped, and file outputs are appended to instead of replaced
+    #[arg(long, requires = "emit")]
+    state: Option<PathBuf>,
+
+    /// Replays decoded in parallel before their records are written
+    #[arg(long, default_value_t = 64)]
+    batch_size: usize,
+
+    /// JSON map catalog (bounds, bases, spawns) merged over the built-in map bounds
+    #[arg(long)]
+    maps: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Accumulate vehicle positions of all input replays into one heatmap per map
+    Heatmap(HeatmapArgs),
+    /// Render each input replay as an animated SVG minimap
+    Render(RenderArgs),
+    /// Compare decoded totals with the battle results, per client version
+    Validate(ValidateArgs),
+    /// Merge replays of the same battle into one position timeline, as JSON lines
+    MergePerspectives(MergeArgs),
+    /// Print one JSON line of metadata per input replay, without decoding the packet stream
+    Scan,
+}
+
+#[derive(clap::Args, Debug)]
+struct HeatmapArgs {
+    /// Only use replays on this map (e.g. "05_prohorovka")
+    #[arg(long)]
+    map: Option<String>,
+
+    /// Heatmap cells per side
+    #[arg(long, default_value_t = 64, value_parser = clap::value_parser!(u16).range(1..=1024))]
+    grid: u16,
+
+    /// Whose positions to count
+    #[arg(long, value_enum, default_value_t = WhoArg::All)]
+    who: WhoArg,
+
+    /// Only vehicles of this class (light, medium, heavy, td, spg), as listed in `--vehicles`
+    #[arg(long, requires = "vehicles")]
+    class: Option<VehicleClass>,
+
+    /// Only vehicles of this team side
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+    team: Option<u8>,
+
+    /// Only vehicles whose team won / lost (needs battle results)
+    #[arg(long, value_enum)]
+    outcome: Option<OutcomeArg>,
+
+    /// Start of the battle phase, in seconds since the battle started
+    #[arg(long)]
+    from: Option<f32>,
+
+    /// End of the battle phase, in seconds since the battle started
+    #[arg(long)]
+    to: Option<f32>,
+
+    /// Seconds between position samples
+    #[arg(long, default_value_t = 1.0)]
+    interval: f32,
+
+    /// Array format written per map
+    #[arg(long, value_enum, default_value_t = ArrayFormat::Npy)]
+    format: ArrayFormat,
+
+    /// Also render a PNG per map
+    #[arg(long, default_value_t = false)]
+    png: bool,
+
+    /// JSON vehicle class catalog (`{"<vehicleType>": "<class>"}`) for the client version of the replays
+    #[arg(long)]
+    vehicles: Option<PathBuf>,
+
+    /// Directory the per-map files are written to
+    #[arg(long, required = true)]
+    out: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct RenderArgs {
+    /// Width and height of the map area in pixels
+    #[arg(long, default_value_t = 800.0)]
+    size: f32,
+
+    /// Replay seconds between animation frames
+    #[arg(long, default_value_t = 1.0)]
+    interval: f32,
+
+    /// Playback speed (replay seconds per second)
+    #[arg(long, default_value_t = 10.0)]
+    speed: f32,
+
+    /// Directory the `<replay>.svg` files are written to, at the replay's path relative to `--input`
+    #[arg(long, required = true)]
+    out: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct ValidateArgs {
+    /// Exit with an error if any metric of any version agrees less often than this (0-1)
+    #[arg(long)]
+    min_agreement: Option<f64>,
+}
+
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    /// Only merge battles recorded by at least this many input replays
+    #[arg(long, default_value_t = 2)]
+    min_perspectives: usize,
+
+    /// Seconds around a precise ENTITY_MOVE sample in which minimap samples of the same vehicle are dropped
+    #[arg(long, default_value_t = replays_parser::merge::PRECISE_WINDOW)]
+    window: f32,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum WhoArg {
+    Player,
+    Allies,
+    All,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutcomeArg {
+    Win,
+    Loss,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ArrayFormat {
+    Npy,
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Emit {
+    /// Per-shot lifecycle records plus accuracy/penetration stats per player
+    Shots,
+    /// Every decoded event, plus derived intervals, positions and shells
+    Events,
+    /// Fixed-interval samples of the recording player's state
+    Ticks,
+    /// One line of derived player metrics per replay, checked against the battle results
+    Summary,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Dedupe {
+    /// Skip replays of a battle already seen earlier in the (sorted) input
+    First,
+    /// Keep every perspective, each record tagged with the battle's `battle_id`
+    AllPerspectives,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// JSON lines on stdout
+    Jsonl,
+    /// One Parquet file
+    Parquet,
+    /// One Arrow IPC file
+    Arrow,
+    /// Length-prefixed MessagePack records on stdout (see `frames`)
+    Msgpack,
+    /// Length-prefixed CBOR records on stdout (see `frames`)
+    Cbor,
+    /// Length-prefixed Erlang terms on stdout, for an Elixir port with `{:packet, 4}`
+    Etf,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Jsonl => "jsonl",
+            Format::Parquet => "parquet",
+            Format::Arrow => "arrow",
+            Format::Msgpack => "msgpack",
+            Format::Cbor => "cbor",
+            Format::Etf => "etf",
+        }
+    }
+
+    fn columnar(self) -> Option<ColumnarFormat> {
+        match self {
+            Format::Parquet => Some(ColumnarFormat::Parquet),
+            Format::Arrow => Some(ColumnarFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    fn frames(self) -> Option<FrameFormat> {
+        match self {
+            Format::Msgpack => Some(FrameFormat::MessagePack),
+            Format::Cbor => Some(FrameFormat::Cbor),
+            Format::Etf => Some(FrameFormat::Etf),
+            _ => None,
+        }
+    }
+}
+
+/// A JSON line or binary frame: `{"type": ..., "replay": ..., <record fields>}`
+#[derive(Serialize)]
+struct Record<'a, T: Serialize> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    replay: &'a str,
+    /// Set with `--dedupe`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    battle_id: Option<BattleId>,
+    #[serde(flatten)]
+    data: T,
+}
+
+#[derive(Serialize)]
+struct PlayerShotStats<'a> {
+    vehicle_id: Option<u32>,
+    player: Option<&'a str>,
+    #[serde(flatten)]
+    stats: &'a replays_parser::shots::ShotStats,
+    accuracy: Option<f64>,
+    penetration_ratio: Option<f64>,
+}
+
+/// A tick or event with the `--labels` fields nested under `labels`.
+#[derive(Serialize)]
+struct Labeled<'a, T: Serialize> {
+    #[serde(flatten)]
+    data: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<&'a replays_parser::labels::Labels>,
+}
+
+/// The command-line spelling of a `--format` / `--emit` value.
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default()
+}
+
+fn replay_name(input: &Input) -> String {
+    input.name().to_string_lossy().into_owned()
+}
+
+fn push_line<T: Serialize>(out: &mut String, kind: &'static str, replay: &str, data: T) {
+    out.push_str(&serde_json::to_string(&Record { kind, replay, battle_id: None, data }).unwrap());
+    out.push('\n');
+}
+
+/// `--emit shots`: shot records and per-player stats for each replay, then
+/// corpus-wide stats per player name.
+fn emit_shots(inputs: &[Input], defs: &Definitions, packets: &PacketFilter) {
+    use replays_parser::shots::{stats_by_shooter, trace_shots, ShotStats};
+
+    let corpus: Mutex<BTreeMap<String, ShotStats>> = Mutex::new(BTreeMap::new());
+
+    inputs.par_iter().for_each(|input| {
+        let path = input.path();
+        let replay = match Parser::parse_input(input) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let name = replay_name(input);
+        let config = &replay.battle_config;
+        let log = replays_parser::events::decode_events_filtered(&replay, defs, packets);
+        let shots = trace_shots(&log.events, config.player_vehicle_id());
+
+        let mut out = String::new();
+        for shot in &shots {
+            push_line(&mut out, "shot", &name, shot);
+        }
+        let per_shooter = stats_by_shooter(&shots);
+        for (vehicle_id, stats) in &per_shooter {
+            let player = config.vehicle(*vehicle_id).map(|v| v.name.as_str());
+            push_line(&mut out, "shot_stats", &name, PlayerShotStats {
+                vehicle_id: Some(*vehicle_id),
+                player,
+                stats,
+                accuracy: stats.accuracy(),
+                penetration_ratio: stats.penetration_ratio(),
+            });
+            if let Some(player) = player {
+                corpus.lock().unwrap().entry(player.to_string()).or_default().merge(stats);
+            }
+        }
+        std::io::stdout().lock().write_all(out.as_bytes()).unwrap();
+    });
+
+    let mut out = String::new();
+    for (player, stats) in &corpus.into_inner().unwrap() {
+        push_line(&mut out, "shot_stats", "*", PlayerShotStats {
+            vehicle_id: None,
+            player: Some(player),
+            stats,
+            accuracy: stats.accuracy(),
+            penetration_ratio: stats.penetration_ratio(),
+        });
+    }
+    std::io::stdout().lock().write_all(out.as_bytes()).unwrap();
+}
+
+/// What `--emit ticks|events|summary` needs besides the replay itself.
+struct EmitOptions<'a> {
+    defs: &'a Definitions,
+    packets: &'a PacketFilter,
+    maps: &'a MapCatalog,
+    emit: Emit,
+    tick_interval: f32,
+    labels: bool,
+    dedupe: Option<Dedupe>,
+}
+
+/// Decoded records of one replay.
+enum Records {
+    Ticks(Vec<Row<Tick>>),
+    Events(Vec<Row<TimedEvent>>),
+    Summary(Box<Row<ReplaySummary>>),
+}
+
+impl Records {
+    fn len(&self) -> usize {
+        match self {
+            Records::Ticks(rows) => rows.len(),
+            Records::Events(rows) => rows.len(),
+            Records::Summary(_) => 1,
+        }
+    }
+}
+
+/// One parsed replay with its records.
+struct Decoded {
+    replay: Replay,
+    records: Records,
+    /// Computed with `--dedupe`.
+    battle_id: Option<BattleId>,
+    /// `shards::shard_key`, shared by all perspectives of the battle.
+    shard_key: u64,
+}
+
+/// Ticks, events (with derived intervals, positions and shells) or the summary of one replay.
+fn replay_records(input: &Input, options: &EmitOptions) -> anyhow::Result<Decoded> {
+    use replays_parser::artillery::{arty_shots, stun_intervals};
+    use replays_parser::devices::device_changes;
+    use replays_parser::events::{decode_events_filtered, Event};
+    use replays_parser::labels::episode_labels;
+    use replays_parser::positions::{attach_map_positions, minimap_positions};
+    use replays_parser::spotting::spotted_intervals;
+    use replays_parser::summary::summarize;
+    use replays_parser::ticks::build_ticks;
+
+    let replay = Parser::parse_input(input)?;
+    let name = replay_name(input);
+    let mut log = decode_events_filtered(&replay, options.defs, options.packets);
+    let battle_id = options.dedupe.map(|_| replays_parser::dedupe::battle_id(&replay, &log));
+    let shard_key = replays_parser::shards::shard_key(&replay, &log);
+    let episode = options.labels.then(|| episode_labels(&replay, &log));
+    let labels_at = |time: f32| episode.as_ref().map(|episode| episode.at(time));
+
+    let records = match options.emit {
+        Emit::Ticks => Records::Ticks(
+            build_ticks(&log, &replay.battle_config, options.tick_interval)?
+                .into_iter()
+                .map(|tick| Row { replay: name.clone(), battle_id, labels: labels_at(tick.time), record: tick })
+                .collect(),
+        ),
+        Emit::Summary => Records::Summary(Box::new(Row {
+            replay: name.clone(),
+            battle_id,
+            record: summarize(&replay, &log),
+            labels: None,
+        })),
+        Emit::Events | Emit::Shots => {
+            // Derive everything from the decoded events before mixing the results in
+            let intervals = spotted_intervals(&log, &replay.battle_config);
+            let minimap = minimap_positions(&log, &replay.battle_config);
+            let devices = device_changes(&log);
+            let stuns = stun_intervals(&log);
+            let shells = arty_shots(&log);
+
+            log.events.extend(intervals.into_iter().map(|i| TimedEvent { time: i.from, event: Event::SpottedInterval(i) }));
+            log.events.extend(minimap);
+            log.events.extend(devices);
+            log.events.extend(stuns.into_iter().map(|s| TimedEvent { time: s.from, event: Event::StunInterval(s) }));
+            log.events.extend(shells.into_iter().map(|s| TimedEvent { time: s.time, event: Event::ArtyShot(s) }));
+            log.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+            if let Some(map) = options.maps.get(&replay.battle_config.map_name) {
+                attach_map_positions(&mut log.events, map);
+            }
+            Records::Events(
+                log.events
+                    .into_iter()
+                    .map(|event| Row { replay: name.clone(), battle_id, labels: labels_at(event.time), record: event })
+                    .collect(),
+            )
+        }
+    };
+    Ok(Decoded { replay, records, battle_id, shard_key })
+}
+
+/// Appends one record to `out` as a JSON line or, for `--format msgpack|cbor`, a frame.
+fn push_record<T: Serialize>(out: &mut Vec<u8>, format: Format, kind: &'static str, replay: &str, battle_id: Option<BattleId>, data: T) {
+    let record = Record { kind, replay, battle_id, data };
+    match format.frames() {
+        Some(frames) => encode_frame(out, frames, &record).unwrap(),
+        None => {
+            serde_json::to_writer(&mut *out, &record).unwrap();
+            out.push(b'\n');
+        }
+    }
+}
+
+fn push_rows<T: Serialize>(out: &mut Vec<u8>, format: Format, kind: &'static str, rows: &[Row<T>]) {
+    for row in rows {
+        push_record(out, format, kind, &row.replay, row.battle_id, Labeled { data: &row.record, labels: row.labels.as_ref() });
+    }
+}
+
+/// Where `--emit` records go: a stream of JSON lines / frames, or a Parquet / Arrow file.
+enum Sink {
+    Stream(Box<dyn Write>),
+    Columnar(Box<ColumnarWriter>),
+}
+
+impl Sink {
+    /// A sink for `path`, or stdout for the streaming formats without one. With `append`,
+    /// stream files keep their contents (columnar files cannot be appended to).
+    fn create(path: Option<&Path>, format: Format, emit: Emit, append: bool) -> anyhow::Result<Self> {
+        use anyhow::Context;
+        use replays_parser::columnar::{event_schema, summary_schema, tick_schema};
+
+        if let Some(columnar) = format.columnar() {
+            let path = path.ok_or_else(|| anyhow::anyhow!("--format {:?} needs --output or --out-dir", format))?;
+            let schema = match emit {
+                Emit::Ticks => tick_schema(),
+                Emit::Events | Emit::Shots => event_schema(),
+                Emit::Summary => summary_schema(),
+            };
+            return Ok(Sink::Columnar(Box::new(ColumnarWriter::create(path, columnar, schema)?)));
+        }
+        Ok(Sink::Stream(match path {
+            Some(path) => {
+                let file = fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(append)
+                    .truncate(!append)
+                    .open(path)
+                    .with_context(|| format!("Failed to create {}", path.display()))?;
+                Box::new(std::io::BufWriter::new(file))
+            }
+            None => Box::new(std::io::stdout()),
+        }))
+    }
+
+    /// Writes the records of `replays`, grouped per replay; one record batch for the columnar formats.
+    fn write(&mut self, replays: Vec<(&Input, Decoded)>, format: Format, options: &EmitOptions) -> anyhow::Result<()> {
+        use replays_parser::columnar::{events_batch, summaries_batch, ticks_batch};
+
+        match self {
+            Sink::Stream(out) => {
+                let mut buf = Vec::new();
+                for (input, Decoded { replay, records, battle_id, .. }) in &replays {
+                    if format.frames().is_some() {
+                        push_record(&mut buf, format, "header", &replay_name(input), *battle_id, replay);
+                    }
+                    match records {
+                        Records::Ticks(rows) => push_rows(&mut buf, format, "tick", rows),
+                        Records::Events(rows) => push_rows(&mut buf, format, "event", rows),
+                        Records::Summary(row) => push_rows(&mut buf, format, "summary", std::slice::from_ref(&**row)),
+                    }
+                }
+                out.write_all(&buf)?;
+                out.flush()?;
+            }
+            Sink::Columnar(writer) => {
+                if replays.is_empty() {
+                    return Ok(());
+                }
+                let records = replays.into_iter().map(|(_, decoded)| decoded.records);
+                let batch = match options.emit {
+                    Emit::Ticks => ticks_batch(
+                        &records.flat_map(|r| match r { Records::Ticks(rows) => rows, _ => Vec::new() }).collect::<Vec<_>>(),
+                        options.tick_interval,
+                    )?,
+                    Emit::Events | Emit::Shots => events_batch(
+                        &records.flat_map(|r| match r { Records::Events(rows) => rows, _ => Vec::new() }).collect::<Vec<_>>(),
+                        options.tick_interval,
+                    )?,
+                    Emit::Summary => summaries_batch(
+                        &records.filter_map(|r| match r { Records::Summary(row) => Some(*row), _ => None }).collect::<Vec<_>>(),
+                    )?,
+                };
+                writer.write(&batch)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Sink::Stream(mut out) => Ok(out.flush()?),
+            Sink::Columnar(writer) => writer.finish(),
+        }
+    }
+}
+
+/// Rewrites the JSON lines or frames at `path` without the records of `replays`,
+/// through a temporary file.
+fn drop_records(path: &Path, format: Format, replays: &HashSet<String>) -> anyhow::Result<()> {
+    use anyhow::Context;
+    use replays_parser::frames::{decode_payload, write_payload, FrameReader};
+    use std::io::BufRead;
+
+    #[derive(Deserialize)]
+    struct Key {
+        replay: String,
+    }
+
+    let input = std::io::BufReader::new(fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?);
+    let tmp = path.with_extension("tmp");
+    let mut out = std::io::BufWriter::new(fs::File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?);
+    match format.frames() {
+        Some(frames) => {
+            let mut reader = FrameReader::new(input, frames);
+            while let Some(payload) = reader.read_payload()? {
+                let key: Key = decode_payload(&payload, frames).with_context(|| format!("Invalid record in {}", path.display()))?;
+                if !replays.contains(&key.replay) {
+                    write_payload(&mut out, &payload)?;
+                }
+            }
+        }
+        None => {
+            for line in input.lines() {
+                let line = line?;
+                let key: Key = serde_json::from_str(&line).with_context(|| format!("Invalid record in {}", path.display()))?;
+                if !replays.contains(&key.replay) {
+                    writeln!(out, "{}", line)?;
+                }
+            }
+        }
+    }
+    out.flush()?;
+    drop(out);
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Where `emit_records` writes: stdout / `--output`, or `--out-dir` with `--shards`.
+enum Destination<'a> {
+    Single(Option<&'a Path>),
+    Sharded { dir: &'a Path, shards: usize },
+}
+
+/// `--state`: the checkpoint file and the definitions the run decodes with.
+struct Checkpoint<'a> {
+    path: &'a Path,
+    /// `state::definitions_key` of the loaded definitions.
+    definitions: String,
+}
+
+/// What became of one input file in `emit_records`.
+enum Processed {
+    /// Unchanged since a previous run; its checkpoint entry.
+    Skipped(replays_parser::state::StateEntry),
+    Decoded { shard: usize, decoded: Box<Decoded>, file: Option<(u64, u64, String)> },
+}
+
+/// `--emit ticks|events|summary`: decodes `batch_size` replays at a time in parallel and
+/// writes their records in input order, as JSON lines, frames (MessagePack, CBOR, ETF)
+/// or Parquet / Arrow rows buffered into row groups of `columnar::ROW_GROUP_ROWS` per output file.
+/// On an error, the unfinished Parquet / Arrow files are removed.
+///
+/// Frames of a replay start with a `header` record holding the replay header, battle
+/// config and battle results, as `--json` prints them. With `--out-dir`, each replay goes
+/// to the shard of its battle (`shards::shard_key`) and the
+/// run is listed in `manifest.json`.
+///
+/// With a `checkpoint`, unchanged files are skipped, file outputs are appended to (after
+/// dropping the old records of files decoded again), and the manifest keeps the entries
+/// of earlier runs. The checkpoint is saved after every batch written to a stream output.
+fn emit_records(
+    inputs: &[Input],
+    options: &EmitOptions,
+    format: Format,
+    destination: Destination,
+    checkpoint: Option<&Checkpoint>,
+    batch_size: usize,
+) -> anyhow::Result<()> {
+    use replays_parser::shards::{shard_file_name, shard_of, Manifest, ManifestEntry, ReplayStatus};
+    use replays_parser::state::{Check, State, StateEntry};
+
+    if options.emit == Emit::Shots {
+        anyhow::bail!("--emit shots only supports JSON lines on stdout, without --dedupe or --state");
+    }
+    if checkpoint.is_some() && format.columnar().is_some() {
+        match destination {
+            Destination::Sharded { .. } => anyhow::bail!("--state cannot append to Parquet / Arrow shards; use a new --output per run instead"),
+            Destination::Single(Some(output)) if output.exists() => {
+                anyhow::bail!("--state cannot append to the Parquet / Arrow file {}; use a new --output per run instead", output.display())
+            }
+            Destination::Single(_) => {}
+        }
+    }
+    let mut state = match checkpoint {
+        Some(checkpoint) => State::load(checkpoint.path)?,
+        None => State::default(),
+    };
+    let (shards, files) = match destination {
+        Destination::Single(_) => (1, Vec::new()),
+        Destination::Sharded { dir, shards } => {
+            fs::create_dir_all(dir)?;
+            let shards = shards.max(1);
+            (shards, (0..shards).map(|i| shard_file_name(i, format.extension())).collect())
+        }
+    };
+    let outputs: Vec<Option<PathBuf>> = match destination {
+        Destination::Single(output) => vec![output.map(Path::to_path_buf)],
+        Destination::Sharded { dir, .. } => files.iter().map(|file| Some(dir.join(file))).collect(),
+    };
+    let output_of = |shard: usize| outputs[shard].as_ref().map_or("-".to_string(), |path| path.display().to_string());
+
+    // Checked up front, so the records of files decoded again (changed, or with new
+    // definitions) can be dropped from the outputs before appending their new ones
+    let checks: Vec<anyhow::Result<Option<Check>>> = inputs
+        .par_iter()
+        .map(|input| match checkpoint {
+            Some(checkpoint) => state.check(input, &checkpoint.definitions).map(Some),
+            None => Ok(None),
+        })
+        .collect();
+    let mut stale: HashMap<&str, HashSet<String>> = HashMap::new();
+    for (input, check) in inputs.iter().zip(&checks) {
+        if let Ok(Some(Check::Process { .. })) = check
+            && let Some(entry) = state.entries.get(&*input.path().to_string_lossy())
+            && entry.status == ReplayStatus::Ok
+        {
+            stale.entry(entry.output.as_str()).or_default().insert(replay_name(input));
+        }
+    }
+    for (shard, output) in outputs.iter().enumerate() {
+        if let (Some(output), Some(replays)) = (output, stale.get(output_of(shard).as_str()))
+            && output.exists()
+        {
+            drop_records(output, format, replays)?;
+        }
+    }
+
+    let append = checkpoint.is_some();
+    let mut sinks = outputs
+        .iter()
+        .map(|output| Sink::create(output.as_deref(), format, options.emit, append))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let sharded = matches!(destination, Destination::Sharded { .. });
+    let manifest_path = match destination {
+        Destination::Sharded { dir, .. } => Some(dir.join("manifest.json")),
+        Destination::Single(_) => None,
+    };
+    // Files skipped by --state keep their entries from earlier runs
+    let earlier: Vec<ManifestEntry> = match &manifest_path {
+        Some(path) if checkpoint.is_some() && path.exists() => Manifest::load(path)?.replays,
+        _ => Vec::new(),
+    };
+    let write_manifest = |manifest: &[ManifestEntry]| -> anyhow::Result<()> {
+        let Some(path) = &manifest_path else {
+            return Ok(());
+        };
+        let replays = earlier
+            .iter()
+            .filter(|old| !manifest.iter().any(|new| new.replay == old.replay))
+            .chain(manifest)
+            .cloned()
+            .collect();
+        Manifest { shards, format: value_name(format), emit: value_name(options.emit), files: files.clone(), replays }.write(path)
+    };
+
+    let mut manifest = Vec::new();
+    // Battles already written, and by which file; earlier runs count with --state
+    let mut seen: HashMap<BattleId, String> = state
+        .entries
+        .iter()
+        .filter(|(_, entry)| entry.status == ReplayStatus::Ok)
+        .filter_map(|(file, entry)| Some((entry.battle_id?, file.clone())))
+        .collect();
+    let (mut duplicates, mut unchanged) = (0, 0);
+    let mut checks = checks.into_iter();
+    for chunk in inputs.chunks(batch_size.max(1)) {
+        let chunk_checks: Vec<_> = checks.by_ref().take(chunk.len()).collect();
+        let processed: Vec<_> = chunk
+            .par_iter()
+            .zip(chunk_checks)
+            .map(|(input, check)| -> anyhow::Result<_> {
+                let file = match check? {
+                    Some(Check::Unchanged(entry)) => return Ok(Processed::Skipped(entry)),
+                    Some(Check::Process { size, mtime_ns, hash }) => Some((size, mtime_ns, hash)),
+                    None => None,
+                };
+                let decoded = replay_records(input, options)?;
+                // All perspectives of a battle share a shard, so no battle straddles a split
+                let shard = if sharded { shard_of(decoded.shard_key, shards) } else { 0 };
+                Ok(Processed::Decoded { shard, decoded: Box::new(decoded), file })
+            })
+            .collect();
+
+        let mut per_shard: Vec<Vec<(&Input, Decoded)>> = (0..shards).map(|_| Vec::new()).collect();
+        for (input, result) in chunk.iter().zip(processed) {
+            let path = input.path();
+            let entry = match result {
+                Ok(Processed::Skipped(entry)) => {
+                    unchanged += 1;
+                    state.record(path, entry);
+                    continue;
+                }
+                Ok(Processed::Decoded { shard, decoded, file }) => {
+                    let name = path.to_string_lossy();
+                    let duplicate = options.dedupe == Some(Dedupe::First)
+                        && decoded.battle_id.is_some_and(|battle_id| match seen.get(&battle_id) {
+                            Some(first) => *first != name,
+                            None => {
+                                seen.insert(battle_id, name.into_owned());
+                                false
+                            }
+                        });
+                    let entry = ManifestEntry {
+                        replay: replay_name(input),
+                        shard: (!duplicate).then_some(shard),
+                        battle_id: decoded.battle_id,
+                        arena_unique_id: match decoded.battle_id {
+                            Some(BattleId::Arena(id)) => Some(id),
+                            _ => decoded.replay.arena_unique_id(),
+                        },
+                        version: Some(decoded.replay.battle_config.client_version_from_exe.clone()),
+                        status: if duplicate { ReplayStatus::Duplicate } else { ReplayStatus::Ok },
+                        error: None,
+                        records: if duplicate { 0 } else { decoded.records.len() },
+                    };
+                    if let (Some(checkpoint), Some((size, mtime_ns, hash))) = (checkpoint, file) {
+                        state.record(path, StateEntry {
+                            size,
+                            mtime_ns,
+                            hash,
+                            definitions: checkpoint.definitions.clone(),
+                            output: output_of(shard),
+                            status: entry.status,
+                            battle_id: decoded.battle_id,
+                        });
+                    }
+                    if duplicate {
+                        duplicates += 1;
+                    } else {
+                        per_shard[shard].push((input, *decoded));
+                    }
+                    entry
+                }
+                Err(e) => {
+                    eprintln!("Error parsing {}: {:#}", path.display(), e);
+                    ManifestEntry {
+                        replay: replay_name(input),
+                        shard: None,
+                        battle_id: None,
+                        arena_unique_id: None,
+                        version: None,
+                        status: ReplayStatus::Error,
+                        error: Some(format!("{:#}", e)),
+                        records: 0,
+                    }
+                }
+            };
+            if sharded {
+                manifest.push(entry);
+            }
+        }
+        for (sink, replays) in sinks.iter_mut().zip(per_shard) {
+            sink.write(replays, format, options)?;
+        }
+        // Stream outputs are flushed per chunk, so an interrupted run resumes after the
+        // last chunk written; a columnar file is only complete once finished
+        if let Some(checkpoint) = checkpoint
+            && format.columnar().is_none()
+        {
+            state.save(checkpoint.path)?;
+            write_manifest(&manifest)?;
+        }
+    }
+    for sink in sinks {
+        sink.finish()?;
+    }
+    if duplicates > 0 {
+        eprintln!("Skipped {} replays of battles already seen", duplicates);
+    }
+    if let Some(checkpoint) = checkpoint {
+        state.save(checkpoint.path)?;
+        if unchanged > 0 {
+            eprintln!("Skipped {} replays unchanged since the last run", unchanged);
+        }
+    }
+
+    write_manifest(&manifest)?;
+    Ok(())
+}
+
+/// `heatmap`: one heatmap per map over all replays, written to `<out>/<map>.<format>`.
+fn run_heatmap(inputs: &[Input], defs: &Definitions, maps: &MapCatalog, args: &HeatmapArgs) -> anyhow::Result<()> {
+    use replays_parser::events::decode_events;
+
+    let mut vehicles = VehicleCatalog::default();
+    if let Some(path) = &args.vehicles {
+        vehicles.load_json(path)?;
+    }
+    let filter = HeatmapFilter {
+        who: match args.who {
+            WhoArg::Player => Who::Player,
+            WhoArg::Allies => Who::Allies,
+            WhoArg::All => Who::All,
+        },
+        class: args.class,
+        team: args.team,
+        outcome: args.outcome.map(|outcome| match outcome {
+            OutcomeArg::Win => Outcome::Win,
+            OutcomeArg::Loss => Outcome::Loss,
+        }),
+        from: args.from,
+        to: args.to,
+    };
+
+    let empty = Heatmap::new(args.grid as usize)?;
+    let heatmaps: Mutex<BTreeMap<String, (Heatmap, u32)>> = Mutex::new(BTreeMap::new());
+    inputs.par_iter().for_each(|input| {
+        let path = input.path();
+        let replay = match Parser::parse_input(input) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let map_name = &replay.battle_config.map_name;
+        if args.map.as_ref().is_some_and(|map| map != map_name) {
+            return;
+        }
+        let Some(map) = maps.get(map_name) else {
+            eprintln!("Skipping {}: no bounds for map '{}'", path.display(), map_name);
+            return;
+        };
+        let log = decode_events(&replay, defs);
+        let mut heatmap = empty.clone();
+        heatmap.accumulate(&log, &replay.battle_config, replay.winner_team(), map, &vehicles, &filter, args.interval);
+
+        let mut heatmaps = heatmaps.lock().unwrap();
+        let (total, replays) = heatmaps
+            .entry(map_name.clone())
+            .or_insert_with(|| (empty.clone(), 0));
+        total.merge(&heatmap);
+        *replays += 1;
+    });
+
+    fs::create_dir_all(&args.out)?;
+    for (map_name, (heatmap, replays)) in heatmaps.into_inner().unwrap() {
+        let path = match args.format {
+            ArrayFormat::Npy => args.out.join(format!("{}.npy", map_name)),
+            ArrayFormat::Csv => args.out.join(format!("{}.csv", map_name)),
+        };
+        match args.format {
+            ArrayFormat::Npy => heatmap.write_npy(&path)?,
+            ArrayFormat::Csv => heatmap.write_csv(&path)?,
+        }
+        if args.png {
+            heatmap.write_png(&args.out.join(format!("{}.png", map_name)))?;
+        }
+        println!("{}: {} replays, {} samples -> {}", map_name, replays, heatmap.samples, path.display());
+    }
+    Ok(())
+}
+
+/// `render`: one animated SVG per replay, written to `<out>/<replay>.svg`.
+fn run_render(inputs: &[Input], defs: &Definitions, maps: &MapCatalog, args: &RenderArgs) -> anyhow::Result<()> {
+    use replays_parser::events::decode_events;
+    use replays_parser::render::{render_svg, RenderOptions};
+
+    let options = RenderOptions { size: args.size, interval: args.interval, speed: args.speed };
+    fs::create_dir_all(&args.out)?;
+    inputs.par_iter().for_each(|input| {
+        let path = input.path();
+        let replay = match Parser::parse_input(input) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let map_name = &replay.battle_config.map_name;
+        let Some(map) = maps.get(map_name) else {
+            eprintln!("Skipping {}: no bounds for map '{}'", path.display(), map_name);
+            return;
+        };
+        let log = decode_events(&replay, defs);
+        let svg = match render_svg(&log, &replay.battle_config, map, &options) {
+            Ok(svg) => svg,
+            Err(e) => {
+                eprintln!("Error rendering {}: {}", path.display(), e);
+                return;
+            }
+        };
+        // Mirrors the path relative to --input, so equal file names do not overwrite each other
+        let out = args.out.join(input.name()).with_extension("svg");
+        if let Some(parent) = out.parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            eprintln!("Error creating {}: {}", parent.display(), e);
+            return;
+        }
+        match fs::write(&out, svg) {
+            Ok(()) => println!("{} -> {}", path.display(), out.display()),
+            Err(e) => eprintln!("Error writing {}: {}", out.display(), e),
+        }
+    });
+    Ok(())
+}
+
+/// `validate`: agreement of the decoded totals with the battle results, per client version.
+fn run_validate(inputs: &[Input], defs: &Definitions, json: bool, args: &ValidateArgs) -> anyhow::Result<()> {
+    use replays_parser::events::decode_events;
+    use replays_parser::summary::summarize;
+    use replays_parser::validate::{ValidationReport, VALIDATED_METRICS};
+
+    let report: Mutex<ValidationReport> = Mutex::new(ValidationReport::new());
+    inputs.par_iter().for_each(|input| {
+        let path = input.path();
+        let replay = match Parser::parse_input(input) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let log = decode_events(&replay, defs);
+        let summary = summarize(&replay, &log);
+        let version = replay.battle_config.client_version_from_exe.clone();
+        report.lock().unwrap().entry(version).or_default().add(&summary);
+    });
+    let report = report.into_inner().unwrap();
+
+    if json {
+        #[derive(Serialize)]
+        struct VersionLine<'a> {
+            #[serde(rename = "type")]
+            kind: &'static str,
+            version: &'a str,
+            #[serde(flatten)]
+            report: &'a replays_parser::validate::VersionReport,
+        }
+        let mut out = String::new();
+        for (version, version_report) in &report {
+            let line = VersionLine { kind: "validation", version, report: version_report };
+            out.push_str(&serde_json::to_string(&line).unwrap());
+            out.push('\n');
+        }
+        std::io::stdout().lock().write_all(out.as_bytes()).unwrap();
+    } else {
+        print!("{:<16} | {:>7} | {:>10}", "Version", "Replays", "No results");
+        for metric in VALIDATED_METRICS {
+            print!(" | {:>15}", metric);
+        }
+        println!();
+        for (version, version_report) in &report {
+            print!("{:<16} | {:>7} | {:>10}", version, version_report.replays, version_report.without_results);
+            for metric in VALIDATED_METRICS {
+                let cell = match version_report.metrics.get(metric).and_then(|a| a.rate().map(|rate| (a, rate))) {
+                    Some((agreement, rate)) => format!("{:.1}% ({}/{})", rate * 100.0, agreement.agreed, agreement.checked),
+                    None => "-".to_string(),
+                };
+                print!(" | {:>15}", cell);
+            }
+            println!();
+        }
+    }
+
+    if let Some(min_agreement) = args.min_agreement {
+        let failing: Vec<&str> = report
+            .iter()
+            .filter(|(_, r)| r.worst_rate().is_some_and(|rate| rate < min_agreement))
+            .map(|(version, _)| version.as_str())
+            .collect();
+        if !failing.is_empty() {
+            anyhow::bail!("Agreement below {} for versions: {}", min_agreement, failing.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// `merge-perspectives`: groups the input by battle, then writes a `battle` line and the
+/// merged `observation` lines of every battle with enough perspectives.
+fn run_merge(inputs: &[Input], defs: &Definitions, maps: &MapCatalog, args: &MergeArgs) -> anyhow::Result<()> {
+    use replays_parser::dedupe::battle_id;
+    use replays_parser::events::decode_events;
+    use replays_parser::merge::{merge_perspectives, Observation, PerspectiveInfo, Perspective};
+
+    #[derive(Serialize)]
+    struct BattleLine<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        battle_id: BattleId,
+        map: &'a str,
+        perspectives: &'a [PerspectiveInfo],
+        unaligned: &'a [String],
+    }
+
+    #[derive(Serialize)]
+    struct ObservationLine<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        battle_id: BattleId,
+        #[serde(flatten)]
+        observation: &'a Observation,
+    }
+
+    let parse = |input: &Input| match Parser::parse_input(input) {
+        Ok(replay) => Some(replay),
+        Err(e) => {
+            eprintln!("Error parsing {}: {}", input.path().display(), e);
+            None
+        }
+    };
+
+    // Identify battles first, so only the events of replays that get merged are kept in memory.
+    // The battle results in the header carry the arena ID; only replays without them are decoded.
+    let identify = |input: &Input| -> Option<BattleId> {
+        let header = match input.open().and_then(Parser::parse_header) {
+            Ok(header) => header,
+            Err(e) => {
+                eprintln!("Error parsing {}: {}", input.path().display(), e);
+                return None;
+            }
+        };
+        match header.arena_unique_id() {
+            Some(id) => Some(BattleId::Arena(id)),
+            None => parse(input).map(|replay| battle_id(&replay, &decode_events(&replay, defs))),
+        }
+    };
+    let ids: Vec<(BattleId, &Input)> = inputs.par_iter().filter_map(|input| identify(input).map(|id| (id, input))).collect();
+    let mut battles: BTreeMap<BattleId, Vec<&Input>> = BTreeMap::new();
+    for (id, input) in ids {
+        battles.entry(id).or_default().push(input);
+    }
+    let battles: Vec<_> = battles.into_iter().filter(|(_, inputs)| inputs.len() >= args.min_perspectives).collect();
+
+    battles.into_par_iter().for_each(|(id, inputs)| {
+        let decoded: Vec<_> = inputs
+            .iter()
+            .filter_map(|input| parse(input).map(|replay| (replay_name(input), decode_events(&replay, defs), replay)))
+            .collect();
+        let Some((_, _, first)) = decoded.first() else { return };
+        let map_name = first.battle_config.map_name.as_str();
+        let perspectives: Vec<Perspective> = decoded
+            .iter()
+            .map(|(name, log, replay)| Perspective { replay: name, config: &replay.battle_config, log })
+            .collect();
+        let merged = merge_perspectives(&perspectives, maps.get(map_name), args.window);
+
+        let mut out = String::new();
+        let battle = BattleLine {
+            kind: "battle",
+            battle_id: id,
+            map: map_name,
+            perspectives: &merged.perspectives,
+            unaligned: &merged.unaligned,
+        };
+        out.push_str(&serde_json::to_string(&battle).unwrap());
+        out.push('\n');
+        for observation in &merged.observations {
+            out.push_str(&serde_json::to_string(&ObservationLine { kind: "observation", battle_id: id, observation }).unwrap());
+            out.push('\n');
+        }
+        std::io::stdout().lock().write_all(out.as_bytes()).unwrap();
+    });
+    Ok(())
+}
+
+/// The inputs whose header passes `selection`, in input order. Unreadable headers are
+/// kept, so the full parse reports them like any other broken replay.
+fn select(inputs: Vec<Input>, selection: &Selection) -> Vec<Input> {
+    let total = inputs.len();
+    let selected: Vec<Input> = inputs
+        .into_par_iter()
+        .filter(|input| input.open().and_then(Parser::parse_header).map_or(true, |replay| selection.matches(&replay)))
+        .collect();
+    eprintln!("Selected {} of {} replays", selected.len(), total);
+    selected
+}
+
+/// `scan`: battle config and results metadata of each replay, from `Parser::parse_header`.
+fn run_scan(inputs: &[Input]) -> anyhow::Result<()> {
+    #[derive(Serialize)]
+    struct ScanLine<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        replay: String,
+        path: &'a Path,
+        version: &'a str,
+        map: &'a str,
+        gameplay: &'a str,
+        player: &'a str,
+        vehicle: &'a str,
+        date_time: &'a str,
+        vehicles: usize,
+        arena_unique_id: Option<u64>,
+        has_results: bool,
+        winner_team: Option<u8>,
+    }
+
+    let lines: Vec<Option<String>> = inputs
+        .par_iter()
+        .map(|input| {
+            let path = input.path();
+            let replay = match input.open().and_then(Parser::parse_header) {
+                Ok(replay) => replay,
+                Err(e) => {
+                    eprintln!("Error parsing {}: {:#}", path.display(), e);
+                    return None;
+                }
+            };
+            let config = &replay.battle_config;
+            let line = ScanLine {
+                kind: "scan",
+                replay: replay_name(input),
+                path,
+                version: &config.client_version_from_exe,
+                map: &config.map_name,
+                gameplay: &config.gameplay_id,
+                player: &config.player_name,
+                vehicle: &config.player_vehicle,
+                date_time: &config.date_time,
+                vehicles: config.vehicles.len(),
+                arena_unique_id: replay.arena_unique_id(),
+                has_results: replay.battle_results.is_some(),
+                winner_team: replay.winner_team(),
+            };
+            Some(serde_json::to_string(&line).unwrap())
+        })
+        .collect();
+
+    let mut out = std::io::stdout().lock();
+    for line in lines.into_iter().flatten() {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
 }
 
 fn main() {
     let args = Args::parse();
 
-    let paths: Vec<PathBuf> = if args.input.is_dir() {
-        fs::read_dir(&args.input)
-            .unwrap()
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.path())
-            .filter(|path| path.extension().map_or(false, |ext| ext == "wotreplay"))
-            .collect()
-    } else {
-        vec![args.input.clone()]
+    let inputs = Filter::new(&args.include, &args.exclude).and_then(|filter| discover(&args.input, &filter));
+    let mut inputs = match inputs {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
     };
+    // Sorted input keeps output and manifests reproducible
+    inputs.sort_by(|a, b| a.path().cmp(b.path()));
+
+    let selection = glob_set(&args.vehicle_globs).map(|vehicles| Selection {
+        maps: args.map_names.clone(),
+        vehicles,
+        players: args.player_names.clone(),
+        battle_types: args.battle_types.clone(),
+        gameplays: args.gameplays.clone(),
+        min_version: args.min_version.clone(),
+        max_version: args.max_version.clone(),
+        date_from: args.date_from,
+        date_to: args.date_to,
+        has_results: args.has_results.then_some(true),
+    });
+    match selection {
+        Ok(selection) if !selection.is_empty() => inputs = select(inputs, &selection),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            // Removes the directories tar members were extracted to
+            drop(inputs);
+            std::process::exit(1);
+        }
+    }
 
     // Load Definitions once
     // We expect the version string to be safe (e.g. "wot_eu_v1_25_1_0" or just "1_25_1_0" if we construct it)
@@ -48,7 +1343,22 @@ fn main() {
     // But the user said "do not trying to detect it".
     // So we assume args.version is the full ID or we try to load it directly.
     
-    let defs = match replays_parser::definitions::Definitions::load(&args.version) {
+    let packets = glob_set(&args.methods).map(|methods| PacketFilter {
+        packet_types: args.packet_types.clone(),
+        entity_types: args.entity_types.clone(),
+        methods,
+        time: args.time,
+    });
+    let packets = match packets {
+        Ok(packets) => packets,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            drop(inputs);
+            std::process::exit(1);
+        }
+    };
+
+    let defs = match Definitions::load(&args.version) {
         Ok(d) => Some(d),
         Err(e) => {
             eprintln!("Warning: Failed to load definitions for version '{}': {}", args.version, e);
@@ -56,22 +1366,87 @@ fn main() {
         }
     };
 
+    let load_maps = || {
+        let mut maps = MapCatalog::builtin();
+        if let Some(path) = &args.maps
+            && let Err(e) = maps.load_json(path)
+        {
+            eprintln!("Warning: {:#}", e);
+        }
+        maps
+    };
+
+    if let Some(command) = &args.command {
+        let defs = defs.unwrap_or_default();
+        let result = match command {
+            Command::Heatmap(heatmap_args) => run_heatmap(&inputs, &defs, &load_maps(), heatmap_args),
+            Command::Render(render_args) => run_render(&inputs, &defs, &load_maps(), render_args),
+            Command::Validate(validate_args) => run_validate(&inputs, &defs, args.json, validate_args),
+            Command::MergePerspectives(merge_args) => run_merge(&inputs, &defs, &load_maps(), merge_args),
+            Command::Scan => run_scan(&inputs),
+        };
+        if let Err(e) = result {
+            eprintln!("Error: {:#}", e);
+            drop(inputs);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(emit) = args.emit {
+        let defs = defs.unwrap_or_default();
+        let maps = load_maps();
+        let plain_stdout = args.output.is_none() && args.out_dir.is_none() && args.dedupe.is_none() && args.state.is_none();
+        if emit == Emit::Shots && args.format == Format::Jsonl && plain_stdout {
+            emit_shots(&inputs, &defs, &packets);
+            return;
+        }
+        let options = EmitOptions {
+            defs: &defs,
+            packets: &packets,
+            maps: &maps,
+            emit,
+            tick_interval: args.tick_interval,
+            labels: args.labels,
+            dedupe: args.dedupe,
+        };
+        let destination = match &args.out_dir {
+            Some(dir) => Destination::Sharded { dir, shards: args.shards },
+            None => Destination::Single(args.output.as_deref()),
+        };
+        let checkpoint = args.state.as_deref().map(|path| Checkpoint {
+            path,
+            definitions: replays_parser::state::definitions_key(&args.version, defs.fingerprint()),
+        });
+        if let Err(e) = emit_records(&inputs, &options, args.format, destination, checkpoint.as_ref(), args.batch_size) {
+            eprintln!("Error: {:#}", e);
+            drop(inputs);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Packet filters resolve entity types with the definitions, if any were loaded
+    let no_defs = Definitions::default();
+    let tracker_defs = defs.as_ref().unwrap_or(&no_defs);
+
     // For --stats mode, we need to collect results from parallel iteration
     if args.stats {
-        use std::sync::Mutex;
         // Key: (PacketType, SubType)
         let global_stats: Mutex<HashMap<(u32, Option<u32>), u64>> = Mutex::new(HashMap::new());
         let total_packets: Mutex<u64> = Mutex::new(0);
         let total_errors: Mutex<u64> = Mutex::new(0);
 
-        paths.par_iter().for_each(|path| {
-            match Parser::parse_file(path) {
+        inputs.par_iter().for_each(|input| {
+            let path = input.path();
+            match Parser::parse_input(input) {
                 Ok(replay) => {
                     use std::io::Cursor;
                     use byteorder::{ReadBytesExt, LittleEndian};
 
                     let mut cursor = Cursor::new(replay.packets_buffer.clone());
-                    let packet_stream = replays_parser::packet_stream::PacketStream::new(&mut cursor);
+                    let packet_stream = replays_parser::packet_stream::PacketStream::with_filter(&mut cursor, &packets);
+                    let mut tracker = replays_parser::entities::EntityTracker::new(tracker_defs);
 
                     let mut local_stats: HashMap<(u32, Option<u32>), u64> = HashMap::new();
                     let mut local_count: u64 = 0;
@@ -79,6 +1454,7 @@ fn main() {
 
                     for packet in packet_stream {
                         match packet {
+                            Ok(p) if !packets.matches(&p, tracker.handle(&p).as_ref()) => {}
                             Ok(p) => {
                                 let mut sub_type = None;
                                 
@@ -123,7 +1499,7 @@ fn main() {
         let errors = *total_errors.lock().unwrap();
 
         println!("\n=== Message Type Statistics ===");
-        println!("Total replays analyzed: {}", paths.len());
+        println!("Total replays analyzed: {}", inputs.len());
         println!("Total packets parsed: {}", packets);
         println!("Total packet errors: {}", errors);
         println!("\nPacket Type Distribution:");
@@ -175,8 +1551,9 @@ fn main() {
 
     } else {
         // Original behavior
-        paths.par_iter().for_each(|path| {
-            match Parser::parse_file(path) {
+        inputs.par_iter().for_each(|input| {
+            let path = input.path();
+            match Parser::parse_input(input) {
                 Ok(replay) => {
                     if args.json {
                         println!("{}", serde_json::to_string(&replay).unwrap());
@@ -209,7 +1586,10 @@ fn main() {
                         use byteorder::{ReadBytesExt, LittleEndian};
                         
                         let mut cursor = Cursor::new(replay.packets_buffer.clone());
-                        let packet_stream = replays_parser::packet_stream::PacketStream::new(&mut cursor);
+                        let packet_stream = replays_parser::packet_stream::PacketStream::with_filter(&mut cursor, &packets);
+                        let mut tracker = replays_parser::entities::EntityTracker::new(tracker_defs);
+                        let packet_stream = packet_stream
+                            .filter(|packet| packet.as_ref().map_or(true, |p| packets.matches(p, tracker.handle(p).as_ref())));
 
                         println!("  First 20 packets:");
                         for (i, packet) in packet_stream.enumerate().take(20) {
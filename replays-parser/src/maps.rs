@@ -0,0 +1,165 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+// Minimap grid labels: rows top to bottom (no "I"), columns left to right
+const GRID_ROWS: &[u8; 10] = b"ABCDEFGHJK";
+const GRID_COLUMNS: &[u8; 10] = b"1234567890";
+
+/// Playable area of a map in world coordinates. `x` runs west to east and
+/// `z` south to north, i.e. the ground plane seen on the minimap.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_x: f32,
+    pub min_z: f32,
+    pub max_x: f32,
+    pub max_z: f32,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f32 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max_z - self.min_z
+    }
+}
+
+// Ported from the reference wot-replay-tools project (replay_tools/src/maps.rs)
+const BOUNDS: &[(&str, BoundingBox)] = &[
+    ("01_karelia", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("02_malinovka", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("04_himmelsdorf", BoundingBox { min_x: -300.0, min_z: -300.0, max_x: 400.0, max_z: 400.0 }),
+    ("05_prohorovka", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("07_lakeville", BoundingBox { min_x: -400.0, min_z: -400.0, max_x: 400.0, max_z: 400.0 }),
+    ("06_ensk", BoundingBox { min_x: -300.0, min_z: -300.0, max_x: 300.0, max_z: 300.0 }),
+    ("11_murovanka", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("13_erlenberg", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("10_hills", BoundingBox { min_x: -400.0, min_z: -400.0, max_x: 400.0, max_z: 400.0 }),
+    ("18_cliff", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("19_monastery", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("28_desert", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("35_steppes", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("37_caucasus", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("33_fjord", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("34_redshire", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("36_fishing_bay", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("38_mannerheim_line", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("08_ruinberg", BoundingBox { min_x: -425.0, min_z: -400.0, max_x: 425.0, max_z: 450.0 }),
+    ("14_siegfried_line", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("23_westfeld", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("29_el_hallouf", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("31_airfield", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("17_munchen", BoundingBox { min_x: -300.0, min_z: -300.0, max_x: 300.0, max_z: 300.0 }),
+    ("44_north_america", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("45_north_america", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("47_canada_a", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("59_asia_great_wall", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("63_tundra", BoundingBox { min_x: -400.0, min_z: -400.0, max_x: 400.0, max_z: 400.0 }),
+    ("60_asia_miao", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("101_dday", BoundingBox { min_x: -400.0, min_z: -500.0, max_x: 600.0, max_z: 500.0 }),
+    ("114_czech", BoundingBox { min_x: -400.0, min_z: -500.0, max_x: 600.0, max_z: 500.0 }),
+    ("112_eiffel_tower_ctf", BoundingBox { min_x: -400.0, min_z: -400.0, max_x: 400.0, max_z: 400.0 }),
+    ("115_sweden", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("03_campania_big", BoundingBox { min_x: -400.0, min_z: -400.0, max_x: 450.0, max_z: 450.0 }),
+    ("212_epic_random_valley", BoundingBox { min_x: -700.0, min_z: -700.0, max_x: 700.0, max_z: 700.0 }),
+    ("217_er_alaska", BoundingBox { min_x: -700.0, min_z: -700.0, max_x: 700.0, max_z: 700.0 }),
+    ("99_poland", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("90_minsk", BoundingBox { min_x: -506.2189, min_z: -520.7199, max_x: 527.31134, max_z: 512.8103 }),
+    ("222_er_clime", BoundingBox { min_x: -700.0, min_z: -800.0, max_x: 700.0, max_z: 600.0 }),
+    ("250_br_battle_city2-1", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 1500.0, max_z: 1500.0 }),
+    ("95_lost_city_ctf", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("83_kharkiv", BoundingBox { min_x: -550.0, min_z: -500.0, max_x: 450.0, max_z: 500.0 }),
+    ("105_germany", BoundingBox { min_x: -525.0, min_z: -550.0, max_x: 525.0, max_z: 500.0 }),
+    ("208_bf_epic_normandy", BoundingBox { min_x: -1500.0, min_z: -1500.0, max_x: 1500.0, max_z: 1500.0 }),
+    ("251_br_battle_city3", BoundingBox { min_x: -1000.0, min_z: -1000.0, max_x: 1000.0, max_z: 1000.0 }),
+    ("252_br_battle_city4", BoundingBox { min_x: -1000.0, min_z: -1000.0, max_x: 1000.0, max_z: 1000.0 }),
+    ("127_japort", BoundingBox { min_x: -500.0, min_z: -500.0, max_x: 500.0, max_z: 500.0 }),
+    ("128_last_frontier_v", BoundingBox { min_x: -400.0, min_z: -600.0, max_x: 600.0, max_z: 400.0 }),
+    ("209_wg_epic_suburbia", BoundingBox { min_x: -1500.0, min_z: -1500.0, max_x: 1500.0, max_z: 1500.0 }),
+];
+
+/// A position on the minimap: `x` left to right and `y` top (north) to bottom, both in `[0, 1]`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MapPosition {
+    pub x: f32,
+    pub y: f32,
+    /// Minimap grid square, "A1" (top left) to "K0" (bottom right).
+    pub square: String,
+}
+
+/// Team bases and spawn points of one gameplay mode, keyed by team (1 or 2). Points are world `[x, z]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TeamPositions {
+    #[serde(default)]
+    pub bases: BTreeMap<u8, Vec<[f32; 2]>>,
+    #[serde(default)]
+    pub spawns: BTreeMap<u8, Vec<[f32; 2]>>,
+    /// Neutral base of encounter / assault style modes.
+    #[serde(default)]
+    pub control_point: Option<[f32; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapGeometry {
+    pub bounds: BoundingBox,
+    /// Keyed by `BattleConfig.gameplay_id` (`ctf`, `domination`, `assault`, ...).
+    #[serde(default)]
+    pub modes: BTreeMap<String, TeamPositions>,
+}
+
+impl MapGeometry {
+    /// Normalized minimap position of the world point `(x, z)`. Points outside the bounds are clamped.
+    pub fn normalize(&self, x: f32, z: f32) -> MapPosition {
+        let b = &self.bounds;
+        let nx = ((x - b.min_x) / b.width()).clamp(0.0, 1.0);
+        let ny = ((b.max_z - z) / b.height()).clamp(0.0, 1.0);
+        let cell = |v: f32| ((v * 10.0) as usize).min(9);
+        let square = format!("{}{}", GRID_ROWS[cell(ny)] as char, GRID_COLUMNS[cell(nx)] as char);
+        MapPosition { x: nx, y: ny, square }
+    }
+
+    pub fn team_positions(&self, gameplay_id: &str) -> Option<&TeamPositions> {
+        self.modes.get(gameplay_id)
+    }
+}
+
+/// Map geometry keyed by `BattleConfig.map_name` (e.g. `04_himmelsdorf`).
+///
+/// The built-in catalog only knows bounds. Bases and spawns come from the
+/// game's `arena_defs`, which are not redistributable; export them to JSON in
+/// the `MapGeometry` layout and merge them with `load_json`.
+#[derive(Debug, Clone, Default)]
+pub struct MapCatalog {
+    maps: HashMap<String, MapGeometry>,
+}
+
+impl MapCatalog {
+    pub fn builtin() -> Self {
+        let maps = BOUNDS
+            .iter()
+            .map(|(name, bounds)| (name.to_string(), MapGeometry { bounds: *bounds, modes: BTreeMap::new() }))
+            .collect();
+        Self { maps }
+    }
+
+    /// Merges a JSON object of `{ "<map_name>": MapGeometry }`, replacing built-in entries.
+    pub fn load_json(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let maps: HashMap<String, MapGeometry> =
+            serde_json::from_str(&content).with_context(|| format!("Failed to parse map catalog {}", path.display()))?;
+        self.maps.extend(maps);
+        Ok(())
+    }
+
+    pub fn get(&self, map_name: &str) -> Option<&MapGeometry> {
+        self.maps.get(map_name)
+    }
+
+    pub fn map_names(&self) -> impl Iterator<Item = &str> {
+        self.maps.keys().map(String::as_str)
+    }
+}
@@ -0,0 +1,144 @@
+//! Joint position timeline of several replays of one battle (`merge-perspectives`).
+//!
+//! Each replay only has precise positions for the vehicles its recorder could see.
+//! Their union is denser ground truth than any single perspective: clocks are
+//! aligned on the server time the battle period started, and a minimap sample is
+//! dropped wherever some perspective has a precise ENTITY_MOVE sample of the same vehicle.
+
+use crate::arena::Period;
+use crate::events::{Event, EventLog, TimedEvent};
+use crate::maps::MapGeometry;
+use crate::positions::{minimap_positions, PositionSample, PositionSource};
+use crate::types::BattleConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Default for `merge_perspectives`' `window`: about the interval of minimap updates.
+pub const PRECISE_WINDOW: f32 = 1.0;
+
+/// One replay of the battle, decoded.
+pub struct Perspective<'a> {
+    /// Replay file name, copied to `Observation::source_replay`.
+    pub replay: &'a str,
+    pub config: &'a BattleConfig,
+    pub log: &'a EventLog,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PerspectiveInfo {
+    pub replay: String,
+    pub player: String,
+    pub vehicle: Option<u32>,
+    /// Replay time the battle period started at on the server, subtracted from all its observations.
+    pub battle_start: f32,
+}
+
+/// One position of a vehicle, as seen by one of the replays.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Observation {
+    /// Seconds since the battle period started; negative during the countdown.
+    pub time: f32,
+    pub source_replay: String,
+    #[serde(flatten)]
+    pub sample: PositionSample,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MergedTimeline {
+    pub perspectives: Vec<PerspectiveInfo>,
+    /// Replays left out because their clock cannot be tied to server time, e.g.
+    /// recorded after a reconnect without any period change or stun to go by.
+    pub unaligned: Vec<String>,
+    pub observations: Vec<Observation>,
+}
+
+/// Merges the positions of `perspectives` into one timeline sorted by time.
+///
+/// A minimap sample is kept only if no perspective has an ENTITY_MOVE sample of
+/// the same vehicle within `window` seconds of it. Precise samples are all kept,
+/// even when several recorders saw the vehicle at once. `map` fills in the
+/// normalized minimap coordinates.
+pub fn merge_perspectives(perspectives: &[Perspective], map: Option<&MapGeometry>, window: f32) -> MergedTimeline {
+    let mut merged = MergedTimeline::default();
+    for perspective in perspectives {
+        let battle_start = perspective.log.events.iter().find_map(|e| match e.event {
+            Event::ArenaPeriod { period: Period::Battle, end_time, length } if end_time > 0.0 => Some(end_time - length),
+            _ => None,
+        });
+        let (Some(battle_start), Some(offset)) = (battle_start, server_offset(perspective.log)) else {
+            merged.unaligned.push(perspective.replay.to_string());
+            continue;
+        };
+        let battle_start = (battle_start - offset) as f32;
+        merged.perspectives.push(PerspectiveInfo {
+            replay: perspective.replay.to_string(),
+            player: perspective.config.player_name.clone(),
+            vehicle: perspective.config.player_vehicle_id(),
+            battle_start,
+        });
+
+        let minimap = minimap_positions(perspective.log, perspective.config);
+        for timed in perspective.log.events.iter().chain(&minimap) {
+            if let Event::Position(sample) = &timed.event {
+                let mut sample = sample.clone();
+                if let Some(map) = map {
+                    sample.map = Some(map.normalize(sample.x, sample.z));
+                }
+                merged.observations.push(Observation {
+                    time: timed.time - battle_start,
+                    source_replay: perspective.replay.to_string(),
+                    sample,
+                });
+            }
+        }
+    }
+
+    let mut precise: HashMap<u32, Vec<f32>> = HashMap::new();
+    for observation in &merged.observations {
+        if observation.sample.source == PositionSource::EntityMove {
+            precise.entry(observation.sample.vehicle).or_default().push(observation.time);
+        }
+    }
+    for times in precise.values_mut() {
+        times.sort_by(f32::total_cmp);
+    }
+    merged.observations.retain(|observation| {
+        if observation.sample.source == PositionSource::EntityMove {
+            return true;
+        }
+        let Some(times) = precise.get(&observation.sample.vehicle) else {
+            return true;
+        };
+        // The precise samples closest before and after the minimap sample
+        let next = times.partition_point(|&t| t < observation.time);
+        let near = |i: usize| times.get(i).is_some_and(|&t| (t - observation.time).abs() <= window);
+        !(near(next) || (next > 0 && near(next - 1)))
+    });
+    merged.observations.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.sample.vehicle.cmp(&b.sample.vehicle)));
+    merged
+}
+
+/// Server time minus replay time, from the messages that tell the server time they
+/// were sent at: a period update that follows another one (the period started as it
+/// arrived; the first one may have been delivered on joining) and a stun with its
+/// duration. Each arrives after it was sent, so the largest estimate is the closest.
+fn server_offset(log: &EventLog) -> Option<f64> {
+    let mut previous = None;
+    let mut offset: Option<f64> = None;
+    for TimedEvent { time, event } in &log.events {
+        let estimate = match *event {
+            Event::ArenaPeriod { period, end_time, length } if end_time > 0.0 => {
+                let started = previous.is_some_and(|previous| previous != period);
+                previous = Some(period);
+                started.then_some(end_time - length)
+            }
+            Event::StunInfo { finish_time, duration: Some(duration), .. } if finish_time > 0.0 => Some(finish_time - duration as f64),
+            _ => None,
+        };
+        if let Some(sent) = estimate {
+            let estimate = sent - *time as f64;
+            offset = Some(offset.map_or(estimate, |offset| offset.max(estimate)));
+        }
+    }
+    offset
+}
@@ -0,0 +1,183 @@
+use crate::arena::{self, DeathReason};
+use crate::bigworld::ArgReader;
+use crate::entities::MethodCall;
+use crate::events::{Event, EventLog, TimedEvent};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// VEHICLE_SETTING codes from the client's constants.py
+const CURRENT_SHELLS: u8 = 0;
+const NEXT_SHELLS: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShellSlot {
+    /// The shell type in the breech (or being loaded).
+    Current,
+    /// The shell type selected for the next reload.
+    Next,
+}
+
+/// Avatar.updateTargetingInfo: gun position and the factors driving dispersion and aiming time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TargetingInfo {
+    pub turret_yaw: f32,
+    pub gun_pitch: f32,
+    pub max_turret_rotation_speed: f32,
+    pub max_gun_rotation_speed: f32,
+    pub shot_disp_multiplier_factor: f32,
+    pub gun_shot_dispersion_factors_turret_rotation: f32,
+    pub chassis_shot_dispersion_factors_movement: f32,
+    pub chassis_shot_dispersion_factors_rotation: f32,
+    pub aiming_time: f32,
+}
+
+/// A value of the recording player's vehicle at a point in replay time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimedValue<T> {
+    pub time: f32,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ShellCount {
+    pub quantity: u16,
+    pub quantity_in_clip: u8,
+}
+
+/// Private telemetry of the recording player's own vehicle over time.
+///
+/// Only the recording client receives these methods, so every replay carries
+/// exactly one perspective's worth of them.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OwnVehicleTimeline {
+    /// Shell counts per shell compact descriptor.
+    pub ammo: BTreeMap<u32, Vec<TimedValue<ShellCount>>>,
+    pub loaded_shell: Vec<TimedValue<u32>>,
+    pub next_shell: Vec<TimedValue<u32>>,
+    pub health: Vec<TimedValue<i16>>,
+    /// Auto-aim target lock; `None` when the lock is released.
+    pub target: Vec<TimedValue<Option<u32>>>,
+    pub targeting: Vec<TimedValue<TargetingInfo>>,
+    pub private_stats: Vec<TimedValue<serde_json::Value>>,
+    pub quick_shell_changer: Vec<TimedValue<bool>>,
+}
+
+impl OwnVehicleTimeline {
+    /// Shells left per type at `time`.
+    pub fn shells_at(&self, time: f32) -> BTreeMap<u32, u16> {
+        self.ammo
+            .iter()
+            .filter_map(|(shell, counts)| Some((*shell, value_at(counts, time)?.quantity)))
+            .collect()
+    }
+
+    pub fn loaded_shell_at(&self, time: f32) -> Option<u32> {
+        value_at(&self.loaded_shell, time).copied()
+    }
+
+    pub fn targeting_at(&self, time: f32) -> Option<&TargetingInfo> {
+        value_at(&self.targeting, time)
+    }
+}
+
+/// Latest value at or before `time`; samples are in replay order.
+fn value_at<T>(samples: &[TimedValue<T>], time: f32) -> Option<&T> {
+    let end = samples.partition_point(|s| s.time <= time);
+    end.checked_sub(1).map(|index| &samples[index].value)
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    if call.entity != "Avatar" {
+        return Ok(None);
+    }
+    let mut args = ArgReader::new(call.args);
+    let event = match call.method {
+        // updateVehicleAmmo(vehicleID, compactDescr, quantity, quantityInClip, previousStage, timeRemaining, totalTime)
+        "updateVehicleAmmo" => Event::VehicleAmmo {
+            vehicle: args.read_entity_id()?,
+            shell: args.read_i32()? as u32,
+            quantity: args.read_u16()?,
+            quantity_in_clip: args.read_u8()?,
+        },
+        // updateVehicleSetting(vehicleID, code, value)
+        "updateVehicleSetting" => {
+            let vehicle = args.read_entity_id()?;
+            let slot = match args.read_u8()? {
+                CURRENT_SHELLS => ShellSlot::Current,
+                NEXT_SHELLS => ShellSlot::Next,
+                _ => return Ok(None),
+            };
+            Event::ShellSelected { vehicle, slot, shell: args.read_i32()? as u32 }
+        }
+        // updateVehicleHealth(vehicleID, health, deathReasonID, isCrewActive, isRespawn)
+        "updateVehicleHealth" => {
+            let vehicle = args.read_entity_id()?;
+            let health = args.read_i16()?;
+            let death_reason = args.read_i8()?;
+            Event::VehicleHealth {
+                vehicle,
+                health,
+                death_reason: (death_reason >= 0).then(|| DeathReason::from(death_reason as i64)),
+                is_crew_active: args.read_bool()?,
+            }
+        }
+        "updateTargetingInfo" => Event::TargetingInfo(TargetingInfo {
+            turret_yaw: args.read_f32()?,
+            gun_pitch: args.read_f32()?,
+            max_turret_rotation_speed: args.read_f32()?,
+            max_gun_rotation_speed: args.read_f32()?,
+            shot_disp_multiplier_factor: args.read_f32()?,
+            gun_shot_dispersion_factors_turret_rotation: args.read_f32()?,
+            chassis_shot_dispersion_factors_movement: args.read_f32()?,
+            chassis_shot_dispersion_factors_rotation: args.read_f32()?,
+            aiming_time: args.read_f32()?,
+        }),
+        "updateTargetVehicleID" => {
+            let target = args.read_entity_id()?;
+            Event::TargetLock { target: (target != 0).then_some(target) }
+        }
+        // updateAvatarPrivateStats(stats): a pickled dict
+        "updateAvatarPrivateStats" => Event::PrivateStats {
+            stats: arena::unpickle(args.read_blob()?)?.to_json(),
+        },
+        // updateVehicleQuickShellChanger(vehicleID, isActive)
+        "updateVehicleQuickShellChanger" => Event::QuickShellChanger {
+            vehicle: args.read_entity_id()?,
+            active: args.read_bool()?,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/// Collects the own-vehicle telemetry of one replay.
+pub fn own_vehicle_timeline(log: &EventLog) -> OwnVehicleTimeline {
+    let mut timeline = OwnVehicleTimeline::default();
+    for TimedEvent { time, event } in &log.events {
+        let time = *time;
+        match event {
+            Event::VehicleAmmo { shell, quantity, quantity_in_clip, .. } => {
+                let value = ShellCount { quantity: *quantity, quantity_in_clip: *quantity_in_clip };
+                timeline.ammo.entry(*shell).or_default().push(TimedValue { time, value });
+            }
+            Event::ShellSelected { slot, shell, .. } => {
+                let samples = match slot {
+                    ShellSlot::Current => &mut timeline.loaded_shell,
+                    ShellSlot::Next => &mut timeline.next_shell,
+                };
+                samples.push(TimedValue { time, value: *shell });
+            }
+            Event::VehicleHealth { health, .. } => timeline.health.push(TimedValue { time, value: *health }),
+            Event::TargetLock { target } => timeline.target.push(TimedValue { time, value: *target }),
+            Event::TargetingInfo(info) => timeline.targeting.push(TimedValue { time, value: *info }),
+            Event::PrivateStats { stats } => timeline.private_stats.push(TimedValue { time, value: stats.clone() }),
+            Event::QuickShellChanger { active, .. } => {
+                timeline.quick_shell_changer.push(TimedValue { time, value: *active });
+            }
+            _ => {}
+        }
+    }
+    timeline
+}
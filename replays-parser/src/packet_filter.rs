@@ -0,0 +1,108 @@
+//! Packet filters (`--packet-types`, `--entity-types`, `--methods`, `--time`).
+//!
+//! Packet type and time are known from the 12-byte packet header, so
+//! `PacketStream::with_filter` skips the payload of packets failing them without
+//! reading it. Entity creations and leaves are always read whatever the filter,
+//! since the `EntityTracker` cannot resolve later packets of an entity it never
+//! saw created; `PacketFilter::matches` then checks the resolved message.
+
+use crate::entities::{EntityMessage, BASE_PLAYER_CREATE, ENTITY_CREATE, ENTITY_LEAVE};
+use crate::packet_stream::Packet;
+use anyhow::{Context, Result};
+use globset::GlobSet;
+use std::str::FromStr;
+
+/// Replay-clock window `start..end`, in seconds; either bound may be left out.
+/// The start is inclusive, the end exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeRange {
+    pub start: Option<f32>,
+    pub end: Option<f32>,
+}
+
+impl TimeRange {
+    pub fn contains(&self, time: f32) -> bool {
+        self.start.is_none_or(|start| time >= start) && self.end.is_none_or(|end| time < end)
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = anyhow::Error;
+
+    /// `120..180`, `120..` or `..180`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (start, end) = s.split_once("..").with_context(|| format!("Invalid time range '{}', expected START..END", s))?;
+        let bound = |b: &str| -> Result<Option<f32>> {
+            match b.trim() {
+                "" => Ok(None),
+                b => Ok(Some(b.parse().with_context(|| format!("Invalid time '{}'", b))?)),
+            }
+        };
+        Ok(TimeRange { start: bound(start)?, end: bound(end)? })
+    }
+}
+
+/// Parses a packet type: hexadecimal with `0x` (`0x08`) or decimal (`8`).
+pub fn parse_packet_type(s: &str) -> Result<u32> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .with_context(|| format!("Invalid packet type '{}'", s))
+}
+
+/// Which packets to keep; a packet is kept when it passes every filter that is set.
+#[derive(Debug, Clone, Default)]
+pub struct PacketFilter {
+    pub packet_types: Vec<u32>,
+    /// Entity type names (`Vehicle`, `Avatar`); keeps only packets about an entity of these types.
+    pub entity_types: Vec<String>,
+    /// Globs on `<Entity>.<method>` (`Vehicle.*Damage*`); keeps only method calls (0x08) matching one.
+    pub methods: Option<GlobSet>,
+    pub time: Option<TimeRange>,
+}
+
+impl PacketFilter {
+    pub fn is_empty(&self) -> bool {
+        self.packet_types.is_empty() && self.entity_types.is_empty() && self.methods.is_none() && self.time.is_none()
+    }
+
+    /// Whether a packet with this header passes the packet type and time filters.
+    pub fn accepts_header(&self, packet_type: u32, time: f32) -> bool {
+        (self.packet_types.is_empty() || self.packet_types.contains(&packet_type)) && self.time.is_none_or(|range| range.contains(time))
+    }
+
+    /// Whether the payload of a packet with this header must be read: it may match,
+    /// or it keeps the tracker's entity types current.
+    pub fn reads(&self, packet_type: u32, time: f32) -> bool {
+        self.accepts_header(packet_type, time) || matches!(packet_type, BASE_PLAYER_CREATE | ENTITY_CREATE | ENTITY_LEAVE)
+    }
+
+    /// Whether `packet` passes every filter; `message` is what `EntityTracker::handle` resolved it to.
+    pub fn matches(&self, packet: &Packet, message: Option<&EntityMessage>) -> bool {
+        if !self.accepts_header(packet.packet_type, packet.time) {
+            return false;
+        }
+        if !self.entity_types.is_empty() {
+            let entity = message.map(|message| match message {
+                EntityMessage::Method(call) => call.entity,
+                EntityMessage::Property(update) => update.entity,
+                EntityMessage::Entered(presence) | EntityMessage::Left(presence) => presence.entity,
+                EntityMessage::Moved(movement) => movement.entity,
+            });
+            if !entity.is_some_and(|entity| self.entity_types.iter().any(|t| t == entity)) {
+                return false;
+            }
+        }
+        if let Some(methods) = &self.methods {
+            let Some(EntityMessage::Method(call)) = message else {
+                return false;
+            };
+            if !methods.is_match(format!("{}.{}", call.entity, call.method)) {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -1,3 +1,4 @@
+use crate::packet_filter::PacketFilter;
 use anyhow::{anyhow, Result};
 use byteorder::{ReadBytesExt, LittleEndian};
 use std::io::{Cursor, Read};
@@ -12,11 +13,25 @@ pub struct Packet {
 
 pub struct PacketStream<'a> {
     reader: &'a mut Cursor<Vec<u8>>,
+    filter: Option<&'a PacketFilter>,
+    end_time: f32,
 }
 
 impl<'a> PacketStream<'a> {
     pub fn new(reader: &'a mut Cursor<Vec<u8>>) -> Self {
-        Self { reader }
+        Self { reader, filter: None, end_time: 0.0 }
+    }
+
+    /// A stream that skips, without reading their payload, the packets whose header
+    /// `filter` rules out (see `PacketFilter::reads`). Entity creations and leaves are
+    /// always yielded for the `EntityTracker`, so check packets with `PacketFilter::matches`.
+    pub fn with_filter(reader: &'a mut Cursor<Vec<u8>>, filter: &'a PacketFilter) -> Self {
+        Self { reader, filter: Some(filter), end_time: 0.0 }
+    }
+
+    /// Latest time of the complete packets read so far, skipped packets included.
+    pub fn end_time(&self) -> f32 {
+        self.end_time
     }
 }
 
@@ -24,19 +39,23 @@ impl<'a> Iterator for PacketStream<'a> {
     type Item = Result<Packet>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.reader.position() >= self.reader.get_ref().len() as u64 {
-            return None;
-        }
+        loop {
+            if self.reader.position() >= self.reader.get_ref().len() as u64 {
+                return None;
+            }
 
-        match self.read_packet() {
-            Ok(packet) => Some(Ok(packet)),
-            Err(e) => Some(Err(e)),
+            match self.read_packet() {
+                Ok(Some(packet)) => return Some(Ok(packet)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            }
         }
     }
 }
 
 impl<'a> PacketStream<'a> {
-    fn read_packet(&mut self) -> Result<Packet> {
+    /// The next packet, or `None` if the filter skipped it.
+    fn read_packet(&mut self) -> Result<Option<Packet>> {
         // Basic packet structure (based on assumptions/common WoT formats, needs verification against wotdecoder)
         // Usually: Length (4 bytes) + Type (4 bytes) + Time (4 bytes) + Payload
         
@@ -44,14 +63,27 @@ impl<'a> PacketStream<'a> {
         let packet_type = self.reader.read_u32::<LittleEndian>()?;
         let time = self.reader.read_f32::<LittleEndian>()?;
 
+        if let Some(filter) = self.filter
+            && !filter.reads(packet_type, time)
+        {
+            let end = self.reader.position() + payload_len as u64;
+            if end > self.reader.get_ref().len() as u64 {
+                return Err(anyhow!("Packet of {} bytes runs past the end of the stream", payload_len));
+            }
+            self.reader.set_position(end);
+            self.end_time = self.end_time.max(time);
+            return Ok(None);
+        }
+
         let mut payload = vec![0u8; payload_len as usize];
         self.reader.read_exact(&mut payload)?;
+        self.end_time = self.end_time.max(time);
 
-        Ok(Packet {
+        Ok(Some(Packet {
             payload,
             packet_type,
             time,
             length: payload_len + 12, // storing total length including header for debug/consistency
-        })
+        }))
     }
 }
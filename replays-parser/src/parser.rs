@@ -1,3 +1,4 @@
+use crate::inputs::Input;
 use crate::types::{BattleConfig, Replay, ReplayHeader};
 use anyhow::{anyhow, Context, Result};
 use byteorder::{ReadBytesExt, LittleEndian};
@@ -5,8 +6,8 @@ use std::io::Read;
 use std::path::Path;
 use std::{fs::File, io::Cursor};
 
-pub struct Parser {
-    reader: Cursor<Vec<u8>>, 
+pub struct Parser<R = Cursor<Vec<u8>>> {
+    reader: R,
 }
 
 impl Parser {
@@ -14,14 +15,48 @@ impl Parser {
         let mut file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
+        Self::parse_bytes(buffer)
+    }
+
+    /// Parses a replay from a file, an archive member or stdin.
+    pub fn parse_input(input: &Input) -> Result<Replay> {
+        Self::parse_bytes(input.read()?)
+    }
 
+    pub fn parse_bytes(buffer: Vec<u8>) -> Result<Replay> {
         let mut parser = Parser {
             reader: Cursor::new(buffer),
         };
         parser.parse()
     }
 
+    /// Reads only the magic, `BattleConfig` and optional BattleResults, stopping before
+    /// the binary block: the returned `Replay` has an empty `packets_buffer`.
+    ///
+    /// Nothing is decrypted or inflated, and only the JSON blocks at the start of
+    /// `reader` are read, so scanning a corpus costs little more than listing it.
+    pub fn parse_header(reader: impl Read) -> Result<Replay> {
+        let (header, battle_config, battle_results) = Parser { reader }.read_json_blocks()?;
+        Ok(Replay { header, battle_config, battle_results, packets_buffer: Vec::new() })
+    }
+}
+
+impl<R: Read> Parser<R> {
     pub fn parse(&mut self) -> Result<Replay> {
+        let (header, battle_config, battle_results) = self.read_json_blocks()?;
+
+        // The binary block is always at the end.
+        let packets_buffer = self.read_binary_block()?;
+
+        Ok(Replay {
+            header,
+            battle_config,
+            battle_results,
+            packets_buffer,
+        })
+    }
+
+    fn read_json_blocks(&mut self) -> Result<(ReplayHeader, BattleConfig, Option<serde_json::Value>)> {
         let magic = self.read_magic()?;
         let block_count = self.read_block_count()?;
         
@@ -39,16 +74,7 @@ impl Parser {
                  // We can either warn or continue. For now, let's treat it as optional if it fails.
              }
         }
-
-        // The binary block is always at the end.
-        let packets_buffer = self.read_binary_block()?;
-
-        Ok(Replay {
-            header: ReplayHeader { magic, block_count },
-            battle_config,
-            battle_results,
-            packets_buffer,
-        })
+        Ok((ReplayHeader { magic, block_count }, battle_config, battle_results))
     }
 
     fn read_magic(&mut self) -> Result<u32> {
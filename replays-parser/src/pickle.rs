@@ -0,0 +1,389 @@
+use anyhow::{anyhow, Context, Result};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::collections::HashMap;
+
+/// A value decoded from a Python 2 pickle (protocols 0-2, as written by cPickle).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PickleValue {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// Python 2 `str` (raw bytes) and `unicode` both end up here.
+    String(String),
+    Tuple(Vec<PickleValue>),
+    List(Vec<PickleValue>),
+    Dict(Vec<(PickleValue, PickleValue)>),
+    /// `module.name` reference from GLOBAL.
+    Global(String),
+    /// Result of REDUCE / NEWOBJ / BUILD that we cannot evaluate, kept for inspection.
+    Object { callable: Box<PickleValue>, args: Box<PickleValue> },
+}
+
+impl PickleValue {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            PickleValue::Int(i) => Some(*i),
+            PickleValue::Bool(b) => Some(*b as i64),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PickleValue::Float(f) => Some(*f),
+            PickleValue::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PickleValue::Bool(b) => Some(*b),
+            PickleValue::Int(i) => Some(*i != 0),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PickleValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Elements of a tuple or list.
+    pub fn as_seq(&self) -> Option<&[PickleValue]> {
+        match self {
+            PickleValue::Tuple(items) | PickleValue::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Looks up a string key in a dict.
+    pub fn get(&self, key: &str) -> Option<&PickleValue> {
+        match self {
+            PickleValue::Dict(entries) => entries.iter().find(|(k, _)| k.as_str() == Some(key)).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Converts to JSON. Tuples become arrays and non-string dict keys are stringified.
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value;
+        match self {
+            PickleValue::None => Value::Null,
+            PickleValue::Bool(b) => Value::Bool(*b),
+            PickleValue::Int(i) => Value::from(*i),
+            PickleValue::Float(f) => Value::from(*f),
+            PickleValue::String(s) | PickleValue::Global(s) => Value::String(s.clone()),
+            PickleValue::Tuple(items) | PickleValue::List(items) => Value::Array(items.iter().map(|v| v.to_json()).collect()),
+            PickleValue::Dict(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| {
+                        let key = match k {
+                            PickleValue::String(s) => s.clone(),
+                            other => other.to_json().to_string(),
+                        };
+                        (key, v.to_json())
+                    })
+                    .collect(),
+            ),
+            PickleValue::Object { callable, args } => serde_json::json!({
+                "callable": callable.to_json(),
+                "args": args.to_json(),
+            }),
+        }
+    }
+}
+
+// Stack entries: a MARK or a value; a memoized value keeps its memo indices, so that
+// changes made to it on the stack (APPEND, SETITEM, BUILD) reach the memo too
+enum Item {
+    Mark,
+    Value(PickleValue),
+    Memoized(PickleValue, Vec<u32>),
+}
+
+/// Decodes a pickle. Arbitrary objects are never instantiated, only described.
+pub fn loads(data: &[u8]) -> Result<PickleValue> {
+    let mut stack: Vec<Item> = Vec::new();
+    let mut memo: HashMap<u32, PickleValue> = HashMap::new();
+    let mut pos = 0usize;
+
+    let take = |pos: &mut usize, n: usize| -> Result<&[u8]> {
+        let slice = data.get(*pos..*pos + n).ok_or_else(|| anyhow!("Pickle truncated at offset {}", *pos))?;
+        *pos += n;
+        Ok(slice)
+    };
+    let line = |pos: &mut usize| -> Result<String> {
+        let rest = data.get(*pos..).unwrap_or_default();
+        let end = rest.iter().position(|b| *b == b'\n').ok_or_else(|| anyhow!("Pickle line not terminated"))?;
+        *pos += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    };
+
+    loop {
+        let opcode = *take(&mut pos, 1)?.first().unwrap();
+        match opcode {
+            0x80 => {
+                take(&mut pos, 1)?; // PROTO version
+            }
+            b'.' => break, // STOP
+            b'(' => stack.push(Item::Mark),
+            b'N' => stack.push(Item::Value(PickleValue::None)),
+            0x88 => stack.push(Item::Value(PickleValue::Bool(true))),
+            0x89 => stack.push(Item::Value(PickleValue::Bool(false))),
+            b'J' => stack.push(Item::Value(PickleValue::Int(LittleEndian::read_i32(take(&mut pos, 4)?) as i64))),
+            b'K' => stack.push(Item::Value(PickleValue::Int(take(&mut pos, 1)?[0] as i64))),
+            b'M' => stack.push(Item::Value(PickleValue::Int(LittleEndian::read_u16(take(&mut pos, 2)?) as i64))),
+            0x8a | 0x8b => {
+                // LONG1 / LONG4: little-endian two's complement of the given length
+                let n = if opcode == 0x8a {
+                    take(&mut pos, 1)?[0] as usize
+                } else {
+                    LittleEndian::read_u32(take(&mut pos, 4)?) as usize
+                };
+                let bytes = take(&mut pos, n)?;
+                if n > 8 {
+                    return Err(anyhow!("Pickle long of {} bytes does not fit in i64", n));
+                }
+                let mut value: i64 = 0;
+                for (i, b) in bytes.iter().enumerate() {
+                    value |= (*b as i64) << (8 * i);
+                }
+                if n > 0 && n < 8 && bytes[n - 1] & 0x80 != 0 {
+                    value -= 1i64 << (8 * n);
+                }
+                stack.push(Item::Value(PickleValue::Int(value)));
+            }
+            b'I' | b'L' => {
+                let text = line(&mut pos)?;
+                let text = text.trim_end_matches('L');
+                let value = match text {
+                    "00" => PickleValue::Bool(false),
+                    "01" => PickleValue::Bool(true),
+                    _ => PickleValue::Int(text.parse().with_context(|| format!("Bad pickle int {:?}", text))?),
+                };
+                stack.push(Item::Value(value));
+            }
+            b'G' => stack.push(Item::Value(PickleValue::Float(BigEndian::read_f64(take(&mut pos, 8)?)))),
+            b'F' => {
+                let text = line(&mut pos)?;
+                stack.push(Item::Value(PickleValue::Float(text.parse().with_context(|| format!("Bad pickle float {:?}", text))?)));
+            }
+            b'U' | b'T' | b'X' => {
+                let n = match opcode {
+                    b'U' => take(&mut pos, 1)?[0] as usize,
+                    _ => LittleEndian::read_u32(take(&mut pos, 4)?) as usize,
+                };
+                let bytes = take(&mut pos, n)?;
+                stack.push(Item::Value(PickleValue::String(String::from_utf8_lossy(bytes).into_owned())));
+            }
+            b'S' | b'V' => {
+                let text = line(&mut pos)?;
+                let text = text.trim_matches(|c| c == '\'' || c == '"').to_string();
+                stack.push(Item::Value(PickleValue::String(text)));
+            }
+            b')' => stack.push(Item::Value(PickleValue::Tuple(Vec::new()))),
+            b']' => stack.push(Item::Value(PickleValue::List(Vec::new()))),
+            b'}' => stack.push(Item::Value(PickleValue::Dict(Vec::new()))),
+            0x85..=0x87 => {
+                let n = (opcode - 0x84) as usize;
+                let items = pop_n(&mut stack, n)?;
+                stack.push(Item::Value(PickleValue::Tuple(items)));
+            }
+            b't' => {
+                let items = pop_mark(&mut stack)?;
+                stack.push(Item::Value(PickleValue::Tuple(items)));
+            }
+            b'l' => {
+                let items = pop_mark(&mut stack)?;
+                stack.push(Item::Value(PickleValue::List(items)));
+            }
+            b'd' => {
+                let items = pop_mark(&mut stack)?;
+                stack.push(Item::Value(PickleValue::Dict(pairs(items)?)));
+            }
+            b'a' => {
+                let item = pop(&mut stack)?;
+                extend(&mut stack, vec![item])?;
+                sync_memo(&stack, &mut memo);
+            }
+            b'e' => {
+                let items = pop_mark(&mut stack)?;
+                extend(&mut stack, items)?;
+                sync_memo(&stack, &mut memo);
+            }
+            b's' => {
+                let items = pop_n(&mut stack, 2)?;
+                extend(&mut stack, items)?;
+                sync_memo(&stack, &mut memo);
+            }
+            b'u' => {
+                let items = pop_mark(&mut stack)?;
+                extend(&mut stack, items)?;
+                sync_memo(&stack, &mut memo);
+            }
+            b'q' | b'r' | b'p' => {
+                let index = match opcode {
+                    b'q' => take(&mut pos, 1)?[0] as u32,
+                    b'r' => LittleEndian::read_u32(take(&mut pos, 4)?),
+                    _ => line(&mut pos)?.parse().context("Bad pickle PUT index")?,
+                };
+                if let Some(item) = stack.last_mut() {
+                    *item = match std::mem::replace(item, Item::Mark) {
+                        Item::Value(value) => Item::Memoized(value, vec![index]),
+                        Item::Memoized(value, mut indices) => {
+                            indices.push(index);
+                            Item::Memoized(value, indices)
+                        }
+                        Item::Mark => Item::Mark,
+                    };
+                    sync_memo(&stack, &mut memo);
+                }
+            }
+            b'h' | b'j' | b'g' => {
+                let index = match opcode {
+                    b'h' => take(&mut pos, 1)?[0] as u32,
+                    b'j' => LittleEndian::read_u32(take(&mut pos, 4)?),
+                    _ => line(&mut pos)?.parse().context("Bad pickle GET index")?,
+                };
+                let value = memo.get(&index).cloned().ok_or_else(|| anyhow!("Pickle memo {} missing", index))?;
+                stack.push(Item::Memoized(value, vec![index]));
+            }
+            b'c' => {
+                let module = line(&mut pos)?;
+                let name = line(&mut pos)?;
+                stack.push(Item::Value(PickleValue::Global(format!("{}.{}", module, name))));
+            }
+            b'R' | 0x81 => {
+                let args = pop(&mut stack)?;
+                let callable = pop(&mut stack)?;
+                stack.push(Item::Value(reduce(callable, args)));
+            }
+            b'b' => {
+                // BUILD: keep the state alongside the object
+                let state = pop(&mut stack)?;
+                let (object, indices) = pop_memoized(&mut stack)?;
+                let object = PickleValue::Object {
+                    callable: Box::new(object),
+                    args: Box::new(state),
+                };
+                stack.push(if indices.is_empty() { Item::Value(object) } else { Item::Memoized(object, indices) });
+                sync_memo(&stack, &mut memo);
+            }
+            b'0' => {
+                stack.pop();
+            }
+            b'1' => {
+                pop_mark(&mut stack)?;
+            }
+            b'2' => {
+                let top = match stack.last() {
+                    Some(Item::Value(value) | Item::Memoized(value, _)) => value.clone(),
+                    _ => return Err(anyhow!("Pickle DUP on empty stack")),
+                };
+                stack.push(Item::Value(top));
+            }
+            other => return Err(anyhow!("Unsupported pickle opcode 0x{:02X} at offset {}", other, pos - 1)),
+        }
+    }
+
+    pop(&mut stack)
+}
+
+fn pop(stack: &mut Vec<Item>) -> Result<PickleValue> {
+    pop_memoized(stack).map(|(value, _)| value)
+}
+
+/// Pops a value with the memo indices it is stored under.
+fn pop_memoized(stack: &mut Vec<Item>) -> Result<(PickleValue, Vec<u32>)> {
+    match stack.pop() {
+        Some(Item::Value(value)) => Ok((value, Vec::new())),
+        Some(Item::Memoized(value, indices)) => Ok((value, indices)),
+        _ => Err(anyhow!("Pickle stack underflow")),
+    }
+}
+
+/// Copies the value on top of the stack to the memo entries it is stored under.
+fn sync_memo(stack: &[Item], memo: &mut HashMap<u32, PickleValue>) {
+    if let Some(Item::Memoized(value, indices)) = stack.last() {
+        for index in indices {
+            memo.insert(*index, value.clone());
+        }
+    }
+}
+
+fn pop_n(stack: &mut Vec<Item>, n: usize) -> Result<Vec<PickleValue>> {
+    let mut items = Vec::with_capacity(n);
+    for _ in 0..n {
+        items.push(pop(stack)?);
+    }
+    items.reverse();
+    Ok(items)
+}
+
+fn pop_mark(stack: &mut Vec<Item>) -> Result<Vec<PickleValue>> {
+    let mut items = Vec::new();
+    loop {
+        match stack.pop() {
+            Some(Item::Value(value) | Item::Memoized(value, _)) => items.push(value),
+            Some(Item::Mark) => break,
+            None => return Err(anyhow!("Pickle MARK not found")),
+        }
+    }
+    items.reverse();
+    Ok(items)
+}
+
+fn pairs(items: Vec<PickleValue>) -> Result<Vec<(PickleValue, PickleValue)>> {
+    if !items.len().is_multiple_of(2) {
+        return Err(anyhow!("Odd number of items for pickle dict"));
+    }
+    let mut iter = items.into_iter();
+    let mut entries = Vec::new();
+    while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+        entries.push((k, v));
+    }
+    Ok(entries)
+}
+
+/// APPEND(S) / SETITEM(S): add items to the list or dict on top of the stack.
+fn extend(stack: &mut [Item], items: Vec<PickleValue>) -> Result<()> {
+    let target = match stack.last_mut() {
+        Some(Item::Value(value) | Item::Memoized(value, _)) => Some(value),
+        _ => None,
+    };
+    match target {
+        Some(PickleValue::List(list)) => list.extend(items),
+        Some(PickleValue::Dict(dict)) => dict.extend(pairs(items)?),
+        // Appending to an opaque object (e.g. a set built by REDUCE): keep the items as its args
+        Some(PickleValue::Object { args, .. }) => {
+            if let PickleValue::List(list) = args.as_mut() {
+                list.extend(items);
+            }
+        }
+        _ => return Err(anyhow!("Pickle APPEND/SETITEM target is not a list or dict")),
+    }
+    Ok(())
+}
+
+/// REDUCE a few well-known builtins into plain values; everything else stays opaque.
+fn reduce(callable: PickleValue, args: PickleValue) -> PickleValue {
+    match (&callable, &args) {
+        (PickleValue::Global(name), PickleValue::Tuple(items))
+            if (name == "__builtin__.set" || name == "__builtin__.frozenset") && items.len() == 1 =>
+        {
+            match &items[0] {
+                PickleValue::List(values) => PickleValue::List(values.clone()),
+                _ => PickleValue::List(Vec::new()),
+            }
+        }
+        _ => PickleValue::Object {
+            callable: Box::new(callable),
+            args: Box::new(args),
+        },
+    }
+}
@@ -0,0 +1,127 @@
+use crate::arena::VehicleListChange;
+use crate::bigworld::ArgReader;
+use crate::entities::{EntityMove, MethodCall};
+use crate::events::{Event, EventLog, TimedEvent};
+use crate::maps::{MapGeometry, MapPosition};
+use crate::types::BattleConfig;
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionSource {
+    /// ENTITY_MOVE: precise, but only for vehicles inside the client's area of interest.
+    EntityMove,
+    /// Avatar.updatePositions: the minimap marker, rounded to whole meters, for everyone else.
+    Minimap,
+}
+
+/// One vehicle position in world coordinates. `x`/`z` span the ground plane,
+/// which is what the minimap shows; `y` (height) and `yaw` are only known for ENTITY_MOVE.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionSample {
+    pub vehicle: u32,
+    pub x: f32,
+    pub z: f32,
+    pub y: Option<f32>,
+    pub yaw: Option<f32>,
+    pub source: PositionSource,
+    /// Normalized minimap coordinates, filled in by `attach_map_positions`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub map: Option<MapPosition>,
+}
+
+/// One raw entry of Avatar.updatePositions. `index` points into the arena roster
+/// sorted by vehicle ID; `minimap_positions` resolves it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MinimapEntry {
+    pub index: u8,
+    pub x: f32,
+    pub z: f32,
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    if (call.entity, call.method) != ("Avatar", "updatePositions") {
+        return Ok(None);
+    }
+    // updatePositions(indices: ARRAY<UINT8>, positions: ARRAY<INT16>) with an (x, z) pair per index
+    let mut args = ArgReader::new(call.args);
+    let indices = args.read_array(|r| r.read_u8())?;
+    let coordinates = args.read_array(|r| r.read_i16())?;
+    if coordinates.len() != indices.len() * 2 {
+        bail!("updatePositions has {} indices but {} coordinates", indices.len(), coordinates.len());
+    }
+    let entries = indices
+        .iter()
+        .zip(coordinates.chunks_exact(2))
+        .map(|(&index, xz)| MinimapEntry { index, x: xz[0] as f32, z: xz[1] as f32 })
+        .collect();
+    Ok(Some(Event::MinimapPositions { entries }))
+}
+
+pub(crate) fn decode_move(movement: &EntityMove) -> Option<Event> {
+    if movement.entity != "Vehicle" {
+        return None;
+    }
+    let [x, y, z] = movement.position;
+    Some(Event::Position(PositionSample {
+        vehicle: movement.entity_id,
+        x,
+        z,
+        y: Some(y),
+        yaw: Some(movement.rotation[0]),
+        source: PositionSource::EntityMove,
+        map: None,
+    }))
+}
+
+/// Resolves the minimap updates of one replay into per-vehicle `Event::Position`
+/// events tagged `PositionSource::Minimap`, in stream order.
+///
+/// Like the client, indices are mapped through the arena roster sorted by vehicle ID.
+/// The roster starts out as `BattleConfig.vehicles` and follows the VEHICLE_LIST /
+/// VEHICLE_ADDED arena updates.
+pub fn minimap_positions(log: &EventLog, config: &BattleConfig) -> Vec<TimedEvent> {
+    let mut roster: Vec<u32> = config.vehicles.keys().filter_map(|id| id.parse().ok()).collect();
+    roster.sort_unstable();
+
+    let mut positions = Vec::new();
+    for TimedEvent { time, event } in &log.events {
+        match event {
+            Event::VehicleListUpdate { change, vehicles } => {
+                if *change == VehicleListChange::List {
+                    roster.clear();
+                }
+                roster.extend(vehicles.iter().map(|v| v.vehicle));
+                roster.sort_unstable();
+                roster.dedup();
+            }
+            Event::MinimapPositions { entries } => {
+                for entry in entries {
+                    let Some(&vehicle) = roster.get(entry.index as usize) else { continue };
+                    let sample = PositionSample {
+                        vehicle,
+                        x: entry.x,
+                        z: entry.z,
+                        y: None,
+                        yaw: None,
+                        source: PositionSource::Minimap,
+                        map: None,
+                    };
+                    positions.push(TimedEvent { time: *time, event: Event::Position(sample) });
+                }
+            }
+            _ => {}
+        }
+    }
+    positions
+}
+
+/// Fills in the normalized minimap coordinates of every position event.
+pub fn attach_map_positions(events: &mut [TimedEvent], map: &MapGeometry) {
+    for timed in events {
+        if let Event::Position(sample) = &mut timed.event {
+            sample.map = Some(map.normalize(sample.x, sample.z));
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::events::{Event, EventLog, TimedEvent};
+use crate::maps::MapGeometry;
+use crate::positions::minimap_positions;
+use crate::shots::trace_shots;
+use crate::spotting::spotted_intervals;
+use crate::ticks::tick_count;
+use crate::types::BattleConfig;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+// Positions older than this (seconds) are drawn faded: the vehicle is somewhere near
+const STALE_POSITION: f32 = 5.0;
+// Tracers stay on screen at least this long (replay seconds)
+const TRACER_MIN_DURATION: f32 = 0.5;
+// Room for the grid labels around the map
+const MARGIN: f32 = 20.0;
+
+const GRID_ROWS: &str = "ABCDEFGHJK";
+const GRID_COLUMNS: &str = "1234567890";
+const ALLY_COLOR: &str = "#43a047";
+const ENEMY_COLOR: &str = "#e53935";
+const DEAD_COLOR: &str = "#616161";
+const SPOTTED_COLOR: &str = "#fdd835";
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// Width and height of the map area in pixels.
+    pub size: f32,
+    /// Replay seconds between animation frames.
+    pub interval: f32,
+    /// Playback speed: replay seconds per animation second.
+    pub speed: f32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { size: 800.0, interval: 1.0, speed: 10.0 }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Frame {
+    x: f32,
+    y: f32,
+    opacity: f32,
+    dead: bool,
+    spotted: bool,
+}
+
+/// Renders one replay as a looping animated SVG on the 10x10 minimap grid.
+///
+/// Vehicles are dots colored by team relative to the recording player (ringed while
+/// spotted, grey once dead), tracers are lines from muzzle to impact and deaths leave
+/// a cross. Frames are sampled every `options.interval` seconds and played back with
+/// SMIL animations, so any browser can show the result. Fails past `ticks::MAX_TICKS` frames.
+pub fn render_svg(log: &EventLog, config: &BattleConfig, map: &MapGeometry, options: &RenderOptions) -> Result<String> {
+    let size = options.size;
+    let interval = options.interval.max(0.01);
+    let frame_count = tick_count(log.end_time, interval)?;
+    let frame_times: Vec<f32> = (0..frame_count).map(|i| i as f32 * interval).collect();
+    // The animation ends on the last frame, which may fall short of `end_time`
+    let last_time = (frame_count - 1) as f32 * interval;
+    let duration = (last_time / options.speed.max(0.01)).max(0.1);
+    let key_time = |time: f32| (time / last_time.max(f32::EPSILON)).clamp(0.0, 1.0);
+    let to_pixels = |x: f32, z: f32| {
+        let position = map.normalize(x, z);
+        (MARGIN + position.x * size, MARGIN + position.y * size)
+    };
+
+    let player = config.player_vehicle_id();
+    let player_team = player.and_then(|id| config.vehicle(id)).map(|v| v.team);
+
+    let minimap = minimap_positions(log, config);
+    let mut tracks: BTreeMap<u32, Vec<(f32, f32, f32)>> = BTreeMap::new();
+    for TimedEvent { time, event } in log.events.iter().chain(&minimap) {
+        if let Event::Position(p) = event {
+            tracks.entry(p.vehicle).or_default().push((*time, p.x, p.z));
+        }
+    }
+    for track in tracks.values_mut() {
+        track.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    let deaths: HashMap<u32, f32> = log
+        .events
+        .iter()
+        .filter_map(|e| match e.event {
+            Event::VehicleKilled { victim, .. } => Some((victim, e.time)),
+            _ => None,
+        })
+        .collect();
+    let spotted = spotted_intervals(log, config);
+
+    let mut svg = String::new();
+    let total = size + 2.0 * MARGIN;
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="{total}" viewBox="0 0 {total} {total}" font-family="sans-serif" font-size="12">"#
+    );
+    let _ = writeln!(svg, r##"<rect width="{total}" height="{total}" fill="#202020"/>"##);
+    let _ = writeln!(svg, r##"<title>{} - {}</title>"##, escape(&config.map_name), escape(&config.player_name));
+
+    // Minimap squares and their labels
+    let square = size / 10.0;
+    for i in 0..=10 {
+        let offset = MARGIN + i as f32 * square;
+        let _ = writeln!(svg, r##"<line x1="{MARGIN}" y1="{offset}" x2="{}" y2="{offset}" stroke="#505050"/>"##, MARGIN + size);
+        let _ = writeln!(svg, r##"<line x1="{offset}" y1="{MARGIN}" x2="{offset}" y2="{}" stroke="#505050"/>"##, MARGIN + size);
+    }
+    for (i, (row, column)) in GRID_ROWS.chars().zip(GRID_COLUMNS.chars()).enumerate() {
+        let center = MARGIN + (i as f32 + 0.5) * square;
+        let _ = writeln!(svg, r##"<text x="{}" y="{center}" fill="#9e9e9e" text-anchor="middle" dominant-baseline="middle">{row}</text>"##, MARGIN / 2.0);
+        let _ = writeln!(svg, r##"<text x="{center}" y="{}" fill="#9e9e9e" text-anchor="middle" dominant-baseline="middle">{column}</text>"##, MARGIN / 2.0);
+    }
+
+    // Tracers: visible from firing until impact
+    for shot in trace_shots(&log.events, player) {
+        let (Some(start), Some(end)) = (shot.tracer_start, shot.impact_point) else { continue };
+        let fired = shot.fired_at;
+        let hidden = shot.impact_time.unwrap_or(fired).max(fired + TRACER_MIN_DURATION);
+        let (x1, y1) = to_pixels(start[0], start[2]);
+        let (x2, y2) = to_pixels(end[0], end[2]);
+        let color = team_color(config, player_team, shot.shooter);
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{x1:.1}" y1="{y1:.1}" x2="{x2:.1}" y2="{y2:.1}" stroke="{color}" stroke-width="1.5" opacity="0">{}</line>"#,
+            discrete_animation("opacity", &[(0.0, "0"), (key_time(fired), "0.9"), (key_time(hidden), "0")], duration)
+        );
+    }
+
+    // Vehicles that never had a position are left out
+    for (&vehicle, track) in &tracks {
+        let death = deaths.get(&vehicle).copied();
+        let mut next = 0;
+        let mut latest: Option<(f32, f32, f32)> = None;
+        let frames: Vec<Frame> = frame_times
+            .iter()
+            .map(|&time| {
+                while next < track.len() && track[next].0 <= time {
+                    latest = Some(track[next]);
+                    next += 1;
+                }
+                let dead = death.is_some_and(|death| death <= time);
+                let spotted = spotted.iter().any(|s| s.vehicle == vehicle && s.contains(time));
+                match latest {
+                    Some((t, x, z)) => {
+                        let (x, y) = to_pixels(x, z);
+                        let opacity = if dead || time - t <= STALE_POSITION { 1.0 } else { 0.4 };
+                        Frame { x, y, opacity, dead, spotted }
+                    }
+                    None => Frame { x: 0.0, y: 0.0, opacity: 0.0, dead, spotted: false },
+                }
+            })
+            .collect();
+
+        let xs = join(frames.iter().map(|f| format!("{:.1}", f.x)));
+        let ys = join(frames.iter().map(|f| format!("{:.1}", f.y)));
+        let color = team_color(config, player_team, vehicle);
+        let title = match config.vehicle(vehicle) {
+            Some(info) => format!("{} ({})", info.name, info.vehicle_type),
+            None => vehicle.to_string(),
+        };
+        let _ = writeln!(svg, "<g><title>{}</title>", escape(&title));
+        let _ = writeln!(
+            svg,
+            r#"<circle r="9" fill="none" stroke="{SPOTTED_COLOR}" stroke-width="2" opacity="0">{}{}{}</circle>"#,
+            animation("cx", &xs, duration),
+            animation("cy", &ys, duration),
+            frame_animation("opacity", &frames, duration, |f| if f.spotted && !f.dead { "1" } else { "0" }),
+        );
+        let stroke = if Some(vehicle) == player { "#ffffff" } else { "none" };
+        let _ = writeln!(
+            svg,
+            r#"<circle r="5" fill="{color}" stroke="{stroke}" stroke-width="1.5" opacity="0">{}{}{}{}</circle>"#,
+            animation("cx", &xs, duration),
+            animation("cy", &ys, duration),
+            frame_animation("opacity", &frames, duration, |f| opacity_value(f.opacity)),
+            frame_animation("fill", &frames, duration, |f| if f.dead { DEAD_COLOR } else { color }),
+        );
+        if let Some(death) = death
+            && let Some(frame) = frames.iter().find(|f| f.dead && f.opacity > 0.0)
+        {
+            let (x, y) = (frame.x, frame.y);
+            let _ = writeln!(
+                svg,
+                r#"<path d="M{:.1},{:.1} l10,10 m0,-10 l-10,10" stroke="{color}" stroke-width="2" opacity="0">{}</path>"#,
+                x - 5.0,
+                y - 5.0,
+                discrete_animation("opacity", &[(0.0, "0"), (key_time(death), "1")], duration)
+            );
+        }
+        svg.push_str("</g>\n");
+    }
+
+    // Playback progress along the bottom edge
+    let _ = writeln!(
+        svg,
+        r##"<rect x="{MARGIN}" y="{}" width="0" height="4" fill="#9e9e9e"><animate attributeName="width" values="0;{size}" dur="{duration:.2}s" repeatCount="indefinite"/></rect>"##,
+        total - 6.0
+    );
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+fn team_color(config: &BattleConfig, player_team: Option<u8>, vehicle: u32) -> &'static str {
+    match (player_team, config.vehicle(vehicle)) {
+        (Some(team), Some(info)) if info.team != team => ENEMY_COLOR,
+        (Some(_), Some(_)) => ALLY_COLOR,
+        _ => ENEMY_COLOR,
+    }
+}
+
+fn opacity_value(opacity: f32) -> &'static str {
+    if opacity >= 1.0 {
+        "1"
+    } else if opacity > 0.0 {
+        "0.4"
+    } else {
+        "0"
+    }
+}
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(";")
+}
+
+/// Linear animation over evenly spaced frames.
+fn animation(attribute: &str, values: &str, duration: f32) -> String {
+    format!(r#"<animate attributeName="{attribute}" values="{values}" dur="{duration:.2}s" repeatCount="indefinite"/>"#)
+}
+
+/// Per-frame discrete animation, collapsing runs of equal values into one key.
+fn frame_animation(attribute: &str, frames: &[Frame], duration: f32, value: impl Fn(&Frame) -> &'static str) -> String {
+    // Frame i of n is shown at i / (n - 1), like the linear position animations
+    let last_frame = frames.len().saturating_sub(1).max(1) as f32;
+    let mut keys: Vec<(f32, &str)> = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let value = value(frame);
+        if keys.last().is_none_or(|(_, last)| *last != value) {
+            keys.push((i as f32 / last_frame, value));
+        }
+    }
+    discrete_animation(attribute, &keys, duration)
+}
+
+/// `keys` are `(key time in 0..1, value)`, starting at 0. Key times must increase,
+/// so a key at the same time as the previous one replaces it.
+fn discrete_animation(attribute: &str, keys: &[(f32, &str)], duration: f32) -> String {
+    let mut increasing: Vec<(f32, &str)> = Vec::with_capacity(keys.len());
+    for &(time, value) in keys {
+        match increasing.last_mut() {
+            Some(last) if time <= last.0 => last.1 = value,
+            _ => increasing.push((time, value)),
+        }
+    }
+    let keys = &increasing;
+    let key_times = join(keys.iter().map(|(time, _)| format!("{:.4}", time)));
+    let values = join(keys.iter().map(|(_, value)| value.to_string()));
+    format!(
+        r#"<animate attributeName="{attribute}" values="{values}" keyTimes="{key_times}" calcMode="discrete" dur="{duration:.2}s" repeatCount="indefinite"/>"#
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,226 @@
+//! Replay selection on metadata: filters checked against the battle config and
+//! results read by `Parser::parse_header`, before anything is decrypted.
+
+use crate::types::Replay;
+use anyhow::{bail, Context, Result};
+use globset::GlobSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Dotted client version (`1.32.0.0`), compared component-wise; missing trailing
+/// components count as 0, so `1.32` equals `1.32.0.0`.
+#[derive(Debug, Clone)]
+pub struct ClientVersion(pub Vec<u32>);
+
+impl ClientVersion {
+    fn component(&self, i: usize) -> u32 {
+        self.0.get(i).copied().unwrap_or(0)
+    }
+}
+
+impl PartialEq for ClientVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ClientVersion {}
+
+impl PartialOrd for ClientVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ClientVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len).map(|i| self.component(i).cmp(&other.component(i))).find(|o| o.is_ne()).unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+impl FromStr for ClientVersion {
+    type Err = anyhow::Error;
+
+    /// Accepts `1.32.0.0` and `1_32_0` (the spelling of `--version`).
+    fn from_str(s: &str) -> Result<Self> {
+        let components = s
+            .split(['.', '_'])
+            .map(|c| c.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Invalid client version '{}'", s))?;
+        Ok(ClientVersion(components))
+    }
+}
+
+/// Battle start as recorded in `dateTime` (`19.02.2025 17:20:10`, the recording
+/// client's local time). Fields in order of significance, so derived `Ord` is chronological.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BattleDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl BattleDate {
+    /// Parses the replay's `dateTime` (`DD.MM.YYYY HH:MM:SS`).
+    pub fn from_replay(date_time: &str) -> Result<Self> {
+        let (date, time) = date_time.split_once(' ').unwrap_or((date_time, "0:0:0"));
+        let [day, month, year] = numbers(date, '.').with_context(|| format!("Invalid dateTime '{}'", date_time))?;
+        let [hour, minute, second] = numbers(time, ':').with_context(|| format!("Invalid dateTime '{}'", date_time))?;
+        BattleDate::new(year, month, day, hour, minute, second)
+    }
+
+    fn new(year: u32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Result<Self> {
+        if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+            bail!("Date out of range: {:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second);
+        }
+        Ok(BattleDate {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: hour as u8,
+            minute: minute as u8,
+            second: second as u8,
+        })
+    }
+
+    /// The last second of the same day, so a date-only upper bound includes the whole day.
+    fn end_of_day(self) -> Self {
+        BattleDate { hour: 23, minute: 59, second: 59, ..self }
+    }
+}
+
+/// Days in `month` (1-12) of `year`, in the Gregorian calendar.
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn numbers<const N: usize>(s: &str, separator: char) -> Result<[u32; N]> {
+    let parts = s.split(separator).map(|p| p.trim().parse::<u32>()).collect::<Result<Vec<_>, _>>()?;
+    parts.try_into().map_err(|parts: Vec<u32>| anyhow::anyhow!("Expected {} numbers, found {}", N, parts.len()))
+}
+
+impl fmt::Display for BattleDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
+
+/// A `--date-from` / `--date-to` bound: `YYYY-MM-DD`, optionally followed by
+/// `HH:MM[:SS]` after a space or `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateBound {
+    pub date: BattleDate,
+    /// Whether only the day was given.
+    pub date_only: bool,
+}
+
+impl FromStr for DateBound {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let context = || format!("Invalid date '{}', expected YYYY-MM-DD[THH:MM[:SS]]", s);
+        let (date, time) = match s.split_once(['T', ' ']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (s, None),
+        };
+        let [year, month, day] = numbers(date, '-').with_context(context)?;
+        let [hour, minute, second] = match time {
+            Some(time) => match numbers::<3>(time, ':') {
+                Ok(hms) => hms,
+                Err(_) => {
+                    let [hour, minute] = numbers(time, ':').with_context(context)?;
+                    [hour, minute, 0]
+                }
+            },
+            None => [0, 0, 0],
+        };
+        Ok(DateBound { date: BattleDate::new(year, month, day, hour, minute, second).with_context(context)?, date_only: time.is_none() })
+    }
+}
+
+/// Metadata filters; a replay is selected when it passes all that are set. Filters
+/// with several values select a replay matching any of them.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// `mapName`, e.g. `05_prohorovka`.
+    pub maps: Vec<String>,
+    /// Globs on `playerVehicle`, e.g. `ussr-*` or `*Object_277`.
+    pub vehicles: Option<GlobSet>,
+    pub players: Vec<String>,
+    /// `battleType`, the server's arena bonus type (1 is a random battle).
+    pub battle_types: Vec<u32>,
+    /// `gameplayID`: `ctf`, `domination`, `assault`, ...
+    pub gameplays: Vec<String>,
+    /// Bounds on `clientVersionFromExe`, inclusive.
+    pub min_version: Option<ClientVersion>,
+    pub max_version: Option<ClientVersion>,
+    /// Bounds on `dateTime`, inclusive; a date-only `date_to` includes that whole day.
+    pub date_from: Option<DateBound>,
+    pub date_to: Option<DateBound>,
+    /// Whether the replay must (or must not) carry battle results.
+    pub has_results: Option<bool>,
+}
+
+impl Selection {
+    /// Whether no filter is set, so every replay is selected without reading it.
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+            && self.vehicles.is_none()
+            && self.players.is_empty()
+            && self.battle_types.is_empty()
+            && self.gameplays.is_empty()
+            && self.min_version.is_none()
+            && self.max_version.is_none()
+            && self.date_from.is_none()
+            && self.date_to.is_none()
+            && self.has_results.is_none()
+    }
+
+    /// Whether `replay` passes every filter. Replays whose version or date cannot be
+    /// parsed never pass a filter on them.
+    pub fn matches(&self, replay: &Replay) -> bool {
+        let config = &replay.battle_config;
+        let any = |values: &[String], value: &str| values.is_empty() || values.iter().any(|v| v == value);
+        if !any(&self.maps, &config.map_name) || !any(&self.players, &config.player_name) || !any(&self.gameplays, &config.gameplay_id) {
+            return false;
+        }
+        if self.vehicles.as_ref().is_some_and(|globs| !globs.is_match(&config.player_vehicle)) {
+            return false;
+        }
+        if !self.battle_types.is_empty() && !config.battle_type.is_some_and(|t| self.battle_types.contains(&t)) {
+            return false;
+        }
+        if self.has_results.is_some_and(|has| has != replay.battle_results.is_some()) {
+            return false;
+        }
+
+        if self.min_version.is_some() || self.max_version.is_some() {
+            let Ok(version) = config.client_version_from_exe.parse::<ClientVersion>() else {
+                return false;
+            };
+            if self.min_version.as_ref().is_some_and(|min| version < *min) || self.max_version.as_ref().is_some_and(|max| version > *max) {
+                return false;
+            }
+        }
+        if self.date_from.is_some() || self.date_to.is_some() {
+            let Ok(date) = BattleDate::from_replay(&config.date_time) else {
+                return false;
+            };
+            let to = |bound: &DateBound| if bound.date_only { bound.date.end_of_day() } else { bound.date };
+            if self.date_from.is_some_and(|from| date < from.date) || self.date_to.is_some_and(|bound| date > to(&bound)) {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,84 @@
+//! Deterministic assignment of replays to output shards (`--out-dir --shards N`).
+//!
+//! The key of a replay is its battle (`dedupe::battle_id`): the `arenaUniqueID`, else
+//! a fingerprint of map, start time and roster. A replay lands in the same shard on
+//! every run and on every machine regardless of file names or processing order, and
+//! all perspectives of a battle land in the same one.
+
+use crate::dedupe::{battle_id, BattleId};
+use crate::events::EventLog;
+use crate::types::Replay;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a: stable across Rust versions and platforms, unlike `DefaultHasher`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Shard key of a replay: the key of its battle, equal for every perspective of it.
+pub fn shard_key(replay: &Replay, log: &EventLog) -> u64 {
+    battle_id(replay, log).shard_key()
+}
+
+pub fn shard_of(key: u64, shards: usize) -> usize {
+    (key % shards.max(1) as u64) as usize
+}
+
+/// Name of the file holding shard `index`, e.g. `shard-00003.jsonl`.
+pub fn shard_file_name(index: usize, extension: &str) -> String {
+    format!("shard-{:05}.{}", index, extension)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayStatus {
+    Ok,
+    Error,
+    /// Skipped by `--dedupe first`: an earlier replay recorded the same battle.
+    Duplicate,
+}
+
+/// One replay of a sharded run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub replay: String,
+    /// `None` if the replay could not be parsed or was skipped as a duplicate.
+    pub shard: Option<usize>,
+    /// Set with `--dedupe`.
+    pub battle_id: Option<BattleId>,
+    pub arena_unique_id: Option<u64>,
+    /// Client version the replay was recorded with.
+    pub version: Option<String>,
+    pub status: ReplayStatus,
+    pub error: Option<String>,
+    /// Records written for the replay (ticks, events or summaries).
+    pub records: usize,
+}
+
+/// `manifest.json` of an output directory, listing replays in input order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub shards: usize,
+    pub format: String,
+    pub emit: String,
+    /// Shard files, indexed by shard number.
+    pub files: Vec<String>,
+    pub replays: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
@@ -0,0 +1,318 @@
+use crate::bigworld::ArgReader;
+use crate::entities::MethodCall;
+use crate::events::{Event, TimedEvent};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// VEHICLE_HIT_FLAGS from the client's constants.py (high half of a shot result)
+const HIT_RICOCHET: u32 = 0x8;
+const HIT_ARMOR_PIERCED: u32 = 0x10 | 0x40;
+const HIT_DEVICE: u32 = 0x100 | 0x200 | 0x400;
+
+// How long after firing a tracer or a damage notification may still belong to a shot
+const TRACER_WINDOW: f32 = 1.0;
+const IMPACT_WINDOW: f32 = 5.0;
+
+/// One entry of Avatar.showShotResults: `[VehicleID (low 32 bits)] [Flags (high 32 bits)]`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ShotHit {
+    pub vehicle: u32,
+    pub flags: u32,
+}
+
+impl ShotHit {
+    pub fn result(&self) -> ShotResult {
+        if self.flags & HIT_ARMOR_PIERCED != 0 {
+            ShotResult::Penetration
+        } else if self.flags & HIT_DEVICE != 0 {
+            ShotResult::ModuleHit
+        } else if self.flags & HIT_RICOCHET != 0 {
+            ShotResult::Ricochet
+        } else {
+            ShotResult::NonPenetration
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShotResult {
+    Penetration,
+    NonPenetration,
+    Ricochet,
+    ModuleHit,
+    Miss,
+    /// The shot left the recording client's view before anything was known about it.
+    Unknown,
+}
+
+/// A single shot, correlated from the fire / tracer / impact / result events.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShotRecord {
+    pub shooter: u32,
+    pub shot_id: Option<i32>,
+    pub fired_at: f32,
+    pub tracer_start: Option<[f32; 3]>,
+    pub velocity: Option<[f32; 3]>,
+    pub impact_time: Option<f32>,
+    pub impact_point: Option<[f32; 3]>,
+    pub target: Option<u32>,
+    pub result: ShotResult,
+    /// Further vehicles hit by the same shot (HE splash), from the same showShotResults.
+    pub splash: Vec<ShotHit>,
+}
+
+/// Accuracy and penetration counters for one shooter.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShotStats {
+    pub shots: u32,
+    pub hits: u32,
+    pub penetrations: u32,
+    pub non_penetrations: u32,
+    pub ricochets: u32,
+    pub module_hits: u32,
+    pub misses: u32,
+    pub unknown: u32,
+}
+
+impl ShotStats {
+    pub fn add(&mut self, record: &ShotRecord) {
+        self.shots += 1;
+        if record.target.is_some() {
+            self.hits += 1;
+        }
+        match record.result {
+            ShotResult::Penetration => self.penetrations += 1,
+            ShotResult::NonPenetration => self.non_penetrations += 1,
+            ShotResult::Ricochet => self.ricochets += 1,
+            ShotResult::ModuleHit => self.module_hits += 1,
+            ShotResult::Miss => self.misses += 1,
+            ShotResult::Unknown => self.unknown += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &ShotStats) {
+        self.shots += other.shots;
+        self.hits += other.hits;
+        self.penetrations += other.penetrations;
+        self.non_penetrations += other.non_penetrations;
+        self.ricochets += other.ricochets;
+        self.module_hits += other.module_hits;
+        self.misses += other.misses;
+        self.unknown += other.unknown;
+    }
+
+    /// Hits per shot with a known outcome.
+    pub fn accuracy(&self) -> Option<f64> {
+        let known = self.shots - self.unknown;
+        (known > 0).then(|| self.hits as f64 / known as f64)
+    }
+
+    /// Penetrations per hit.
+    pub fn penetration_ratio(&self) -> Option<f64> {
+        (self.hits > 0).then(|| self.penetrations as f64 / self.hits as f64)
+    }
+}
+
+pub(crate) fn decode(call: &MethodCall) -> Result<Option<Event>> {
+    let mut args = ArgReader::new(call.args);
+    let event = match (call.entity, call.method) {
+        ("Vehicle", "showShooting") => Event::ShotFired {
+            shooter: call.entity_id,
+            burst_count: args.read_u8()?,
+            gun_index: args.read_u8()?,
+        },
+        // showTracer(shooterID, shotID, isRicochet, effectsIndex, refStartPoint, velocity, gravity, maxShotDist, gunIndex)
+        ("Avatar", "showTracer") => {
+            let shooter = args.read_entity_id()?;
+            let shot_id = args.read_i32()?;
+            let is_ricochet = args.read_bool()?;
+            let _effects_index = args.read_u8()?;
+            Event::TracerStarted {
+                shooter,
+                shot_id,
+                is_ricochet,
+                start: args.read_vec3()?,
+                velocity: args.read_vec3()?,
+            }
+        }
+        ("Avatar", "stopTracer") => Event::TracerStopped {
+            shot_id: args.read_i32()?,
+            end_point: args.read_vec3()?,
+        },
+        // explodeProjectile(shotID, effectsIndex, effectMaterialIndex, endPoint, velocityDir, damagedDestructibles)
+        ("Avatar", "explodeProjectile") => {
+            let shot_id = args.read_i32()?;
+            let _effects_index = args.read_u8()?;
+            let _material_index = args.read_u8()?;
+            Event::ProjectileExploded { shot_id, end_point: args.read_vec3()? }
+        }
+        ("Avatar", "showShotResults") => Event::ShotResults {
+            results: args.read_array(|r| {
+                let packed = r.read_u64()?;
+                Ok(ShotHit {
+                    vehicle: packed as u32,
+                    flags: (packed >> 32) as u32,
+                })
+            })?,
+        },
+        // showDamageFromShot(attackerID, points, effectsIndex, damageFactor, lastMaterialIsShield)
+        ("Vehicle", "showDamageFromShot") => {
+            let attacker = args.read_entity_id()?;
+            let _points = args.read_array(|r| r.read_u64())?;
+            let _effects_index = args.read_u8()?;
+            Event::DamageFromShot {
+                target: call.entity_id,
+                attacker,
+                damage_factor: args.read_u8()?,
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/// Correlates the decoded shot events of one replay into per-shot records.
+///
+/// `player_vehicle` is the recording player's vehicle; Avatar.showShotResults
+/// only reports on its shots. Records are returned in firing order.
+pub fn trace_shots(events: &[TimedEvent], player_vehicle: Option<u32>) -> Vec<ShotRecord> {
+    let mut shots: Vec<ShotRecord> = Vec::new();
+    let mut resolved: Vec<bool> = Vec::new();
+    let mut by_shot_id: HashMap<i32, usize> = HashMap::new();
+
+    for TimedEvent { time, event } in events {
+        let time = *time;
+        match event {
+            Event::ShotFired { shooter, .. } => {
+                shots.push(ShotRecord::new(*shooter, time));
+                resolved.push(false);
+            }
+            Event::TracerStarted { shooter, shot_id, is_ricochet, start, velocity } => {
+                // A ricochet spawns a new tracer for the same shell
+                if *is_ricochet {
+                    if let Some(index) = latest_shot(&shots, *shooter, time, IMPACT_WINDOW, |_| true) {
+                        if !resolved[index] {
+                            shots[index].result = ShotResult::Ricochet;
+                        }
+                        by_shot_id.insert(*shot_id, index);
+                    }
+                    continue;
+                }
+                let index = match latest_shot(&shots, *shooter, time, TRACER_WINDOW, |s| s.shot_id.is_none()) {
+                    Some(index) => index,
+                    // The shooter is outside our view, so showShooting never arrived
+                    None => {
+                        shots.push(ShotRecord::new(*shooter, time));
+                        resolved.push(false);
+                        shots.len() - 1
+                    }
+                };
+                let shot = &mut shots[index];
+                shot.shot_id = Some(*shot_id);
+                shot.tracer_start = Some(*start);
+                shot.velocity = Some(*velocity);
+                by_shot_id.insert(*shot_id, index);
+            }
+            Event::TracerStopped { shot_id, end_point } | Event::ProjectileExploded { shot_id, end_point } => {
+                if let Some(&index) = by_shot_id.get(shot_id) {
+                    let shot = &mut shots[index];
+                    if shot.impact_time.is_none() {
+                        shot.impact_time = Some(time);
+                        shot.impact_point = Some(*end_point);
+                    }
+                }
+            }
+            Event::DamageFromShot { target, attacker, damage_factor } => {
+                let Some(index) = latest_shot(&shots, *attacker, time, IMPACT_WINDOW, |s| s.target.is_none()) else {
+                    continue;
+                };
+                let shot = &mut shots[index];
+                shot.target = Some(*target);
+                shot.impact_time.get_or_insert(time);
+                if !resolved[index] {
+                    shot.result = if *damage_factor > 0 {
+                        ShotResult::Penetration
+                    } else if shot.result == ShotResult::Ricochet {
+                        ShotResult::Ricochet
+                    } else {
+                        ShotResult::NonPenetration
+                    };
+                }
+            }
+            Event::ShotResults { results } => {
+                let Some(player_vehicle) = player_vehicle else { continue };
+                // One showShotResults reports one shot: the first hit resolves it, the others
+                // are its splash, not earlier shots that missed
+                let Some((hit, splash)) = results.split_first() else { continue };
+                let Some(index) = latest_shot(&shots, player_vehicle, time, IMPACT_WINDOW, |s| {
+                    s.target.is_none() || s.target == Some(hit.vehicle)
+                }) else {
+                    continue;
+                };
+                if resolved[index] {
+                    continue;
+                }
+                // The server verdict wins over what we inferred from effects
+                let shot = &mut shots[index];
+                shot.target = Some(hit.vehicle);
+                shot.result = hit.result();
+                shot.splash = splash.to_vec();
+                resolved[index] = true;
+            }
+            _ => {}
+        }
+    }
+
+    for shot in &mut shots {
+        if shot.result == ShotResult::Unknown && shot.target.is_none() && shot.impact_time.is_some() {
+            shot.result = ShotResult::Miss;
+        }
+    }
+
+    shots
+}
+
+/// Aggregates shot records per shooter vehicle.
+pub fn stats_by_shooter(shots: &[ShotRecord]) -> BTreeMap<u32, ShotStats> {
+    let mut stats: BTreeMap<u32, ShotStats> = BTreeMap::new();
+    for shot in shots {
+        stats.entry(shot.shooter).or_default().add(shot);
+    }
+    stats
+}
+
+impl ShotRecord {
+    fn new(shooter: u32, fired_at: f32) -> Self {
+        Self {
+            shooter,
+            shot_id: None,
+            fired_at,
+            tracer_start: None,
+            velocity: None,
+            impact_time: None,
+            impact_point: None,
+            target: None,
+            result: ShotResult::Unknown,
+            splash: Vec::new(),
+        }
+    }
+}
+
+/// Most recent shot of `shooter` fired within `window` seconds before `time` that matches `filter`.
+fn latest_shot(
+    shots: &[ShotRecord],
+    shooter: u32,
+    time: f32,
+    window: f32,
+    filter: impl Fn(&ShotRecord) -> bool,
+) -> Option<usize> {
+    shots
+        .iter()
+        .enumerate()
+        .rev()
+        .take_while(|(_, s)| time - s.fired_at <= window)
+        .find(|(_, s)| s.shooter == shooter && filter(s))
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,155 @@
+use crate::bigworld::ArgReader;
+use crate::entities::{EntityMessage, PropertyUpdate};
+use crate::events::{Event, EventLog, TimedEvent};
+use crate::types::BattleConfig;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// What made a vehicle visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpottingSource {
+    /// An enemy vehicle was inside the recording client's view (entity enter/leave).
+    View,
+    /// The recording player's vehicle detected it (Vehicle.detectedVehicles).
+    Detected,
+    /// The recording player's vehicle was seen by the enemy (Vehicle.isObservedByEnemy).
+    ObservedByEnemy,
+    /// The sixth sense lamp was lit for the recording player's vehicle.
+    SixthSense,
+}
+
+/// `vehicle` was visible to `by_team` from `from` to `to` (seconds). `by_team` is
+/// `None` when the team is unknown, e.g. who saw the recorder with several enemy teams.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpottedInterval {
+    pub vehicle: u32,
+    pub from: f32,
+    pub to: f32,
+    pub by_team: Option<u8>,
+    pub source: SpottingSource,
+}
+
+impl SpottedInterval {
+    pub fn duration(&self) -> f32 {
+        self.to - self.from
+    }
+
+    pub fn contains(&self, time: f32) -> bool {
+        self.from <= time && time < self.to
+    }
+}
+
+pub(crate) fn decode_presence(message: &EntityMessage) -> Option<Event> {
+    match message {
+        EntityMessage::Entered(p) if p.entity == "Vehicle" => Some(Event::VehicleEntered { vehicle: p.entity_id }),
+        EntityMessage::Left(p) if p.entity == "Vehicle" => Some(Event::VehicleLeft { vehicle: p.entity_id }),
+        _ => None,
+    }
+}
+
+pub(crate) fn decode_property(update: &PropertyUpdate) -> Result<Option<Event>> {
+    let mut value = ArgReader::new(update.value);
+    let vehicle = update.entity_id;
+    let event = match (update.entity, update.property) {
+        ("Vehicle", "detectedVehicles") => Event::DetectedVehicles {
+            vehicle,
+            detected: value.read_array(|r| r.read_entity_id())?,
+        },
+        ("Vehicle", "isObservedByEnemy") => Event::ObservedByEnemy { vehicle, observed: value.read_bool()? },
+        ("Vehicle", "sixthSenseState") => Event::SixthSense { vehicle, active: value.read_bool()? },
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/// Builds visibility intervals from the view, detection and observation events.
+///
+/// Allies are always visible to their own team, so only enemies of the
+/// recording player produce `View`/`Detected` intervals, and only the
+/// recording player's own vehicle produces `ObservedByEnemy`/`SixthSense`
+/// intervals. Intervals still open at the end of the recording are closed at
+/// `log.end_time`. The result is sorted by start time.
+pub fn spotted_intervals(log: &EventLog, config: &BattleConfig) -> Vec<SpottedInterval> {
+    let player_vehicle = config.player_vehicle_id();
+    let player_team = player_vehicle.and_then(|id| config.vehicle(id)).map(|v| v.team);
+    // Whoever saw the recorder, when the roster has a single other team; in modes with
+    // more teams the observer is unknown
+    let enemy_team = player_team.and_then(|team| {
+        let mut others = config.vehicles.values().map(|v| v.team).filter(|t| *t != team);
+        let first = others.next()?;
+        others.all(|t| t == first).then_some(first)
+    });
+    let is_enemy = |vehicle: u32| match (player_team, config.vehicle(vehicle)) {
+        (Some(team), Some(info)) => info.team != team,
+        // Without a roster we cannot tell sides apart; treat everyone else as an enemy
+        _ => Some(vehicle) != player_vehicle,
+    };
+
+    let mut timeline = Timeline {
+        open: HashMap::new(),
+        intervals: Vec::new(),
+        player_team,
+        enemy_team,
+    };
+    let mut detected: HashSet<u32> = HashSet::new();
+
+    for TimedEvent { time, event } in &log.events {
+        let time = *time;
+        match event {
+            Event::VehicleEntered { vehicle } if is_enemy(*vehicle) => {
+                timeline.set(*vehicle, SpottingSource::View, true, time);
+            }
+            Event::VehicleLeft { vehicle } => timeline.set(*vehicle, SpottingSource::View, false, time),
+            Event::DetectedVehicles { vehicle, detected: now } if Some(*vehicle) == player_vehicle => {
+                let now: HashSet<u32> = now.iter().copied().collect();
+                for lost in detected.difference(&now) {
+                    timeline.set(*lost, SpottingSource::Detected, false, time);
+                }
+                for found in now.difference(&detected) {
+                    timeline.set(*found, SpottingSource::Detected, true, time);
+                }
+                detected = now;
+            }
+            Event::ObservedByEnemy { vehicle, observed } if Some(*vehicle) == player_vehicle => {
+                timeline.set(*vehicle, SpottingSource::ObservedByEnemy, *observed, time);
+            }
+            Event::SixthSense { vehicle, active } if Some(*vehicle) == player_vehicle => {
+                timeline.set(*vehicle, SpottingSource::SixthSense, *active, time);
+            }
+            _ => {}
+        }
+    }
+
+    let still_open: Vec<(u32, SpottingSource)> = timeline.open.keys().copied().collect();
+    for (vehicle, source) in still_open {
+        timeline.set(vehicle, source, false, log.end_time);
+    }
+
+    let mut intervals = timeline.intervals;
+    intervals.sort_by(|a, b| a.from.total_cmp(&b.from).then(a.vehicle.cmp(&b.vehicle)));
+    intervals
+}
+
+struct Timeline {
+    open: HashMap<(u32, SpottingSource), f32>,
+    intervals: Vec<SpottedInterval>,
+    player_team: Option<u8>,
+    enemy_team: Option<u8>,
+}
+
+impl Timeline {
+    /// Opens or closes the interval for `vehicle` from `source`. Repeated opens keep the earliest start.
+    fn set(&mut self, vehicle: u32, source: SpottingSource, visible: bool, time: f32) {
+        if visible {
+            self.open.entry((vehicle, source)).or_insert(time);
+        } else if let Some(from) = self.open.remove(&(vehicle, source)) {
+            let by_team = match source {
+                SpottingSource::View | SpottingSource::Detected => self.player_team,
+                SpottingSource::ObservedByEnemy | SpottingSource::SixthSense => self.enemy_team,
+            };
+            self.intervals.push(SpottedInterval { vehicle, from, to: time, by_team, source });
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Checkpoint store for incremental runs (`--state <file>`).
+//!
+//! For every input file the store keeps its size, modification time and content
+//! hash, the definitions it was decoded with and where its records went. A file
+//! is skipped when it is unchanged and its definitions are the same; a file with
+//! new metadata but known contents (touched, copied or moved) is skipped too.
+//! Files that failed to parse are retried on every run.
+
+use crate::dedupe::BattleId;
+use crate::inputs::Input;
+use crate::shards::{fnv1a, ReplayStatus};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub size: u64,
+    /// Modification time, nanoseconds since the Unix epoch.
+    pub mtime_ns: u64,
+    /// FNV-1a of the file contents, 16 hex digits.
+    pub hash: String,
+    /// `<version>@<fingerprint>` of the definitions used (see `definitions_key`).
+    pub definitions: String,
+    /// Output file the records were written to, `-` for stdout.
+    pub output: String,
+    pub status: ReplayStatus,
+    pub battle_id: Option<BattleId>,
+}
+
+impl StateEntry {
+    fn is_done(&self) -> bool {
+        self.status != ReplayStatus::Error
+    }
+}
+
+/// What a run should do with one input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Check {
+    /// Already processed with the current definitions; the entry to keep for it.
+    Unchanged(StateEntry),
+    /// New, changed, failed before, or processed with other definitions.
+    Process { size: u64, mtime_ns: u64, hash: String },
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    /// Keyed by input path.
+    pub entries: BTreeMap<String, StateEntry>,
+    /// Content hash -> path of a processed entry.
+    #[serde(skip)]
+    by_hash: HashMap<String, String>,
+}
+
+/// Identifies a set of definitions in the store.
+pub fn definitions_key(version: &str, fingerprint: u64) -> String {
+    format!("{}@{:016x}", version, fingerprint)
+}
+
+impl State {
+    /// Loads the store at `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut state: Self = serde_json::from_slice(&json).with_context(|| format!("Invalid state file {}", path.display()))?;
+        for (file, entry) in &state.entries {
+            if entry.is_done() {
+                state.by_hash.insert(entry.hash.clone(), file.clone());
+            }
+        }
+        Ok(state)
+    }
+
+    /// Writes the store through a temporary file, so an interrupted run never leaves it truncated.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+    }
+
+    /// Decides whether `input` needs processing with `definitions`. Reads the file
+    /// only when its size or modification time changed.
+    pub fn check(&self, input: &Input, definitions: &str) -> Result<Check> {
+        let file = input.path();
+        let (size, mtime_ns) = input.stat()?;
+
+        let current = |entry: &StateEntry| entry.is_done() && entry.definitions == definitions;
+        if let Some(entry) = self.entries.get(&*file.to_string_lossy())
+            && current(entry)
+            && (entry.size, entry.mtime_ns) == (size, mtime_ns)
+        {
+            return Ok(Check::Unchanged(entry.clone()));
+        }
+
+        let hash = format!("{:016x}", fnv1a(&input.read()?));
+        let known = self.by_hash.get(&hash).and_then(|known| self.entries.get(known));
+        match known {
+            Some(entry) if current(entry) => Ok(Check::Unchanged(StateEntry { size, mtime_ns, ..entry.clone() })),
+            _ => Ok(Check::Process { size, mtime_ns, hash }),
+        }
+    }
+
+    pub fn record(&mut self, file: &Path, entry: StateEntry) {
+        let file = file.to_string_lossy().into_owned();
+        if entry.is_done() {
+            self.by_hash.insert(entry.hash.clone(), file.clone());
+        }
+        self.entries.insert(file, entry);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::artillery::{arty_shots, spg_hits_while_stationary};
+use crate::battle_events::battle_event_totals;
+use crate::events::{Event, EventLog};
+use crate::positions::PositionSource;
+use crate::shots::{stats_by_shooter, trace_shots};
+use crate::spotting::{spotted_intervals, SpottingSource};
+use crate::types::Replay;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// ENTITY_MOVE jumps longer than this (meters) are respawns or teleports, not driving
+const MAX_MOVE_STEP: f32 = 50.0;
+// Slower than this (m/s) counts as standing still
+const STATIONARY_SPEED: f32 = 0.5;
+// Gaps between position updates longer than this (seconds) are not attributed to either
+const MAX_MOVE_GAP: f32 = 5.0;
+
+/// One derived metric next to the server's number for it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Metric {
+    pub value: f64,
+    /// The matching battle_results value, if the replay has results and a matching field.
+    pub battle_results: Option<f64>,
+    /// `value - battle_results`, or for a bounded metric how far the value exceeds the bound.
+    pub discrepancy: Option<f64>,
+    #[serde(skip)]
+    tolerance: f64,
+}
+
+impl Metric {
+    /// Cross-checks against `personal[key]`; `tolerance` is the absolute difference still considered agreement.
+    fn checked(value: f64, personal: Option<&Map<String, Value>>, key: &str, tolerance: f64) -> Self {
+        let battle_results = personal.and_then(|p| p.get(key)).and_then(Value::as_f64);
+        Self {
+            value: positive_zero(value),
+            battle_results,
+            discrepancy: battle_results.map(|expected| positive_zero(value - expected)),
+            tolerance,
+        }
+    }
+
+    /// Cross-checks against `personal[key]` as an upper bound, for metrics the server has no field for.
+    fn bounded(value: f64, personal: Option<&Map<String, Value>>, key: &str, tolerance: f64) -> Self {
+        let battle_results = personal.and_then(|p| p.get(key)).and_then(Value::as_f64);
+        Self {
+            value: positive_zero(value),
+            battle_results,
+            discrepancy: battle_results.map(|bound| positive_zero((value - bound).max(0.0))),
+            tolerance,
+        }
+    }
+
+    /// False when the value disagrees with battle_results beyond the metric's tolerance.
+    pub fn agrees(&self) -> bool {
+        self.discrepancy.is_none_or(|d| d.abs() <= self.tolerance)
+    }
+}
+
+// An empty float sum is -0.0, which would serialize as `-0.0`
+fn positive_zero(value: f64) -> f64 {
+    value + 0.0
+}
+
+/// Per-replay performance of the recording player, derived from the packet stream.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaySummary {
+    pub player: String,
+    pub vehicle: String,
+    pub map: String,
+    pub has_battle_results: bool,
+    pub damage_dealt: Metric,
+    pub damage_received: Metric,
+    pub radio_assist: Metric,
+    pub track_assist: Metric,
+    pub stun_assist: Metric,
+    pub blocked: Metric,
+    pub shots: Metric,
+    pub hits: Metric,
+    pub penetrations: Metric,
+    pub kills: Metric,
+    /// Enemies first spotted by the player.
+    pub enemies_spotted: Metric,
+    /// Seconds the player's vehicle was observed by the enemy.
+    pub time_spotted: Metric,
+    /// Meters driven, from ENTITY_MOVE.
+    pub distance_driven: Metric,
+    /// Seconds spent standing still, from ENTITY_MOVE.
+    pub time_stationary: Metric,
+    /// Seconds from the battle start to the player's death, or to the end of the recording.
+    pub survival_time: Metric,
+    pub survived: bool,
+    /// The player's vehicle was the first one destroyed in the battle.
+    pub first_death: bool,
+    /// Artillery shells that hit the player's vehicle while it stood still.
+    pub spg_hits_while_stationary: u32,
+    /// Names of the metrics that disagree with battle_results.
+    pub discrepancies: Vec<&'static str>,
+}
+
+/// Summarizes the recording player's battle from the decoded events of `replay`.
+pub fn summarize(replay: &Replay, log: &EventLog) -> ReplaySummary {
+    let config = &replay.battle_config;
+    let player = config.player_vehicle_id();
+    let personal = replay.personal_results();
+
+    let totals = battle_event_totals(&log.events);
+    let shots = trace_shots(&log.events, player);
+    let shot_stats = player.and_then(|id| stats_by_shooter(&shots).remove(&id)).unwrap_or_default();
+
+    let time_spotted: f32 = spotted_intervals(log, config)
+        .iter()
+        .filter(|i| Some(i.vehicle) == player && i.source == SpottingSource::ObservedByEnemy)
+        .map(|i| i.duration())
+        .sum();
+
+    let mut distance = 0.0;
+    let mut stationary = 0.0;
+    let mut previous: Option<(f32, [f32; 3])> = None;
+    for timed in &log.events {
+        let Event::Position(p) = &timed.event else { continue };
+        if Some(p.vehicle) != player || p.source != PositionSource::EntityMove {
+            continue;
+        }
+        let position = [p.x, p.y.unwrap_or_default(), p.z];
+        if let Some((time, last)) = previous {
+            let step = position.iter().zip(&last).map(|(a, b)| (a - b).powi(2)).sum::<f32>().sqrt();
+            let elapsed = timed.time - time;
+            if step <= MAX_MOVE_STEP {
+                distance += step;
+            }
+            if elapsed > 0.0 && elapsed <= MAX_MOVE_GAP && step / elapsed < STATIONARY_SPEED {
+                stationary += elapsed;
+            }
+        }
+        previous = Some((timed.time, position));
+    }
+
+    let kills: Vec<(f32, u32, u32)> = log
+        .events
+        .iter()
+        .filter_map(|e| match e.event {
+            Event::VehicleKilled { victim, killer, .. } => Some((e.time, victim, killer)),
+            _ => None,
+        })
+        .collect();
+    let death = kills.iter().find(|(_, victim, _)| Some(*victim) == player).map(|(time, ..)| *time);
+    let survival_time = (death.unwrap_or(log.end_time) - log.battle_start()).max(0.0);
+
+    let mut summary = ReplaySummary {
+        player: config.player_name.clone(),
+        vehicle: config.player_vehicle.clone(),
+        map: config.map_name.clone(),
+        has_battle_results: personal.is_some(),
+        damage_dealt: Metric::checked(totals.damage as f64, personal, "damageDealt", 0.0),
+        damage_received: Metric::checked(totals.received_damage as f64, personal, "damageReceived", 0.0),
+        radio_assist: Metric::checked(totals.radio_assist as f64, personal, "damageAssistedRadio", 0.0),
+        track_assist: Metric::checked(totals.track_assist as f64, personal, "damageAssistedTrack", 0.0),
+        stun_assist: Metric::checked(totals.stun_assist as f64, personal, "damageAssistedStun", 0.0),
+        blocked: Metric::checked(totals.blocked as f64, personal, "damageBlockedByArmor", 0.0),
+        shots: Metric::checked(shot_stats.shots as f64, personal, "shots", 0.0),
+        hits: Metric::checked(shot_stats.hits as f64, personal, "directEnemyHits", 0.0),
+        penetrations: Metric::checked(shot_stats.penetrations as f64, personal, "piercingEnemyHits", 0.0),
+        kills: Metric::checked(
+            kills.iter().filter(|(_, _, killer)| Some(*killer) == player).count() as f64,
+            personal,
+            "kills",
+            0.0,
+        ),
+        enemies_spotted: Metric::checked(totals.spotted as f64, personal, "spotted", 0.0),
+        // The server has no spotted or stationary time; neither can exceed lifeTime
+        time_spotted: Metric::bounded(time_spotted as f64, personal, "lifeTime", 2.0),
+        // The server's mileage is whole meters and ignores small corrections
+        distance_driven: Metric::checked(distance as f64, personal, "mileage", (distance as f64 * 0.05).max(10.0)),
+        time_stationary: Metric::bounded(stationary as f64, personal, "lifeTime", 2.0),
+        // lifeTime is whole seconds
+        survival_time: Metric::checked(survival_time as f64, personal, "lifeTime", 2.0),
+        survived: death.is_none(),
+        first_death: death.is_some() && kills.first().is_some_and(|(_, victim, _)| Some(*victim) == player),
+        spg_hits_while_stationary: player.map_or(0, |id| spg_hits_while_stationary(log, &arty_shots(log), id)),
+        discrepancies: Vec::new(),
+    };
+    summary.discrepancies = summary
+        .metrics()
+        .into_iter()
+        .filter(|(_, metric)| !metric.agrees())
+        .map(|(name, _)| name)
+        .collect();
+    summary
+}
+
+/// Field names of the `ReplaySummary` metrics, in declaration order.
+pub const METRIC_NAMES: [&str; 15] = [
+    "damage_dealt",
+    "damage_received",
+    "radio_assist",
+    "track_assist",
+    "stun_assist",
+    "blocked",
+    "shots",
+    "hits",
+    "penetrations",
+    "kills",
+    "enemies_spotted",
+    "time_spotted",
+    "distance_driven",
+    "time_stationary",
+    "survival_time",
+];
+
+impl ReplaySummary {
+    /// All metrics by their field name.
+    pub fn metrics(&self) -> Vec<(&'static str, &Metric)> {
+        let metrics = [
+            &self.damage_dealt,
+            &self.damage_received,
+            &self.radio_assist,
+            &self.track_assist,
+            &self.stun_assist,
+            &self.blocked,
+            &self.shots,
+            &self.hits,
+            &self.penetrations,
+            &self.kills,
+            &self.enemies_spotted,
+            &self.time_spotted,
+            &self.distance_driven,
+            &self.time_stationary,
+            &self.survival_time,
+        ];
+        METRIC_NAMES.into_iter().zip(metrics).collect()
+    }
+}
@@ -0,0 +1,48 @@
+use crate::events::EventLog;
+use crate::spotting::{spotted_intervals, SpottingSource};
+use crate::types::BattleConfig;
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+/// Most samples taken of one replay; battles last minutes, so more means a corrupt packet time.
+pub const MAX_TICKS: usize = 1_000_000;
+
+/// State of the recording player's vehicle sampled at a fixed interval.
+#[derive(Debug, Clone, Serialize)]
+pub struct Tick {
+    pub time: f32,
+    /// The vehicle was visible to the enemy team at this time, as in `ReplaySummary::time_spotted`.
+    pub spotted: bool,
+}
+
+/// Number of samples every `interval` seconds from 0 to `end_time`, up to `MAX_TICKS`.
+pub fn tick_count(end_time: f32, interval: f32) -> Result<usize> {
+    let count = (end_time.max(0.0) / interval).floor() + 1.0;
+    if !count.is_finite() || count > MAX_TICKS as f32 {
+        bail!("Sampling {} s every {} s takes more than {} ticks", end_time, interval, MAX_TICKS);
+    }
+    Ok(count as usize)
+}
+
+/// Samples the replay every `interval` seconds from 0 to `log.end_time`.
+pub fn build_ticks(log: &EventLog, config: &BattleConfig, interval: f32) -> Result<Vec<Tick>> {
+    let player_vehicle = config.player_vehicle_id();
+    let lit: Vec<_> = spotted_intervals(log, config)
+        .into_iter()
+        .filter(|i| Some(i.vehicle) == player_vehicle && i.source == SpottingSource::ObservedByEnemy)
+        .collect();
+
+    if interval <= 0.0 {
+        return Ok(Vec::new());
+    }
+    let count = tick_count(log.end_time, interval)?;
+    Ok((0..count)
+        .map(|i| {
+            let time = i as f32 * interval;
+            Tick {
+                time,
+                spotted: lit.iter().any(|l| l.contains(time)),
+            }
+        })
+        .collect())
+}
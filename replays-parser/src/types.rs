@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Replay {
@@ -31,4 +32,63 @@ pub struct BattleConfig {
     pub map_name: String,
     #[serde(rename = "gameplayID")]
     pub gameplay_id: String,
+    /// Arena bonus type (1 is a random battle).
+    #[serde(rename = "battleType", default)]
+    pub battle_type: Option<u32>,
+    /// Roster at battle start, keyed by vehicle entity ID.
+    #[serde(default)]
+    pub vehicles: HashMap<String, VehicleInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VehicleInfo {
+    pub name: String,
+    #[serde(rename = "vehicleType")]
+    pub vehicle_type: String,
+    pub team: u8,
+    #[serde(rename = "maxHealth", default)]
+    pub max_health: Option<u32>,
+}
+
+impl BattleConfig {
+    pub fn vehicle(&self, vehicle_id: u32) -> Option<&VehicleInfo> {
+        self.vehicles.get(&vehicle_id.to_string())
+    }
+
+    /// Entity ID of the recording player's vehicle, looked up by name in the roster.
+    pub fn player_vehicle_id(&self) -> Option<u32> {
+        self.vehicles
+            .iter()
+            .find(|(_, v)| v.name == self.player_name)
+            .and_then(|(id, _)| id.parse().ok())
+    }
+}
+
+impl Replay {
+    /// Winning team from the battle results (`0` is a draw), if the replay has them.
+    pub fn winner_team(&self) -> Option<u8> {
+        let results = self.battle_results.as_ref()?;
+        let common = results.get(0).unwrap_or(results).get("common")?;
+        common.get("winnerTeam")?.as_u64().map(|team| team as u8)
+    }
+
+    /// The recording player's own results (`personal.<typeCompDescr>`), if the replay has battle results.
+    ///
+    /// The entry is keyed by the `typeCompDescr` the results list for the player's vehicle,
+    /// so results of another vehicle are never taken for the player's.
+    pub fn personal_results(&self) -> Option<&serde_json::Map<String, serde_json::Value>> {
+        let results = self.battle_results.as_ref()?;
+        let results = results.get(0).unwrap_or(results);
+        let vehicle_id = self.battle_config.player_vehicle_id()?;
+        // One entry per vehicle, as a list in recent versions
+        let vehicle = results.get("vehicles")?.get(vehicle_id.to_string())?;
+        let type_comp_descr = vehicle.get(0).unwrap_or(vehicle).get("typeCompDescr")?.as_u64()?;
+        results.get("personal")?.get(type_comp_descr.to_string())?.as_object()
+    }
+
+    /// The server's ID of the battle, shared by every replay recorded in it; needs battle results.
+    pub fn arena_unique_id(&self) -> Option<u64> {
+        let results = self.battle_results.as_ref()?;
+        results.get(0).unwrap_or(results).get("arenaUniqueID")?.as_u64()
+    }
 }
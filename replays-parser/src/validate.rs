@@ -0,0 +1,65 @@
+use crate::summary::ReplaySummary;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Metrics whose decoded totals should match the server exactly when the definitions are right.
+pub const VALIDATED_METRICS: &[&str] = &["damage_dealt", "kills", "shots", "enemies_spotted"];
+
+/// How often one metric matched the battle results.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Agreement {
+    pub checked: u32,
+    pub agreed: u32,
+}
+
+impl Agreement {
+    pub fn rate(&self) -> Option<f64> {
+        (self.checked > 0).then(|| self.agreed as f64 / self.checked as f64)
+    }
+}
+
+impl Serialize for Agreement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Agreement", 3)?;
+        state.serialize_field("checked", &self.checked)?;
+        state.serialize_field("agreed", &self.agreed)?;
+        state.serialize_field("rate", &self.rate())?;
+        state.end()
+    }
+}
+
+/// Decoder agreement with the battle results over the replays of one client version.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VersionReport {
+    pub replays: u32,
+    /// Replays that could not be checked because they have no battle results.
+    pub without_results: u32,
+    pub metrics: BTreeMap<&'static str, Agreement>,
+}
+
+impl VersionReport {
+    pub fn add(&mut self, summary: &ReplaySummary) {
+        self.replays += 1;
+        if !summary.has_battle_results {
+            self.without_results += 1;
+            return;
+        }
+        for (name, metric) in summary.metrics() {
+            if !VALIDATED_METRICS.contains(&name) || metric.battle_results.is_none() {
+                continue;
+            }
+            let agreement = self.metrics.entry(name).or_default();
+            agreement.checked += 1;
+            agreement.agreed += metric.agrees() as u32;
+        }
+    }
+
+    /// Lowest agreement rate over the checked metrics.
+    pub fn worst_rate(&self) -> Option<f64> {
+        self.metrics.values().filter_map(Agreement::rate).min_by(f64::total_cmp)
+    }
+}
+
+/// Per-version reports keyed by `BattleConfig.client_version_from_exe`.
+pub type ValidationReport = BTreeMap<String, VersionReport>;
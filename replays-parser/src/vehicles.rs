@@ -0,0 +1,75 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleClass {
+    Light,
+    Medium,
+    Heavy,
+    TankDestroyer,
+    Artillery,
+}
+
+impl FromStr for VehicleClass {
+    type Err = anyhow::Error;
+
+    /// Accepts our names as well as the client's class tags (`mediumTank`, `AT-SPG`, ...).
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "light" | "lighttank" | "lt" => VehicleClass::Light,
+            "medium" | "mediumtank" | "mt" => VehicleClass::Medium,
+            "heavy" | "heavytank" | "ht" => VehicleClass::Heavy,
+            "td" | "tank_destroyer" | "at-spg" => VehicleClass::TankDestroyer,
+            "spg" | "artillery" => VehicleClass::Artillery,
+            _ => bail!("Unknown vehicle class '{}'", s),
+        })
+    }
+}
+
+/// Vehicle classes keyed by lowercase vehicle tag (`vehicleType` without the nation, e.g. `r155_object_277`).
+///
+/// Replays do not record classes, and vehicles are added or reclassified with every
+/// client update, so there is no built-in list: classes come from a catalog exported
+/// for the client version at hand (`load_json`).
+#[derive(Debug, Clone, Default)]
+pub struct VehicleCatalog {
+    classes: HashMap<String, VehicleClass>,
+}
+
+impl VehicleCatalog {
+    pub fn insert(&mut self, vehicle_type: &str, class: VehicleClass) {
+        self.classes.insert(tag(vehicle_type), class);
+    }
+
+    /// Merges a JSON object of `{ "<vehicleType or tag>": "<class>" }`, e.g. `{"ussr:R155_Object_277": "heavy"}`.
+    pub fn load_json(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let entries: HashMap<String, String> =
+            serde_json::from_str(&content).with_context(|| format!("Failed to parse vehicle catalog {}", path.display()))?;
+        for (vehicle_type, class) in entries {
+            self.insert(&vehicle_type, class.parse()?);
+        }
+        Ok(())
+    }
+
+    /// Class of a `BattleConfig` vehicle type such as `ussr:R155_Object_277`.
+    pub fn class_of(&self, vehicle_type: &str) -> Option<VehicleClass> {
+        let tag = tag(vehicle_type);
+        if let Some(class) = self.classes.get(&tag) {
+            return Some(*class);
+        }
+        // Older tags lack the nation prefix of the full tag: "G02_Hummel" vs "Hummel"
+        let (_, short) = tag.split_once('_')?;
+        self.classes.get(short).copied()
+    }
+}
+
+fn tag(vehicle_type: &str) -> String {
+    let tag = vehicle_type.rsplit(':').next().unwrap_or(vehicle_type);
+    tag.to_ascii_lowercase()
+}
@@ -0,0 +1,101 @@
+use arrow::array::{Array, AsArray};
+use arrow::datatypes::{Float32Type, UInt32Type};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use replays_parser::columnar::{
+    event_schema, events_batch, tick_schema, ticks_batch, ColumnarFormat, ColumnarWriter, Row,
+};
+use replays_parser::events::{Event, TimedEvent};
+use replays_parser::positions::{PositionSample, PositionSource};
+use replays_parser::ticks::Tick;
+use std::fs::{self, File};
+
+fn event_rows(replay: &str) -> Vec<Row<TimedEvent>> {
+    let sample = PositionSample {
+        vehicle: 200,
+        x: 1.0,
+        z: 3.0,
+        y: Some(2.0),
+        yaw: Some(0.5),
+        source: PositionSource::EntityMove,
+        map: None,
+    };
+    vec![
+        Row { replay: replay.to_string(), battle_id: None, record: TimedEvent { time: 2.5, event: Event::Position(sample) }, labels: None },
+        Row {
+            replay: replay.to_string(),
+            battle_id: None,
+            record: TimedEvent { time: 3.0, event: Event::ShotFired { shooter: 300, burst_count: 1, gun_index: 0 } },
+            labels: None,
+        },
+    ]
+}
+
+#[test]
+fn test_parquet_events_buffered_into_row_groups() {
+    let path = std::env::temp_dir().join(format!("replays-parser-events-{}.parquet", std::process::id()));
+    let mut writer = ColumnarWriter::create(&path, ColumnarFormat::Parquet, event_schema()).unwrap();
+    writer.write(&events_batch(&event_rows("a.wotreplay"), 1.0).unwrap()).unwrap();
+    writer.write(&events_batch(&event_rows("b.wotreplay"), 1.0).unwrap()).unwrap();
+    writer.finish().unwrap();
+
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+    // Small batches share a row group
+    assert_eq!(builder.metadata().num_row_groups(), 1);
+    let batches: Vec<_> = builder.build().unwrap().map(Result::unwrap).collect();
+    fs::remove_file(&path).unwrap();
+    let batch = &batches[0];
+    assert_eq!(batch.schema(), event_schema());
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 4);
+
+    let tick = batch.column_by_name("tick").unwrap().as_primitive::<UInt32Type>();
+    assert_eq!((tick.value(0), tick.value(1)), (2, 3));
+    let event = batch.column_by_name("event").unwrap().as_string::<i32>();
+    assert_eq!((event.value(0), event.value(1)), ("position", "shot_fired"));
+    let vehicle = batch.column_by_name("vehicle").unwrap().as_primitive::<UInt32Type>();
+    assert_eq!((vehicle.value(0), vehicle.value(1)), (200, 300));
+
+    let position = batch.column_by_name("position").unwrap().as_fixed_size_list();
+    let xyz = position.value(0);
+    assert_eq!(xyz.as_primitive::<Float32Type>().values().to_vec(), vec![1.0, 2.0, 3.0]);
+    assert!(position.is_null(1));
+    let data = batch.column_by_name("data").unwrap().as_string::<i32>();
+    assert_eq!(data.value(1), r#"{"burst_count":1,"gun_index":0,"shooter":300}"#);
+    // Without --labels the label columns exist but are null
+    assert_eq!(batch.column_by_name("outcome").unwrap().null_count(), batch.num_rows());
+}
+
+#[test]
+fn test_arrow_ipc_ticks() {
+    let path = std::env::temp_dir().join(format!("replays-parser-ticks-{}.arrow", std::process::id()));
+    let rows: Vec<Row<Tick>> = (0..3)
+        .map(|i| Row {
+            replay: "a.wotreplay".to_string(),
+            battle_id: None,
+            record: Tick { time: i as f32 * 0.5, spotted: i == 2 },
+            labels: None,
+        })
+        .collect();
+    let mut writer = ColumnarWriter::create(&path, ColumnarFormat::Arrow, tick_schema()).unwrap();
+    writer.write(&ticks_batch(&rows, 0.5).unwrap()).unwrap();
+    writer.finish().unwrap();
+
+    let reader = arrow::ipc::reader::FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+    let batches: Vec<_> = reader.map(Result::unwrap).collect();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(batches.len(), 1);
+    let tick = batches[0].column_by_name("tick").unwrap().as_primitive::<UInt32Type>();
+    assert_eq!(tick.values().to_vec(), vec![0, 1, 2]);
+    let spotted = batches[0].column_by_name("spotted").unwrap().as_boolean();
+    assert!(!spotted.value(0) && spotted.value(2));
+}
+
+#[test]
+fn test_unfinished_file_removed() {
+    let path = std::env::temp_dir().join(format!("replays-parser-unfinished-{}.parquet", std::process::id()));
+    let mut writer = ColumnarWriter::create(&path, ColumnarFormat::Parquet, event_schema()).unwrap();
+    writer.write(&events_batch(&event_rows("a.wotreplay"), 1.0).unwrap()).unwrap();
+    assert!(path.exists());
+    // Dropped on an error, before the footer was written
+    drop(writer);
+    assert!(!path.exists());
+}
@@ -0,0 +1,41 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use replays_parser::events::{Event, TimedEvent};
+use replays_parser::positions::{PositionSample, PositionSource};
+use replays_parser::types::BattleConfig;
+
+pub const OBJECT_277: &str = "ussr:R155_Object_277";
+pub const TF_4: &str = "usa:A171_TF_4";
+
+/// A battle on 04_himmelsdorf between vehicle 200 (team 1) and vehicle 300 (team 2),
+/// each given as `(name, vehicleType)`, recorded by `player`.
+pub fn battle_config_for(player: &str, vehicles: [(&str, &str); 2]) -> BattleConfig {
+    let [(name_200, type_200), (name_300, type_300)] = vehicles;
+    let player_type = if player == name_300 { type_300 } else { type_200 };
+    serde_json::from_value(serde_json::json!({
+        "playerName": player,
+        "playerVehicle": player_type.replace(':', "-"),
+        "clientVersionFromXml": "1.32.0",
+        "clientVersionFromExe": "1.32.0.0",
+        "dateTime": "19.02.2025 17:20:10",
+        "mapName": "04_himmelsdorf",
+        "gameplayID": "ctf",
+        "vehicles": {
+            "200": {"name": name_200, "vehicleType": type_200, "team": 1},
+            "300": {"name": name_300, "vehicleType": type_300, "team": 2}
+        }
+    }))
+    .unwrap()
+}
+
+/// The recorder in an Object 277 (200) against an enemy TF-4 (300).
+pub fn battle_config() -> BattleConfig {
+    battle_config_for("recorder", [("recorder", OBJECT_277), ("enemy", TF_4)])
+}
+
+/// A ground-level position of `vehicle`, without height or heading.
+pub fn position(time: f32, vehicle: u32, x: f32, z: f32, source: PositionSource) -> TimedEvent {
+    let sample = PositionSample { vehicle, x, z, y: None, yaw: None, source, map: None };
+    TimedEvent { time, event: Event::Position(sample) }
+}
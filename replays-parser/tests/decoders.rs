@@ -0,0 +1,867 @@
+mod common;
+
+use common::battle_config;
+use replays_parser::definitions::Definitions;
+use replays_parser::events::decode_events;
+use replays_parser::shots::{stats_by_shooter, trace_shots, ShotResult};
+use replays_parser::spotting::{spotted_intervals, SpottingSource};
+use replays_parser::ticks::build_ticks;
+use replays_parser::types::{Replay, ReplayHeader};
+
+const AVATAR_ID: u32 = 100;
+const PLAYER_VEHICLE: u32 = 200;
+const ENEMY_VEHICLE: u32 = 300;
+
+// Entity type IDs as they appear in create packets (1-based)
+const AVATAR_TYPE: u16 = 2;
+const VEHICLE_TYPE: u16 = 6;
+
+fn definitions() -> Definitions {
+    serde_json::from_value(serde_json::json!({
+        "packetTypes": {
+            "0x07": {"id": "ENTITY_PROPERTY_UPDATE", "subtypes": {"0x07": "TRACK_DESTROYED"}},
+            "0x08": {"id": "ENTITY_METHOD_CALL", "subtypes": {"0x0B": "MODULE_DAMAGE"}}
+        },
+        "entities": {
+            "1": {
+                "id": 1, "name": "Avatar",
+                "clientMethods": {
+                    "0": {"name": "showTracer"},
+                    "1": {"name": "stopTracer"},
+                    "2": {"name": "explodeProjectile"},
+                    "3": {"name": "showShotResults"},
+                    "4": {"name": "onBattleEvents"},
+                    "5": {"name": "battleEventsSummary"},
+                    "6": {"name": "updatePositions"},
+                    "7": {"name": "updateVehicleAmmo"},
+                    "8": {"name": "updateVehicleSetting"},
+                    "9": {"name": "updateTargetVehicleID"},
+                    "10": {"name": "updateAvatarPrivateStats"},
+                    "11": {"name": "showOtherVehicleDamagedDevices"},
+                    "12": {"name": "enemySPGHit"},
+                    "13": {"name": "updateVehicleHealth"},
+                    "14": {"name": "updateTargetingInfo"},
+                    "15": {"name": "updateVehicleQuickShellChanger"}
+                },
+                "properties": {}, "cellMethods": {}, "baseMethods": {}
+            },
+            "5": {
+                "id": 5, "name": "Vehicle",
+                "clientMethods": {
+                    "0": {"name": "showShooting"},
+                    "1": {"name": "showDamageFromShot"},
+                    "2": {"name": "showAmmoBayEffect"},
+                    "3": {"name": "showDamageFromExplosion"}
+                },
+                "properties": {
+                    "0": {"name": "isObservedByEnemy"},
+                    "1": {"name": "detectedVehicles"},
+                    "2": {"name": "stunInfo"},
+                    "3": {"name": "arenaUniqueID"},
+                    "4": {"name": "sixthSenseState"}
+                }, "cellMethods": {}, "baseMethods": {}
+            }
+        }
+    }))
+    .unwrap()
+}
+
+/// Accumulates raw packets in the decrypted stream layout:
+/// `[Length (4)] [Type (4)] [Time (4)] [Payload]`.
+#[derive(Default)]
+struct StreamBuilder {
+    buffer: Vec<u8>,
+}
+
+impl StreamBuilder {
+    fn packet(&mut self, packet_type: u32, time: f32, payload: &[u8]) -> &mut Self {
+        self.buffer.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.buffer.extend_from_slice(&packet_type.to_le_bytes());
+        self.buffer.extend_from_slice(&time.to_le_bytes());
+        self.buffer.extend_from_slice(payload);
+        self
+    }
+
+    fn create(&mut self, entity_id: u32, type_id: u16) -> &mut Self {
+        self.enter(0.0, entity_id, type_id)
+    }
+
+    fn enter(&mut self, time: f32, entity_id: u32, type_id: u16) -> &mut Self {
+        let mut payload = entity_id.to_le_bytes().to_vec();
+        payload.extend_from_slice(&type_id.to_le_bytes());
+        let packet_type = if type_id == AVATAR_TYPE { 0x00 } else { 0x05 };
+        self.packet(packet_type, time, &payload)
+    }
+
+    fn leave(&mut self, time: f32, entity_id: u32) -> &mut Self {
+        self.packet(0x04, time, &entity_id.to_le_bytes())
+    }
+
+    fn property(&mut self, time: f32, entity_id: u32, property_id: u32, value: &[u8]) -> &mut Self {
+        let mut payload = entity_id.to_le_bytes().to_vec();
+        payload.extend_from_slice(&property_id.to_le_bytes());
+        payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        payload.extend_from_slice(value);
+        self.packet(0x07, time, &payload)
+    }
+
+    fn call(&mut self, time: f32, entity_id: u32, method_id: u32, args: &[u8]) -> &mut Self {
+        let mut payload = entity_id.to_le_bytes().to_vec();
+        payload.extend_from_slice(&method_id.to_le_bytes());
+        payload.extend_from_slice(&(args.len() as u32).to_le_bytes());
+        payload.extend_from_slice(args);
+        self.packet(0x08, time, &payload)
+    }
+
+    fn moved(&mut self, time: f32, entity_id: u32, position: [f32; 3], yaw: f32) -> &mut Self {
+        // [EntityID (4)] [SpaceID (4)] [Position (12)] [PositionError (12)] [Rotation (12)] [IsError (1)]
+        let mut payload = entity_id.to_le_bytes().to_vec();
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        vec3(&mut payload, position);
+        vec3(&mut payload, [0.0; 3]);
+        vec3(&mut payload, [yaw, 0.0, 0.0]);
+        payload.push(0);
+        self.packet(0x0A, time, &payload)
+    }
+
+    fn replay(&self) -> Replay {
+        Replay {
+            header: ReplayHeader { magic: 0x11343212, block_count: 1 },
+            battle_config: battle_config(),
+            battle_results: None,
+            packets_buffer: self.buffer.clone(),
+        }
+    }
+}
+
+fn vec3(out: &mut Vec<u8>, v: [f32; 3]) {
+    for c in v {
+        out.extend_from_slice(&c.to_le_bytes());
+    }
+}
+
+fn tracer_args(shooter: u32, shot_id: i32, is_ricochet: bool) -> Vec<u8> {
+    let mut args = shooter.to_le_bytes().to_vec();
+    args.extend_from_slice(&shot_id.to_le_bytes());
+    args.push(is_ricochet as u8);
+    args.push(0x10); // effectsIndex
+    vec3(&mut args, [1.0, 2.0, 3.0]);
+    vec3(&mut args, [0.0, 0.0, 900.0]);
+    args.extend_from_slice(&9.81f32.to_le_bytes());
+    args.extend_from_slice(&700.0f32.to_le_bytes());
+    args.push(0); // gunIndex
+    args
+}
+
+fn stop_tracer_args(shot_id: i32) -> Vec<u8> {
+    let mut args = shot_id.to_le_bytes().to_vec();
+    vec3(&mut args, [10.0, 2.0, 30.0]);
+    args
+}
+
+#[test]
+fn test_shot_lifecycle_is_correlated() {
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE).create(ENEMY_VEHICLE, VEHICLE_TYPE);
+
+    // Player shot 1: penetrates the enemy (server verdict via showShotResults)
+    stream.call(10.0, PLAYER_VEHICLE, 0, &[1, 0]);
+    stream.call(10.0, AVATAR_ID, 0, &tracer_args(PLAYER_VEHICLE, 1, false));
+    stream.call(10.3, AVATAR_ID, 1, &stop_tracer_args(1));
+    let mut results = vec![1u8];
+    results.extend_from_slice(&((0x10u64 << 32) | ENEMY_VEHICLE as u64).to_le_bytes());
+    stream.call(10.3, AVATAR_ID, 3, &results);
+
+    // Player shot 2: the tracer stops without hitting anything
+    stream.call(20.0, PLAYER_VEHICLE, 0, &[1, 0]);
+    stream.call(20.0, AVATAR_ID, 0, &tracer_args(PLAYER_VEHICLE, 2, false));
+    stream.call(20.4, AVATAR_ID, 1, &stop_tracer_args(2));
+
+    // Enemy shot: bounces off the player without damage
+    stream.call(30.0, ENEMY_VEHICLE, 0, &[1, 0]);
+    stream.call(30.0, AVATAR_ID, 0, &tracer_args(ENEMY_VEHICLE, 3, false));
+    stream.call(30.2, AVATAR_ID, 0, &tracer_args(ENEMY_VEHICLE, 4, true));
+    let mut damage = ENEMY_VEHICLE.to_le_bytes().to_vec();
+    damage.extend_from_slice(&[0, 0x10, 0, 0]); // no points, effectsIndex, damageFactor 0, shield
+    stream.call(30.2, PLAYER_VEHICLE, 1, &damage);
+
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    // 11 shot events plus both vehicles entering the view
+    assert_eq!(log.events.len(), 13);
+
+    let shots = trace_shots(&log.events, replay.battle_config.player_vehicle_id());
+    assert_eq!(shots.len(), 3);
+
+    assert_eq!(shots[0].shot_id, Some(1));
+    assert_eq!(shots[0].target, Some(ENEMY_VEHICLE));
+    assert_eq!(shots[0].result, ShotResult::Penetration);
+    assert_eq!(shots[0].impact_time, Some(10.3));
+
+    assert_eq!(shots[1].target, None);
+    assert_eq!(shots[1].result, ShotResult::Miss);
+
+    assert_eq!(shots[2].shooter, ENEMY_VEHICLE);
+    assert_eq!(shots[2].target, Some(PLAYER_VEHICLE));
+    assert_eq!(shots[2].result, ShotResult::Ricochet);
+
+    let stats = stats_by_shooter(&shots);
+    let player = &stats[&PLAYER_VEHICLE];
+    assert_eq!((player.shots, player.hits, player.penetrations), (2, 1, 1));
+    assert_eq!(player.accuracy(), Some(0.5));
+    assert_eq!(player.penetration_ratio(), Some(1.0));
+}
+
+#[test]
+fn test_splash_hits_stay_on_one_shot() {
+    const SPLASHED: u32 = 400;
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+
+    // A miss, then an HE shell that pierces the enemy and splashes a second vehicle
+    stream.call(20.0, PLAYER_VEHICLE, 0, &[1, 0]);
+    stream.call(20.0, AVATAR_ID, 0, &tracer_args(PLAYER_VEHICLE, 1, false));
+    stream.call(20.4, AVATAR_ID, 1, &stop_tracer_args(1));
+    stream.call(22.0, PLAYER_VEHICLE, 0, &[1, 0]);
+    stream.call(22.0, AVATAR_ID, 0, &tracer_args(PLAYER_VEHICLE, 2, false));
+    stream.call(22.3, AVATAR_ID, 1, &stop_tracer_args(2));
+    let mut results = vec![2u8];
+    results.extend_from_slice(&((0x10u64 << 32) | ENEMY_VEHICLE as u64).to_le_bytes());
+    results.extend_from_slice(&((0x1u64 << 32) | SPLASHED as u64).to_le_bytes());
+    stream.call(22.3, AVATAR_ID, 3, &results);
+
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    let shots = trace_shots(&log.events, replay.battle_config.player_vehicle_id());
+    assert_eq!(shots.len(), 2);
+    assert_eq!((shots[0].target, shots[0].result), (None, ShotResult::Miss));
+    assert_eq!((shots[1].target, shots[1].result), (Some(ENEMY_VEHICLE), ShotResult::Penetration));
+    assert_eq!(shots[1].splash.iter().map(|hit| hit.vehicle).collect::<Vec<_>>(), vec![SPLASHED]);
+}
+
+#[test]
+fn test_spotting_timeline() {
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+
+    // The enemy is in view from 5s to 15s, and the player is lit from 8s to 48s
+    stream.enter(5.0, ENEMY_VEHICLE, VEHICLE_TYPE);
+    stream.property(8.0, PLAYER_VEHICLE, 0, &[1]);
+    stream.leave(15.0, ENEMY_VEHICLE);
+    stream.property(48.0, PLAYER_VEHICLE, 0, &[0]);
+    // The recording ends with the enemy detected again
+    let mut detected = vec![1u8];
+    detected.extend_from_slice(&ENEMY_VEHICLE.to_le_bytes());
+    stream.property(50.0, PLAYER_VEHICLE, 1, &detected);
+    stream.packet(0x0A, 60.0, &[]);
+
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    assert_eq!(log.end_time, 60.0);
+
+    let intervals = spotted_intervals(&log, &replay.battle_config);
+    assert_eq!(intervals.len(), 3);
+
+    assert_eq!(intervals[0].vehicle, ENEMY_VEHICLE);
+    assert_eq!(intervals[0].source, SpottingSource::View);
+    assert_eq!((intervals[0].from, intervals[0].to, intervals[0].by_team), (5.0, 15.0, Some(1)));
+
+    assert_eq!(intervals[1].vehicle, PLAYER_VEHICLE);
+    assert_eq!(intervals[1].source, SpottingSource::ObservedByEnemy);
+    assert_eq!((intervals[1].duration(), intervals[1].by_team), (40.0, Some(2)));
+
+    assert_eq!(intervals[2].source, SpottingSource::Detected);
+    assert_eq!((intervals[2].from, intervals[2].to), (50.0, 60.0));
+
+    let ticks = build_ticks(&log, &replay.battle_config, 10.0).unwrap();
+    let spotted: Vec<bool> = ticks.iter().map(|t| t.spotted).collect();
+    assert_eq!(spotted, vec![false, true, true, true, true, false, false]);
+
+    // With several enemy teams (team numbers above 3 included), who saw the recorder is unknown
+    let mut config = battle_config();
+    config.vehicles.get_mut("200").unwrap().team = 5;
+    let third = serde_json::json!({"name": "third", "vehicleType": "usa:A171_TF_4", "team": 7});
+    config.vehicles.insert("400".to_string(), serde_json::from_value(third).unwrap());
+    let intervals = spotted_intervals(&log, &config);
+    assert_eq!(intervals.iter().map(|i| i.by_team).collect::<Vec<_>>(), vec![Some(5), None, Some(5)]);
+}
+
+#[test]
+fn test_ticks_spotted_only_while_observed() {
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+    // The sixth sense lamp outlasts being observed, as it does in the client
+    stream.property(8.0, PLAYER_VEHICLE, 0, &[1]).property(8.0, PLAYER_VEHICLE, 4, &[1]);
+    stream.property(12.0, PLAYER_VEHICLE, 0, &[0]);
+    stream.property(25.0, PLAYER_VEHICLE, 4, &[0]);
+    stream.packet(0x0A, 30.0, &[]);
+
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    let ticks = build_ticks(&log, &replay.battle_config, 10.0).unwrap();
+    assert_eq!(ticks.iter().map(|t| t.spotted).collect::<Vec<_>>(), vec![false, true, false, false]);
+
+    // A corrupt packet time is refused rather than sampled
+    stream.packet(0x0A, 1e30, &[]);
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    assert!(build_ticks(&log, &replay.battle_config, 10.0).is_err());
+}
+
+#[test]
+fn test_arena_updates_are_unpickled() {
+    use replays_parser::arena::{decode_update, DeathReason, Period, PERIOD, VEHICLE_KILLED};
+    use replays_parser::events::Event;
+
+    // cPickle protocol 2 of (victimID, killerID, equipmentID, reason, numVehiclesAffected)
+    let mut killed = vec![0x80, 2, b'(', b'J'];
+    killed.extend_from_slice(&(PLAYER_VEHICLE as i32).to_le_bytes());
+    killed.push(b'J');
+    killed.extend_from_slice(&(ENEMY_VEHICLE as i32).to_le_bytes());
+    killed.extend_from_slice(&[b'K', 0, b'K', 1, b'K', 1, b't', b'q', 1, b'.']);
+
+    match decode_update(VEHICLE_KILLED, &killed).unwrap() {
+        Some(Event::VehicleKilled { victim, killer, reason }) => {
+            assert_eq!((victim, killer, reason), (PLAYER_VEHICLE, ENEMY_VEHICLE, DeathReason::Fire));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // (period, periodEndTime, periodLength, periodAdditionalInfo)
+    let mut period = vec![0x80, 2, b'(', b'K', 3, b'G'];
+    period.extend_from_slice(&30911.5f64.to_be_bytes());
+    period.extend_from_slice(&[b'M', 0x84, 0x03, b']', b't', b'.']);
+
+    match decode_update(PERIOD, &period).unwrap() {
+        Some(Event::ArenaPeriod { period, end_time, length }) => {
+            assert_eq!((period, end_time, length), (Period::Battle, 30911.5, 900.0));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Map and equipment strikes are not SPG shells
+    assert_eq!(DeathReason::from(10), DeathReason::ArtilleryStrike);
+    assert_eq!(serde_json::to_value(DeathReason::from(13)).unwrap(), "artillery_strike");
+}
+
+#[test]
+fn test_pickle_memo_follows_mutations() {
+    use replays_parser::pickle::{loads, PickleValue};
+
+    // cPickle memoizes containers when they are created, before filling them:
+    // ([1, 2], {'a': 1}) followed by both again through GET
+    let data = [
+        0x80, 2, b'(', b']', b'q', 0, b'(', b'K', 1, b'K', 2, b'e', b'}', b'q', 1, b'U', 1, b'a', b'K', 1, b's',
+        b'h', 0, b'h', 1, b't', b'.',
+    ];
+    let list = PickleValue::List(vec![PickleValue::Int(1), PickleValue::Int(2)]);
+    let dict = PickleValue::Dict(vec![(PickleValue::String("a".to_string()), PickleValue::Int(1))]);
+    assert_eq!(loads(&data).unwrap(), PickleValue::Tuple(vec![list.clone(), dict.clone(), list, dict]));
+}
+
+fn battle_event(code: u8, target: u32, details: u64) -> Vec<u8> {
+    counted_battle_event(code, target, 1, details)
+}
+
+fn counted_battle_event(code: u8, target: u32, count: u16, details: u64) -> Vec<u8> {
+    let mut event = vec![code];
+    event.extend_from_slice(&target.to_le_bytes());
+    event.extend_from_slice(&count.to_le_bytes());
+    event.extend_from_slice(&details.to_le_bytes());
+    event
+}
+
+#[test]
+fn test_battle_events_are_decoded() {
+    use replays_parser::arena::DeathReason;
+    use replays_parser::battle_events::{battle_event_totals, BattleEvent, BattleEventTotals, CRIT, DAMAGE, RADIO_ASSIST, SPOTTED, TANKING};
+    use replays_parser::events::Event;
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE);
+
+    let mut ribbons = vec![3u8];
+    ribbons.extend(battle_event(SPOTTED, ENEMY_VEHICLE, 0));
+    ribbons.extend(battle_event(RADIO_ASSIST, ENEMY_VEHICLE, 320 << 32));
+    ribbons.extend(battle_event(DAMAGE, ENEMY_VEHICLE, (450 << 32) | (1 << 24)));
+    stream.call(40.0, AVATAR_ID, 4, &ribbons);
+    stream.call(55.0, AVATAR_ID, 4, &[&[1u8][..], &battle_event(TANKING, ENEMY_VEHICLE, 390 << 32)].concat());
+    // Three modules hit by one shot: the number is the count, the details are the damaged devices
+    stream.call(57.0, AVATAR_ID, 4, &[&[1u8][..], &counted_battle_event(CRIT, ENEMY_VEHICLE, 3, 0x0000_0050_0000_0000)].concat());
+
+    // damage, trackAssist, radioAssist, tankings, lastKillerID, lastDeathReasonID, stunAssist
+    let mut summary = Vec::new();
+    for value in [450u32, 0, 320, 390] {
+        summary.extend_from_slice(&value.to_le_bytes());
+    }
+    summary.extend_from_slice(&0u32.to_le_bytes());
+    summary.push(0xFF);
+    summary.extend_from_slice(&0u32.to_le_bytes());
+    stream.call(60.0, AVATAR_ID, 5, &summary);
+
+    let log = decode_events(&stream.replay(), &definitions());
+    assert_eq!(log.events.len(), 4);
+
+    let Event::BattleEvents { events } = &log.events[0].event else {
+        panic!("unexpected {:?}", log.events[0]);
+    };
+    assert_eq!(log.events[0].time, 40.0);
+    assert_eq!(events[0], BattleEvent::Spotted { target: ENEMY_VEHICLE });
+    assert_eq!(events[2], BattleEvent::Damage { target: ENEMY_VEHICLE, damage: 450, reason: DeathReason::Fire });
+
+    assert!(matches!(&log.events[2].event, Event::BattleEvents { events } if events[0] == BattleEvent::Crit { target: ENEMY_VEHICLE, count: 3 }));
+    match &log.events[3].event {
+        Event::BattleEventsSummary(summary) => {
+            assert_eq!((summary.damage, summary.radio_assist, summary.blocked), (450, 320, 390));
+            assert_eq!((summary.last_killer, summary.last_death_reason), (None, None));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let totals = battle_event_totals(&log.events);
+    assert_eq!((totals.spotted, totals.damage, totals.assist(), totals.blocked, totals.crits), (1, 450, 320, 390, 3));
+
+    // Corrupt amounts saturate instead of overflowing
+    let mut totals = BattleEventTotals::default();
+    totals.add(&BattleEvent::Damage { target: ENEMY_VEHICLE, damage: u32::MAX, reason: DeathReason::Shot });
+    totals.add(&BattleEvent::Damage { target: ENEMY_VEHICLE, damage: 1, reason: DeathReason::Shot });
+    totals.add(&BattleEvent::RadioAssist { target: ENEMY_VEHICLE, damage: u32::MAX });
+    totals.add(&BattleEvent::TrackAssist { target: ENEMY_VEHICLE, damage: 1 });
+    assert_eq!((totals.damage, totals.assist()), (u32::MAX, u32::MAX));
+}
+
+#[test]
+fn test_minimap_positions_are_resolved() {
+    use replays_parser::events::Event;
+    use replays_parser::positions::{minimap_positions, PositionSource};
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(ENEMY_VEHICLE, VEHICLE_TYPE);
+
+    stream.moved(12.0, ENEMY_VEHICLE, [-120.5, 14.0, 301.25], 1.5);
+
+    // Roster indices follow the sorted vehicle IDs: 0 is the player, 1 the enemy
+    let mut update = vec![2u8, 0, 1, 4];
+    for coordinate in [10i16, -20, -121, 301] {
+        update.extend_from_slice(&coordinate.to_le_bytes());
+    }
+    stream.call(14.0, AVATAR_ID, 6, &update);
+
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    let Event::Position(precise) = &log.events[1].event else {
+        panic!("unexpected {:?}", log.events[1]);
+    };
+    assert_eq!((precise.vehicle, precise.x, precise.z), (ENEMY_VEHICLE, -120.5, 301.25));
+    assert_eq!((precise.y, precise.yaw, precise.source), (Some(14.0), Some(1.5), PositionSource::EntityMove));
+
+    let minimap = minimap_positions(&log, &replay.battle_config);
+    let samples: Vec<_> = minimap
+        .iter()
+        .filter_map(|e| match &e.event {
+            Event::Position(p) => Some((e.time, p.vehicle, p.x, p.z, p.source)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        samples,
+        vec![
+            (14.0, PLAYER_VEHICLE, 10.0, -20.0, PositionSource::Minimap),
+            (14.0, ENEMY_VEHICLE, -121.0, 301.0, PositionSource::Minimap),
+        ]
+    );
+}
+
+fn ammo_args(shell: u32, quantity: u16) -> Vec<u8> {
+    let mut args = PLAYER_VEHICLE.to_le_bytes().to_vec();
+    args.extend_from_slice(&shell.to_le_bytes());
+    args.extend_from_slice(&quantity.to_le_bytes());
+    args.extend_from_slice(&[1, 0, 0, 0, 0, 0]); // quantityInClip, previousStage, timeRemaining, totalTime
+    args
+}
+
+#[test]
+fn test_own_vehicle_timeline() {
+    use replays_parser::arena::DeathReason;
+    use replays_parser::events::Event;
+    use replays_parser::own_vehicle::own_vehicle_timeline;
+
+    const AP: u32 = 0x1A01;
+    const HE: u32 = 0x1B01;
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE);
+    stream.call(0.0, AVATAR_ID, 7, &ammo_args(AP, 30));
+    stream.call(0.0, AVATAR_ID, 7, &ammo_args(HE, 10));
+    stream.call(0.0, AVATAR_ID, 8, &[&PLAYER_VEHICLE.to_le_bytes()[..], &[0], &AP.to_le_bytes()].concat());
+    stream.call(42.0, AVATAR_ID, 7, &ammo_args(AP, 29));
+    stream.call(50.0, AVATAR_ID, 8, &[&PLAYER_VEHICLE.to_le_bytes()[..], &[0], &HE.to_le_bytes()].concat());
+    stream.call(51.0, AVATAR_ID, 9, &ENEMY_VEHICLE.to_le_bytes());
+    stream.call(58.0, AVATAR_ID, 9, &0u32.to_le_bytes());
+    // A pickled {'kills': 1}
+    stream.call(60.0, AVATAR_ID, 10, &[16, 0x80, 2, b'}', b'q', 1, b'U', 5, b'k', b'i', b'l', b'l', b's', b'K', 1, b's', b'.']);
+    // updateVehicleHealth(vehicleID, health, deathReasonID, isCrewActive, isRespawn)
+    let health = |health: i16, reason: i8| [&PLAYER_VEHICLE.to_le_bytes()[..], &health.to_le_bytes(), &[reason as u8, 1, 0]].concat();
+    stream.call(61.0, AVATAR_ID, 13, &health(1200, -1));
+    stream.call(70.0, AVATAR_ID, 13, &health(0, 1));
+    // updateTargetingInfo: turret yaw, gun pitch, rotation speeds, dispersion factors, aiming time
+    let targeting: Vec<u8> = [0.5f32, -0.1, 0.8, 0.6, 1.0, 0.09, 0.2, 0.15, 2.3].iter().flat_map(|v| v.to_le_bytes()).collect();
+    stream.call(62.0, AVATAR_ID, 14, &targeting);
+    stream.call(63.0, AVATAR_ID, 15, &[&PLAYER_VEHICLE.to_le_bytes()[..], &[1]].concat());
+    stream.call(68.0, AVATAR_ID, 15, &[&PLAYER_VEHICLE.to_le_bytes()[..], &[0]].concat());
+
+    let log = decode_events(&stream.replay(), &definitions());
+    let timeline = own_vehicle_timeline(&log);
+
+    assert_eq!(timeline.ammo[&AP].len(), 2);
+    assert_eq!(timeline.shells_at(10.0).into_iter().collect::<Vec<_>>(), vec![(AP, 30), (HE, 10)]);
+    assert_eq!(timeline.shells_at(45.0)[&AP], 29);
+    assert_eq!(timeline.loaded_shell_at(49.0), Some(AP));
+    assert_eq!(timeline.loaded_shell_at(55.0), Some(HE));
+
+    let targets: Vec<_> = timeline.target.iter().map(|t| (t.time, t.value)).collect();
+    assert_eq!(targets, vec![(51.0, Some(ENEMY_VEHICLE)), (58.0, None)]);
+    assert_eq!(timeline.private_stats[0].value, serde_json::json!({"kills": 1}));
+
+    let health: Vec<_> = timeline.health.iter().map(|h| (h.time, h.value)).collect();
+    assert_eq!(health, vec![(61.0, 1200), (70.0, 0)]);
+    let deaths: Vec<_> = log
+        .events
+        .iter()
+        .filter_map(|e| match &e.event {
+            Event::VehicleHealth { vehicle, death_reason, is_crew_active, .. } => Some((*vehicle, *death_reason, *is_crew_active)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(deaths, vec![(PLAYER_VEHICLE, None, true), (PLAYER_VEHICLE, Some(DeathReason::Fire), true)]);
+
+    let targeting = &timeline.targeting[0];
+    assert_eq!(targeting.time, 62.0);
+    assert_eq!((targeting.value.turret_yaw, targeting.value.gun_pitch), (0.5, -0.1));
+    assert_eq!(
+        (targeting.value.shot_disp_multiplier_factor, targeting.value.chassis_shot_dispersion_factors_rotation, targeting.value.aiming_time),
+        (1.0, 0.15, 2.3)
+    );
+
+    let changer: Vec<_> = timeline.quick_shell_changer.iter().map(|c| (c.time, c.value)).collect();
+    assert_eq!(changer, vec![(63.0, true), (68.0, false)]);
+}
+
+fn damaged_devices_args(vehicle: u32, damaged: &[u8], destroyed: &[u8]) -> Vec<u8> {
+    let mut args = vehicle.to_le_bytes().to_vec();
+    args.push(damaged.len() as u8);
+    args.extend_from_slice(damaged);
+    args.push(destroyed.len() as u8);
+    args.extend_from_slice(destroyed);
+    args
+}
+
+#[test]
+fn test_module_damage_transitions() {
+    use replays_parser::devices::{destroyed_intervals, device_changes, CrewRole, Module, ModuleState};
+    use replays_parser::events::Event;
+
+    const ENGINE: u8 = 4;
+    const LEFT_TRACK: u8 = 6;
+    const GUNNER: u8 = 15;
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(ENEMY_VEHICLE, VEHICLE_TYPE);
+    stream.call(10.0, AVATAR_ID, 11, &damaged_devices_args(ENEMY_VEHICLE, &[ENGINE], &[LEFT_TRACK, GUNNER]));
+    stream.call(18.0, AVATAR_ID, 11, &damaged_devices_args(ENEMY_VEHICLE, &[ENGINE], &[]));
+    stream.call(30.0, AVATAR_ID, 11, &damaged_devices_args(ENEMY_VEHICLE, &[], &[]));
+    // showAmmoBayEffect(mode, fireballVolume, projectedTurretSpeed)
+    stream.call(40.0, ENEMY_VEHICLE, 2, &[&[1u8][..], &2.0f32.to_le_bytes(), &0.5f32.to_le_bytes()].concat());
+
+    let log = decode_events(&stream.replay(), &definitions());
+    let changes = device_changes(&log);
+    let summary: Vec<_> = changes
+        .iter()
+        .map(|e| match &e.event {
+            Event::ModuleDamaged { module, state, .. } => (e.time, format!("{:?} {:?}", module, state)),
+            Event::CrewInjured { crew, .. } => (e.time, format!("{:?} injured", crew)),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (10.0, "Engine Damaged".to_string()),
+            (10.0, "LeftTrack Destroyed".to_string()),
+            (10.0, format!("{:?} injured", CrewRole::Gunner)),
+            (18.0, "LeftTrack Repaired".to_string()),
+            (30.0, "Engine Repaired".to_string()),
+        ]
+    );
+
+    let ammo_bay = log.events.last().unwrap();
+    assert!(matches!(
+        ammo_bay.event,
+        Event::ModuleDamaged { vehicle: ENEMY_VEHICLE, module: Module::AmmoBay, state: ModuleState::Destroyed }
+    ));
+
+    let intervals = destroyed_intervals(&changes, log.end_time);
+    assert_eq!(intervals.len(), 1);
+    assert_eq!((intervals[0].module, intervals[0].duration()), (Module::LeftTrack, 8.0));
+}
+
+// TRACK_DESTROYED value: [? (4)] [TrackID (1)]
+fn track_value(track: u8) -> Vec<u8> {
+    [&[0u8; 4][..], &[track]].concat()
+}
+
+// MODULE_DAMAGE args: [Target (4)] [? (2)] [Source (4)]
+fn module_damage_args(target: u32, source: u32) -> Vec<u8> {
+    [&target.to_le_bytes()[..], &[0, 0], &source.to_le_bytes()].concat()
+}
+
+/// `ModuleHit` and the per-track `ModuleDamaged` changes of `log`, as text.
+fn track_and_module_damage(log: &replays_parser::events::EventLog) -> Vec<(f32, String)> {
+    use replays_parser::devices::device_changes;
+    use replays_parser::events::Event;
+
+    let hits = log.events.iter().filter_map(|e| match &e.event {
+        Event::ModuleHit { vehicle, attacker } => Some((e.time, format!("{} hit by {}", vehicle, attacker))),
+        _ => None,
+    });
+    let changes: Vec<_> = device_changes(log)
+        .into_iter()
+        .map(|e| match e.event {
+            Event::ModuleDamaged { vehicle, module, state } => (e.time, format!("{} {:?} {:?}", vehicle, module, state)),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    hits.chain(changes).collect()
+}
+
+#[test]
+fn test_track_and_module_damage_subtypes() {
+    use replays_parser::devices::{destroyed_intervals, device_changes, Module};
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+    stream.call(11.0, PLAYER_VEHICLE, 0x0B, &module_damage_args(PLAYER_VEHICLE, ENEMY_VEHICLE));
+    stream.property(12.0, PLAYER_VEHICLE, 0x07, &track_value(0x1D));
+    stream.property(15.0, PLAYER_VEHICLE, 0x07, &track_value(0x1E));
+    stream.property(20.0, PLAYER_VEHICLE, 0x07, &track_value(0x00));
+
+    let log = decode_events(&stream.replay(), &definitions());
+    assert_eq!(
+        track_and_module_damage(&log),
+        vec![
+            (11.0, "200 hit by 300".to_string()),
+            (12.0, "200 LeftTrack Destroyed".to_string()),
+            (15.0, "200 LeftTrack Repaired".to_string()),
+            (15.0, "200 RightTrack Destroyed".to_string()),
+            (20.0, "200 RightTrack Repaired".to_string()),
+        ]
+    );
+
+    let intervals = destroyed_intervals(&device_changes(&log), log.end_time);
+    let tracks: Vec<_> = intervals.iter().map(|i| (i.vehicle, i.module, i.duration())).collect();
+    assert_eq!(tracks, vec![(PLAYER_VEHICLE, Module::LeftTrack, 3.0), (PLAYER_VEHICLE, Module::RightTrack, 5.0)]);
+}
+
+/// The shipped definitions: `packetTypes` from `message_codes/`, entities from `ids.json`.
+fn shipped_definitions() -> Definitions {
+    let mut defs = Definitions::load("wot_eu").unwrap();
+    // ids.json maps IDs straight to names
+    let ids: serde_json::Value = serde_json::from_str(&std::fs::read_to_string("ids.json").unwrap()).unwrap();
+    let named = |table: &serde_json::Value| -> serde_json::Value {
+        table.as_object().unwrap().iter().map(|(id, name)| (id.clone(), serde_json::json!({"name": name}))).collect()
+    };
+    let entities: serde_json::Map<_, _> = ids
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(key, entity)| {
+            let entity = serde_json::json!({
+                "id": entity["id"],
+                "name": entity["name"],
+                "clientMethods": named(&entity["clientMethods"]),
+                "properties": named(&entity["properties"]),
+                "cellMethods": named(&entity["cellMethods"]),
+                "baseMethods": named(&entity["baseMethods"]),
+            });
+            (key.clone(), entity)
+        })
+        .collect();
+    defs.merge(serde_json::from_value(serde_json::json!({"entities": entities})).unwrap());
+    defs
+}
+
+#[test]
+fn test_subtypes_with_shipped_definitions() {
+    use replays_parser::events::Event;
+
+    let defs = shipped_definitions();
+    // The entity definitions name both IDs themselves
+    let vehicle = defs.entity_by_type(VEHICLE_TYPE).unwrap();
+    assert_eq!(vehicle.property(0x07).unwrap().name, "perksRibbonNotify");
+    assert_eq!(vehicle.client_method(0x05).unwrap().name, "showDamageFromShot");
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+    stream.call(11.0, PLAYER_VEHICLE, 0x0B, &module_damage_args(PLAYER_VEHICLE, ENEMY_VEHICLE));
+    stream.property(12.0, PLAYER_VEHICLE, 0x07, &track_value(0x1E));
+    stream.property(20.0, PLAYER_VEHICLE, 0x07, &track_value(0x00));
+    // showDamageFromShot keeps its name next to the SHOT_HIT subtype
+    stream.call(25.0, PLAYER_VEHICLE, 0x05, &[&ENEMY_VEHICLE.to_le_bytes()[..], &[0], &[0], &[1]].concat());
+
+    let log = decode_events(&stream.replay(), &defs);
+    assert_eq!(
+        track_and_module_damage(&log),
+        vec![
+            (11.0, "200 hit by 300".to_string()),
+            (12.0, "200 RightTrack Destroyed".to_string()),
+            (20.0, "200 RightTrack Repaired".to_string()),
+        ]
+    );
+    assert!(log.events.iter().any(|e| matches!(e.event, Event::DamageFromShot { attacker: ENEMY_VEHICLE, .. })));
+}
+
+fn explosion_args(attacker: u32, center: [f32; 3]) -> Vec<u8> {
+    let mut args = attacker.to_le_bytes().to_vec();
+    vec3(&mut args, center);
+    args.extend_from_slice(&[0, 1]); // effectsIndex, damageFactor
+    args
+}
+
+fn stun_info(finish_time: f64, duration: f32) -> Vec<u8> {
+    [&finish_time.to_le_bytes()[..], &duration.to_le_bytes()].concat()
+}
+
+#[test]
+fn test_artillery_shots_and_stuns() {
+    use arrow::array::AsArray;
+    use arrow::datatypes::UInt32Type;
+    use replays_parser::artillery::{arty_shots, spg_hits_while_stationary, stun_intervals};
+    use replays_parser::columnar::{summaries_batch, Row};
+    use replays_parser::summary::summarize;
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+    stream.moved(5.0, PLAYER_VEHICLE, [100.0, 0.0, 100.0], 0.0);
+    stream.moved(9.5, PLAYER_VEHICLE, [100.2, 0.0, 100.1], 0.0);
+
+    // A stunning shell lands next to the stationary player
+    stream.call(10.0, PLAYER_VEHICLE, 3, &explosion_args(ENEMY_VEHICLE, [101.0, 0.0, 102.0]));
+    stream.property(10.1, PLAYER_VEHICLE, 2, &stun_info(1000.0, 12.0));
+    let mut hit = Vec::new();
+    vec3(&mut hit, [102.0, 0.0, 103.0]);
+    stream.call(10.2, AVATAR_ID, 12, &hit);
+
+    // A miss further away, and a regular HE shell that neither stuns nor is reported as SPG fire
+    let mut miss = Vec::new();
+    vec3(&mut miss, [150.0, 0.0, 150.0]);
+    stream.call(30.0, AVATAR_ID, 12, &miss);
+    stream.moved(38.0, PLAYER_VEHICLE, [140.0, 0.0, 100.0], 0.0);
+    stream.call(40.0, PLAYER_VEHICLE, 3, &explosion_args(ENEMY_VEHICLE, [140.0, 0.0, 101.0]));
+    stream.packet(0x0A, 50.0, &[]);
+
+    let log = decode_events(&stream.replay(), &definitions());
+
+    let stuns = stun_intervals(&log);
+    assert_eq!(stuns.len(), 1);
+    assert_eq!((stuns[0].vehicle, stuns[0].from, stuns[0].duration()), (PLAYER_VEHICLE, 10.1, 12.0));
+
+    let shots = arty_shots(&log);
+    assert_eq!(shots.len(), 2);
+    assert_eq!((shots[0].shooter, shots[0].victims.clone()), (Some(ENEMY_VEHICLE), vec![PLAYER_VEHICLE]));
+    assert_eq!((shots[0].impact_point, shots[0].stun_duration), ([101.0, 0.0, 102.0], Some(12.0)));
+    assert_eq!((shots[1].shooter, shots[1].time), (None, 30.0));
+
+    assert_eq!(spg_hits_while_stationary(&log, &shots, PLAYER_VEHICLE), 1);
+
+    // The count reaches the summary and its table
+    let summary = summarize(&stream.replay(), &log);
+    assert_eq!(summary.spg_hits_while_stationary, 1);
+    let batch = summaries_batch(&[Row { replay: "a.wotreplay".to_string(), battle_id: None, record: summary, labels: None }]).unwrap();
+    let column = batch.column_by_name("spg_hits_while_stationary").unwrap().as_primitive::<UInt32Type>();
+    assert_eq!(column.value(0), 1);
+}
+
+#[test]
+fn test_battle_identity() {
+    use replays_parser::dedupe::{battle_id, BattleId};
+
+    const ARENA_ID: u64 = 3850942432522416;
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE);
+    stream.property(1.0, PLAYER_VEHICLE, 3, &ARENA_ID.to_le_bytes());
+    let replay = stream.replay();
+    let log = decode_events(&replay, &definitions());
+    assert_eq!(battle_id(&replay, &log), BattleId::Arena(ARENA_ID));
+    assert_eq!(battle_id(&replay, &log).to_string(), "3850942432522416");
+
+    // Without the property or battle results, perspectives match on map, start time and roster
+    let empty = decode_events(&StreamBuilder::default().replay(), &definitions());
+    let mut other_player = StreamBuilder::default().replay();
+    other_player.battle_config.player_name = "enemy".to_string();
+    let fingerprint = battle_id(&StreamBuilder::default().replay(), &empty);
+    assert!(matches!(fingerprint, BattleId::Fingerprint(_)));
+    assert!(fingerprint.to_string().starts_with("fp-"));
+    assert_eq!(battle_id(&other_player, &empty), fingerprint);
+
+    let mut other_battle = StreamBuilder::default().replay();
+    other_battle.battle_config.date_time = "19.02.2025 17:35:02".to_string();
+    assert_ne!(battle_id(&other_battle, &empty), fingerprint);
+
+    // The battle results win over the fingerprint
+    other_battle.battle_results = Some(serde_json::json!([{"arenaUniqueID": 42}, {}]));
+    assert_eq!(battle_id(&other_battle, &empty), BattleId::Arena(42));
+    assert_eq!(BattleId::Arena(42).shard_key(), replays_parser::shards::fnv1a(&42u64.to_le_bytes()));
+}
+
+#[test]
+fn test_packet_filters() {
+    use replays_parser::events::{decode_events_filtered, Event};
+    use replays_parser::packet_filter::{parse_packet_type, PacketFilter, TimeRange};
+    use replays_parser::packet_stream::PacketStream;
+
+    let mut stream = StreamBuilder::default();
+    stream.create(AVATAR_ID, AVATAR_TYPE).create(PLAYER_VEHICLE, VEHICLE_TYPE).create(ENEMY_VEHICLE, VEHICLE_TYPE);
+    for (time, shooter, shot_id) in [(10.0, PLAYER_VEHICLE, 1), (20.0, PLAYER_VEHICLE, 2), (30.0, ENEMY_VEHICLE, 3)] {
+        stream.moved(time - 1.0, shooter, [0.0; 3], 0.0);
+        stream.call(time, shooter, 0, &[1, 0]);
+        stream.call(time, AVATAR_ID, 0, &tracer_args(shooter, shot_id, false));
+    }
+    let replay = stream.replay();
+    let defs = definitions();
+
+    // Method globs see through to the entity type, even though creates are not 0x08
+    let filter = PacketFilter {
+        packet_types: vec![parse_packet_type("0x08").unwrap()],
+        methods: replays_parser::inputs::glob_set(&["Vehicle.show*".to_string()]).unwrap(),
+        time: Some("15..".parse::<TimeRange>().unwrap()),
+        ..Default::default()
+    };
+    let log = decode_events_filtered(&replay, &defs, &filter);
+    let times: Vec<f32> = log.events.iter().map(|e| e.time).collect();
+    assert_eq!(times, vec![20.0, 30.0]);
+    assert!(log.events.iter().all(|e| matches!(e.event, Event::ShotFired { .. })));
+    assert_eq!(log.end_time, 30.0);
+
+    let avatar = PacketFilter { entity_types: vec!["Avatar".to_string()], ..Default::default() };
+    assert_eq!(decode_events_filtered(&replay, &defs, &avatar).events.len(), 3);
+
+    // Type or time alone still resolves entities created outside the filter
+    let calls = PacketFilter { packet_types: vec![0x08], ..Default::default() };
+    let log = decode_events_filtered(&replay, &defs, &calls);
+    assert_eq!(log.events.len(), 6);
+    assert!(log.events.iter().all(|e| !matches!(e.event, Event::Position(_) | Event::VehicleEntered { .. })));
+    let window = PacketFilter { time: Some("15..25".parse().unwrap()), ..Default::default() };
+    let times: Vec<f32> = decode_events_filtered(&replay, &defs, &window).events.iter().map(|e| e.time).collect();
+    assert_eq!(times, vec![19.0, 20.0, 20.0]);
+
+    // Header filters skip payloads in the stream itself; only entity creations pass through
+    let moves = PacketFilter { packet_types: vec![0x0A], time: Some("..25".parse().unwrap()), ..Default::default() };
+    let mut cursor = std::io::Cursor::new(replay.packets_buffer.clone());
+    let mut packets = PacketStream::with_filter(&mut cursor, &moves);
+    let read: Vec<(u32, f32)> = packets.by_ref().map(|p| p.map(|p| (p.packet_type, p.time)).unwrap()).collect();
+    assert_eq!(read, vec![(0x00, 0.0), (0x05, 0.0), (0x05, 0.0), (0x0A, 9.0), (0x0A, 19.0)]);
+    assert_eq!(packets.end_time(), 30.0);
+
+    assert_eq!(parse_packet_type("10").unwrap(), 0x0A);
+    assert!("120-180".parse::<TimeRange>().is_err());
+}
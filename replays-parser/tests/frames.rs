@@ -0,0 +1,92 @@
+use replays_parser::frames::{decode_payload, encode_frame, write_payload, FrameFormat, FrameReader};
+use replays_parser::ticks::Tick;
+use serde_json::json;
+
+#[test]
+fn test_frames_round_trip() {
+    let tick = json!({"type": "tick", "replay": "a.wotreplay", "time": 1.5, "spotted": true});
+    let header = json!({"type": "header", "replay": "a.wotreplay", "header": {"magic": 1, "block_count": 2}});
+    for format in [FrameFormat::MessagePack, FrameFormat::Cbor] {
+        let mut stream = Vec::new();
+        encode_frame(&mut stream, format, &header).unwrap();
+        encode_frame(&mut stream, format, &tick).unwrap();
+        // Big-endian length prefix, as Erlang's {packet, 4}
+        let len = u32::from_be_bytes(stream[..4].try_into().unwrap()) as usize;
+        assert!(stream.len() > 4 + len);
+
+        let records: Vec<_> = FrameReader::new(stream.as_slice(), format).map(Result::unwrap).collect();
+        assert_eq!(records, vec![header.clone(), tick.clone()], "{:?}", format);
+
+        // Payloads copied as they are make the same stream
+        let mut reader = FrameReader::new(stream.as_slice(), format);
+        let mut copy = Vec::new();
+        while let Some(payload) = reader.read_payload().unwrap() {
+            assert_eq!(decode_payload::<serde_json::Value>(&payload, format).unwrap()["replay"], "a.wotreplay");
+            write_payload(&mut copy, &payload).unwrap();
+        }
+        assert_eq!(copy, stream);
+    }
+}
+
+#[test]
+fn test_frames_structs_as_maps() {
+    let mut stream = Vec::new();
+    encode_frame(&mut stream, FrameFormat::MessagePack, &Tick { time: 0.5, spotted: false }).unwrap();
+    let mut reader = FrameReader::new(stream.as_slice(), FrameFormat::MessagePack);
+    assert_eq!(reader.read::<serde_json::Value>().unwrap(), Some(json!({"time": 0.5, "spotted": false})));
+    assert!(reader.next().is_none());
+
+    // A stream cut inside a frame is an error, not a silent end
+    stream.truncate(stream.len() - 1);
+    let mut reader = FrameReader::new(stream.as_slice(), FrameFormat::MessagePack);
+    assert!(reader.next().unwrap().is_err());
+}
+
+#[test]
+fn test_etf_terms() {
+    use replays_parser::etf;
+    use std::collections::BTreeMap;
+
+    // %{time: 0.5, spotted: false}: struct fields are atoms
+    let tick = etf::to_vec(&Tick { time: 0.5, spotted: false }).unwrap();
+    let mut expected = vec![131, 116, 0, 0, 0, 2, 119, 4];
+    expected.extend(b"time");
+    expected.extend([70, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0, 119, 7]);
+    expected.extend(b"spotted");
+    expected.extend([119, 5]);
+    expected.extend(b"false");
+    assert_eq!(tick, expected);
+
+    // Data maps keep binary keys: %{"200" => [-1, 300, 5000000000]}
+    let roster = BTreeMap::from([("200", vec![-1i64, 300, 5_000_000_000])]);
+    let mut expected = vec![131, 116, 0, 0, 0, 1, 109, 0, 0, 0, 3];
+    expected.extend(b"200");
+    expected.extend([108, 0, 0, 0, 3, 98, 255, 255, 255, 255, 98, 0, 0, 1, 44, 110, 5, 0, 0, 0xf2, 0x05, 0x2a, 1, 106]);
+    assert_eq!(etf::to_vec(&roster).unwrap(), expected);
+
+    assert_eq!(etf::to_vec(&Vec::<u8>::new()).unwrap(), vec![131, 106]);
+    assert_eq!(etf::to_vec(&(1u8, None::<u8>)).unwrap(), vec![131, 104, 2, 97, 1, 119, 3, b'n', b'i', b'l']);
+}
+
+#[test]
+fn test_etf_frames_round_trip() {
+    use replays_parser::events::{Event, TimedEvent};
+
+    let event = TimedEvent { time: 0.1, event: Event::ShotFired { shooter: 300, burst_count: 1, gun_index: 0 } };
+    let mut stream = Vec::new();
+    encode_frame(&mut stream, FrameFormat::Etf, &event).unwrap();
+    encode_frame(&mut stream, FrameFormat::Etf, &json!({"personal": {"59393": {"damageDealt": 300}}})).unwrap();
+
+    let records: Vec<_> = FrameReader::new(stream.as_slice(), FrameFormat::Etf).map(Result::unwrap).collect();
+    assert_eq!(
+        records,
+        vec![
+            json!({"time": 0.1, "event": "shot_fired", "shooter": 300, "burst_count": 1, "gun_index": 0}),
+            json!({"personal": {"59393": {"damageDealt": 300}}}),
+        ]
+    );
+    // The flattened event fields are atoms, the JSON object's keys binaries
+    assert_eq!(&stream[4..7], &[131, 116, 0]);
+    assert!(stream.windows(6).any(|w| w == [119, 7, b's', b'h', b'o', b'o']));
+    assert!(stream.windows(9).any(|w| w == [109, 0, 0, 0, 8, b'p', b'e', b'r', b's']));
+}
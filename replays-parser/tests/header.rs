@@ -0,0 +1,37 @@
+use replays_parser::Parser;
+
+fn block(out: &mut Vec<u8>, json: serde_json::Value) {
+    let bytes = serde_json::to_vec(&json).unwrap();
+    out.extend((bytes.len() as u32).to_le_bytes());
+    out.extend(bytes);
+}
+
+#[test]
+fn test_parse_header_stops_before_binary_block() {
+    let mut replay = Vec::new();
+    replay.extend(0x11343212u32.to_le_bytes());
+    replay.extend(2u32.to_le_bytes());
+    block(
+        &mut replay,
+        serde_json::json!({
+            "playerName": "recorder",
+            "playerVehicle": "ussr-R155_Object_277",
+            "clientVersionFromXml": "1.32.0",
+            "clientVersionFromExe": "1.32.0.0",
+            "dateTime": "19.02.2025 17:20:10",
+            "mapName": "04_himmelsdorf",
+            "gameplayID": "ctf"
+        }),
+    );
+    block(&mut replay, serde_json::json!([{"arenaUniqueID": 3850942432522416u64, "common": {"winnerTeam": 2}}]));
+    // A binary block cut short: the full parse fails, the header parse never reaches it
+    replay.extend([0x10, 0, 0, 0, 0x40, 0, 0, 0, 1, 2, 3]);
+
+    let header = Parser::parse_header(replay.as_slice()).unwrap();
+    assert_eq!(header.battle_config.map_name, "04_himmelsdorf");
+    assert_eq!((header.arena_unique_id(), header.winner_team()), (Some(3850942432522416), Some(2)));
+    assert!(header.packets_buffer.is_empty());
+    assert!(Parser::parse_bytes(replay.clone()).is_err());
+
+    assert!(Parser::parse_header(&replay[4..]).is_err());
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::battle_config_for;
+use replays_parser::arena::{DeathReason, Period};
+use replays_parser::events::{Event, EventLog, TimedEvent};
+use replays_parser::heatmap::{Heatmap, HeatmapFilter, Outcome, Who};
+use replays_parser::maps::MapCatalog;
+use replays_parser::positions::PositionSource;
+use replays_parser::types::BattleConfig;
+use replays_parser::vehicles::{VehicleCatalog, VehicleClass};
+use std::fs;
+
+const SPG: u32 = 200;
+const HEAVY: u32 = 300;
+
+fn battle_config() -> BattleConfig {
+    battle_config_for("recorder", [("recorder", "germany:G02_Hummel"), ("enemy", "france:F10_AMX_50B")])
+}
+
+fn position(time: f32, vehicle: u32, x: f32, z: f32) -> TimedEvent {
+    common::position(time, vehicle, x, z, PositionSource::EntityMove)
+}
+
+/// Battle starts at 10 s. The SPG sits in the north-west corner, the heavy in the
+/// south-east corner until it dies at 15 s.
+fn event_log() -> EventLog {
+    let mut events = vec![TimedEvent {
+        time: 10.0,
+        event: Event::ArenaPeriod { period: Period::Battle, end_time: 0.0, length: 0.0 },
+    }];
+    for step in 0..=10 {
+        let time = step as f32 * 2.0;
+        events.push(position(time, SPG, -250.0, 350.0));
+        events.push(position(time, HEAVY, 350.0, -250.0));
+    }
+    events.push(TimedEvent {
+        time: 15.0,
+        event: Event::VehicleKilled { victim: HEAVY, killer: SPG, reason: DeathReason::Shot },
+    });
+    events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    EventLog { events, end_time: 20.0 }
+}
+
+fn vehicles() -> VehicleCatalog {
+    let mut vehicles = VehicleCatalog::default();
+    vehicles.insert("germany:G02_Hummel", VehicleClass::Artillery);
+    vehicles.insert("F10_AMX_50B", VehicleClass::Heavy);
+    vehicles
+}
+
+fn accumulate(filter: &HeatmapFilter) -> Heatmap {
+    let maps = MapCatalog::builtin();
+    // Himmelsdorf spans 700 m: 100 m per cell
+    let mut heatmap = Heatmap::new(7).unwrap();
+    heatmap.accumulate(
+        &event_log(),
+        &battle_config(),
+        Some(1),
+        maps.get("04_himmelsdorf").unwrap(),
+        &vehicles(),
+        filter,
+        1.0,
+    );
+    heatmap
+}
+
+#[test]
+fn test_heatmap_samples_living_vehicles_during_battle() {
+    let all = accumulate(&HeatmapFilter::default());
+    // 10..=20 s for the SPG, 10..=14 s for the heavy
+    assert_eq!(all.get(0, 0), 11.0);
+    assert_eq!(all.get(6, 6), 5.0);
+    assert_eq!(all.samples, 16);
+    assert_eq!(all.cells.iter().sum::<f32>(), 16.0);
+
+    let spg = accumulate(&HeatmapFilter { class: Some(VehicleClass::Artillery), ..Default::default() });
+    assert_eq!((spg.get(0, 0), spg.get(6, 6)), (11.0, 0.0));
+
+    let player = accumulate(&HeatmapFilter { who: Who::Player, ..Default::default() });
+    assert_eq!(player, spg);
+
+    let losers = accumulate(&HeatmapFilter { outcome: Some(Outcome::Loss), ..Default::default() });
+    assert_eq!((losers.get(0, 0), losers.get(6, 6)), (0.0, 5.0));
+
+    // First three seconds of the battle on the team 2 side
+    let opening = accumulate(&HeatmapFilter { team: Some(2), to: Some(3.0), ..Default::default() });
+    assert_eq!((opening.get(6, 6), opening.samples), (4.0, 4));
+}
+
+#[test]
+fn test_heatmap_npy_layout() {
+    assert!(Heatmap::new(0).is_err());
+    let mut heatmap = Heatmap::new(3).unwrap();
+    heatmap.cells[1] = 2.5;
+    let path = std::env::temp_dir().join(format!("replays-parser-heatmap-{}.npy", std::process::id()));
+    heatmap.write_npy(&path).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+    assert!(header.contains("'shape': (3, 3)"));
+    assert!(header.ends_with('\n'));
+    assert_eq!((10 + header_len) % 64, 0);
+    let data = &bytes[10 + header_len..];
+    assert_eq!(data.len(), 9 * 4);
+    assert_eq!(f32::from_le_bytes(data[4..8].try_into().unwrap()), 2.5);
+}
+
+#[test]
+fn test_vehicle_classes() {
+    let path = std::env::temp_dir().join(format!("replays-parser-vehicles-{}.json", std::process::id()));
+    fs::write(&path, r#"{"germany:G02_Hummel": "spg", "F10_AMX_50B": "heavyTank"}"#).unwrap();
+    let mut vehicles = VehicleCatalog::default();
+    vehicles.load_json(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(vehicles.class_of("germany:G02_Hummel"), Some(VehicleClass::Artillery));
+    // Keys without the nation match any nation
+    assert_eq!(vehicles.class_of("france:F10_AMX_50B"), Some(VehicleClass::Heavy));
+    assert_eq!(vehicles.class_of("ussr:R155_Object_277"), None);
+    assert_eq!("AT-SPG".parse::<VehicleClass>().unwrap(), VehicleClass::TankDestroyer);
+    assert!("boat".parse::<VehicleClass>().is_err());
+}
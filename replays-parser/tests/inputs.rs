@@ -0,0 +1,76 @@
+use replays_parser::inputs::{discover, Filter};
+use std::io::Write;
+use std::path::Path;
+
+fn write_zip(path: &Path, members: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    for (name, bytes) in members {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn write_tar_gz(path: &Path, members: &[(&str, &[u8])]) {
+    let gz = flate2::write::GzEncoder::new(std::fs::File::create(path).unwrap(), flate2::Compression::fast());
+    let mut tar = tar::Builder::new(gz);
+    for (name, bytes) in members {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, name, *bytes).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn test_discover_walks_directories_and_archives() {
+    let dir = std::env::temp_dir().join(format!("replays-parser-inputs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("2025/02/19")).unwrap();
+    std::fs::write(dir.join("2025/02/19/a.wotreplay"), b"a").unwrap();
+    std::fs::write(dir.join("2025/02/19/b.mtreplay"), b"b").unwrap();
+    std::fs::write(dir.join("2025/02/19/notes.txt"), b"-").unwrap();
+    write_zip(&dir.join("2025/uploads.zip"), &[("c.wotreplay", b"c"), ("readme.md", b"-"), ("old/d.wotreplay", b"d")]);
+    write_tar_gz(&dir.join("batch.tar.gz"), &[("e.wotreplay", b"e")]);
+
+    let found = |filter: &Filter| -> Vec<(String, Vec<u8>)> {
+        let mut inputs = discover(&dir, filter).unwrap();
+        inputs.sort_by(|a, b| a.path().cmp(b.path()));
+        inputs
+            .iter()
+            .map(|input| {
+                // Named by the path relative to the input, so equal file names do not collide
+                assert_eq!(input.path(), dir.join(input.name()));
+                (input.name().to_string_lossy().into_owned(), input.read().unwrap())
+            })
+            .collect()
+    };
+    let all = found(&Filter::default());
+    assert_eq!(
+        all,
+        vec![
+            ("2025/02/19/a.wotreplay".to_string(), b"a".to_vec()),
+            ("2025/02/19/b.mtreplay".to_string(), b"b".to_vec()),
+            ("2025/uploads.zip/c.wotreplay".to_string(), b"c".to_vec()),
+            ("2025/uploads.zip/old/d.wotreplay".to_string(), b"d".to_vec()),
+            ("batch.tar.gz/e.wotreplay".to_string(), b"e".to_vec()),
+        ]
+    );
+
+    let names = |filter| found(&filter).into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    let globs = |globs: &[&str]| globs.iter().map(|glob| glob.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        names(Filter::new(&globs(&["*.wotreplay"]), &globs(&["**/old/**", "*.tar.gz"])).unwrap()),
+        vec!["2025/02/19/a.wotreplay", "2025/uploads.zip/c.wotreplay"]
+    );
+    assert!(Filter::new(&globs(&["a{"]), &[]).is_err());
+
+    // An archive named directly is expanded, a replay named directly taken as is
+    let members = discover(&dir.join("batch.tar.gz"), &Filter::default()).unwrap();
+    assert_eq!(members.iter().map(|input| input.name().to_path_buf()).collect::<Vec<_>>(), vec![Path::new("e.wotreplay")]);
+    assert_eq!(members[0].stat().unwrap().0, 1);
+    assert_eq!(discover(&dir.join("2025/02/19/notes.txt"), &Filter::default()).unwrap().len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,59 @@
+use replays_parser::maps::MapCatalog;
+use std::fs;
+
+#[test]
+fn test_builtin_bounds_and_grid_squares() {
+    let maps = MapCatalog::builtin();
+    // -300..400 on both axes
+    let himmelsdorf = maps.get("04_himmelsdorf").unwrap();
+
+    let top_left = himmelsdorf.normalize(-300.0, 400.0);
+    assert_eq!((top_left.x, top_left.y, top_left.square.as_str()), (0.0, 0.0, "A1"));
+
+    let bottom_right = himmelsdorf.normalize(400.0, -300.0);
+    assert_eq!((bottom_right.x, bottom_right.y, bottom_right.square.as_str()), (1.0, 1.0, "K0"));
+
+    // Row "I" does not exist on the minimap: the ninth row is "J"
+    let center = himmelsdorf.normalize(50.0, 50.0);
+    assert_eq!((center.x, center.y, center.square.as_str()), (0.5, 0.5, "F6"));
+    assert_eq!(himmelsdorf.normalize(-290.0, -200.0).square, "J1");
+
+    // Outside the bounds is clamped to the border squares
+    assert_eq!(himmelsdorf.normalize(900.0, 900.0).square, "A0");
+    assert!(maps.get("00_unknown").is_none());
+
+    // Bounds keep the precision of the game's arena definitions
+    let minsk = maps.get("90_minsk").unwrap().bounds;
+    assert_eq!((minsk.min_x, minsk.min_z, minsk.max_x, minsk.max_z), (-506.2189, -520.7199, 527.31134, 512.8103));
+}
+
+#[test]
+fn test_catalog_json_adds_bases_and_spawns() {
+    let path = std::env::temp_dir().join(format!("replays-parser-maps-{}.json", std::process::id()));
+    fs::write(
+        &path,
+        r#"{
+            "04_himmelsdorf": {
+                "bounds": {"min_x": -300.0, "min_z": -300.0, "max_x": 400.0, "max_z": 400.0},
+                "modes": {
+                    "ctf": {
+                        "bases": {"1": [[-200.0, 300.0]], "2": [[300.0, -200.0]]},
+                        "spawns": {"1": [[-250.0, 350.0], [-150.0, 350.0]]}
+                    }
+                }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let mut maps = MapCatalog::builtin();
+    maps.load_json(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let ctf = maps.get("04_himmelsdorf").unwrap().team_positions("ctf").unwrap();
+    assert_eq!(ctf.bases[&2], vec![[300.0, -200.0]]);
+    assert_eq!(ctf.spawns[&1].len(), 2);
+    assert!(ctf.control_point.is_none());
+    // Other maps keep their built-in bounds
+    assert!(maps.get("127_japort").is_some());
+}
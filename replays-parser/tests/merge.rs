@@ -0,0 +1,84 @@
+mod common;
+
+use common::{battle_config_for, OBJECT_277, TF_4};
+use replays_parser::arena::Period;
+use replays_parser::events::{Event, EventLog, TimedEvent};
+use replays_parser::merge::{merge_perspectives, Perspective, PRECISE_WINDOW};
+use replays_parser::positions::{MinimapEntry, PositionSample, PositionSource};
+use replays_parser::types::BattleConfig;
+
+const ENEMY_VEHICLE: u32 = 300;
+
+fn config(player: &str) -> BattleConfig {
+    battle_config_for(player, [("recorder", OBJECT_277), ("enemy", TF_4)])
+}
+
+// The countdown ends and the battle starts at server time 1000
+fn countdown(time: f32) -> TimedEvent {
+    TimedEvent { time, event: Event::ArenaPeriod { period: Period::Prebattle, end_time: 1000.0, length: 10.0 } }
+}
+
+fn battle_start(time: f32) -> TimedEvent {
+    TimedEvent { time, event: Event::ArenaPeriod { period: Period::Battle, end_time: 1900.0, length: 900.0 } }
+}
+
+fn minimap(time: f32, x: f32) -> TimedEvent {
+    // Index 1 of the roster sorted by vehicle ID
+    TimedEvent { time, event: Event::MinimapPositions { entries: vec![MinimapEntry { index: 1, x, z: 0.0 }] } }
+}
+
+#[test]
+fn test_merge_prefers_precise_positions() {
+    let sample = PositionSample {
+        vehicle: ENEMY_VEHICLE,
+        x: 10.5,
+        z: 0.0,
+        y: Some(1.0),
+        yaw: Some(0.0),
+        source: PositionSource::EntityMove,
+        map: None,
+    };
+    // The first recorder had the enemy in view 5 s into the battle
+    let close = EventLog {
+        events: vec![countdown(0.0), battle_start(10.0), TimedEvent { time: 15.0, event: Event::Position(sample) }],
+        end_time: 60.0,
+    };
+    // The second recorder loaded later and only saw the enemy on the minimap
+    let far = EventLog { events: vec![countdown(10.0), battle_start(20.0), minimap(25.5, 11.0), minimap(40.0, 50.0)], end_time: 60.0 };
+    // Recorded after a reconnect: the period arrives on joining, 45 s into the battle,
+    // and a stun applied at server time 1050 ties the clock to the server
+    let stun = Event::StunInfo { vehicle: 200, finish_time: 1060.0, duration: Some(10.0) };
+    let rejoined = EventLog { events: vec![battle_start(1.0), TimedEvent { time: 6.0, event: stun }, minimap(7.0, 30.0)], end_time: 60.0 };
+    // Nothing tells when a period arriving on joining started
+    let late = EventLog { events: vec![battle_start(1.0), minimap(5.0, 0.0)], end_time: 60.0 };
+
+    let (close_config, far_config) = (config("recorder"), config("enemy"));
+    let perspectives = [
+        Perspective { replay: "close.wotreplay", config: &close_config, log: &close },
+        Perspective { replay: "far.wotreplay", config: &far_config, log: &far },
+        Perspective { replay: "rejoined.wotreplay", config: &far_config, log: &rejoined },
+        Perspective { replay: "late.wotreplay", config: &far_config, log: &late },
+    ];
+    let merged = merge_perspectives(&perspectives, None, PRECISE_WINDOW);
+
+    assert_eq!(merged.unaligned, vec!["late.wotreplay"]);
+    assert_eq!(merged.perspectives.len(), 3);
+    assert_eq!((merged.perspectives[1].player.as_str(), merged.perspectives[1].battle_start), ("enemy", 20.0));
+    assert_eq!(merged.perspectives[2].battle_start, -44.0);
+
+    // The minimap sample half a second after the precise one is dropped
+    let observations: Vec<_> =
+        merged.observations.iter().map(|o| (o.time, o.source_replay.as_str(), o.sample.source, o.sample.x)).collect();
+    assert_eq!(
+        observations,
+        vec![
+            (5.0, "close.wotreplay", PositionSource::EntityMove, 10.5),
+            (20.0, "far.wotreplay", PositionSource::Minimap, 50.0),
+            (51.0, "rejoined.wotreplay", PositionSource::Minimap, 30.0),
+        ]
+    );
+
+    let json = serde_json::to_value(&merged.observations[0]).unwrap();
+    assert_eq!(json["source_replay"], "close.wotreplay");
+    assert_eq!(json["vehicle"], ENEMY_VEHICLE);
+}
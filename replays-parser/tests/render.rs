@@ -0,0 +1,61 @@
+mod common;
+
+use common::{battle_config_for, OBJECT_277, TF_4};
+use replays_parser::arena::DeathReason;
+use replays_parser::events::{Event, EventLog, TimedEvent};
+use replays_parser::maps::MapCatalog;
+use replays_parser::positions::PositionSource;
+use replays_parser::render::{render_svg, RenderOptions};
+use replays_parser::types::BattleConfig;
+
+fn battle_config() -> BattleConfig {
+    // The enemy's name needs escaping
+    battle_config_for("recorder", [("recorder", OBJECT_277), ("<enemy>", TF_4)])
+}
+
+fn position(time: f32, vehicle: u32, x: f32, z: f32) -> TimedEvent {
+    common::position(time, vehicle, x, z, PositionSource::Minimap)
+}
+
+#[test]
+fn test_render_svg_animates_vehicles_tracers_and_deaths() {
+    let log = EventLog {
+        events: vec![
+            position(0.0, 200, -300.0, 400.0),
+            position(0.0, 300, 400.0, -300.0),
+            position(2.0, 200, 50.0, 50.0),
+            TimedEvent { time: 2.0, event: Event::ShotFired { shooter: 200, burst_count: 1, gun_index: 0 } },
+            TimedEvent {
+                time: 2.0,
+                event: Event::TracerStarted {
+                    shooter: 200,
+                    shot_id: 7,
+                    is_ricochet: false,
+                    start: [50.0, 0.0, 50.0],
+                    velocity: [1.0, 0.0, -1.0],
+                },
+            },
+            TimedEvent { time: 2.5, event: Event::TracerStopped { shot_id: 7, end_point: [400.0, 0.0, -300.0] } },
+            TimedEvent { time: 3.0, event: Event::VehicleKilled { victim: 300, killer: 200, reason: DeathReason::Shot } },
+        ],
+        end_time: 4.0,
+    };
+    let maps = MapCatalog::builtin();
+    let options = RenderOptions { size: 700.0, interval: 1.0, speed: 2.0 };
+    let svg = render_svg(&log, &battle_config(), maps.get("04_himmelsdorf").unwrap(), &options).unwrap();
+
+    assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+    // 5 frames over 2 seconds; the player moves from the top-left corner to the center
+    assert!(svg.contains(r#"<animate attributeName="cx" values="20.0;20.0;370.0;370.0;370.0" dur="2.00s""#));
+    // The enemy dies at 3 s: grey from the fourth frame on, with a cross appearing
+    assert!(svg.contains(r##"values="#e53935;#616161" keyTimes="0.0000;0.7500""##));
+    assert!(svg.contains(r#"values="0;1" keyTimes="0.0000;0.7500""#));
+    // The tracer shows from 2 s until impact at 2.5 s
+    assert!(svg.contains(r##"<line x1="370.0" y1="370.0" x2="720.0" y2="720.0" stroke="#43a047""##));
+    assert!(svg.contains(r#"values="0;0.9;0" keyTimes="0.0000;0.5000;0.6250""#));
+    assert!(svg.contains("&lt;enemy&gt; (usa:A171_TF_4)"));
+
+    // A corrupt packet time is refused rather than rendered
+    let corrupt = EventLog { events: log.events, end_time: 1e30 };
+    assert!(render_svg(&corrupt, &battle_config(), maps.get("04_himmelsdorf").unwrap(), &options).is_err());
+}
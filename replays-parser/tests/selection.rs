@@ -0,0 +1,67 @@
+use replays_parser::inputs::glob_set;
+use replays_parser::selection::{BattleDate, ClientVersion, DateBound, Selection};
+use replays_parser::types::{Replay, ReplayHeader};
+
+fn replay(battle_results: Option<serde_json::Value>) -> Replay {
+    Replay {
+        header: ReplayHeader { magic: 0x11343212, block_count: 2 },
+        battle_config: serde_json::from_value(serde_json::json!({
+            "playerName": "recorder",
+            "playerVehicle": "ussr-R155_Object_277",
+            "clientVersionFromXml": "1.32.0",
+            "clientVersionFromExe": "1.32.0.0",
+            "dateTime": "19.02.2025 17:20:10",
+            "mapName": "04_himmelsdorf",
+            "gameplayID": "ctf",
+            "battleType": 1
+        }))
+        .unwrap(),
+        battle_results,
+        packets_buffer: Vec::new(),
+    }
+}
+
+#[test]
+fn test_versions_and_dates() {
+    let version = |s: &str| s.parse::<ClientVersion>().unwrap();
+    assert_eq!(version("1.32"), version("1.32.0.0"));
+    assert_eq!(version("1_32_0"), version("1.32.0"));
+    assert!(version("1.9.1") < version("1.32") && version("1.32.0.1") > version("1.32"));
+    assert!("1.32-beta".parse::<ClientVersion>().is_err());
+
+    let date = BattleDate::from_replay("19.02.2025 17:20:10").unwrap();
+    assert_eq!(date.to_string(), "2025-02-19 17:20:10");
+    assert_eq!("2025-02-19T17:20".parse::<DateBound>().unwrap().date.to_string(), "2025-02-19 17:20:00");
+    assert!("2025-02-19".parse::<DateBound>().unwrap().date_only);
+    assert!("19.02.2025".parse::<DateBound>().is_err());
+    assert!(BattleDate::from_replay("19.02.2025 25:00:00").is_err());
+    assert!(BattleDate::from_replay("31.02.2025 17:20:10").is_err());
+    assert!(BattleDate::from_replay("29.02.2025 17:20:10").is_err());
+    assert!(BattleDate::from_replay("29.02.2024 17:20:10").is_ok());
+    assert!("2100-02-29".parse::<DateBound>().is_err());
+    assert!("2000-02-29".parse::<DateBound>().is_ok());
+    assert!("2025-04-31".parse::<DateBound>().is_err());
+}
+
+#[test]
+fn test_selection_matches_metadata() {
+    let (plain, with_results) = (replay(None), replay(Some(serde_json::json!([{"common": {"winnerTeam": 1}}]))));
+    assert!(Selection::default().is_empty());
+
+    let select = |selection: Selection| (selection.matches(&plain), selection.matches(&with_results));
+    let maps = |maps: &[&str]| maps.iter().map(|map| map.to_string()).collect::<Vec<_>>();
+    assert_eq!(select(Selection { maps: maps(&["05_prohorovka", "04_himmelsdorf"]), ..Default::default() }), (true, true));
+    assert_eq!(select(Selection { gameplays: maps(&["domination"]), ..Default::default() }), (false, false));
+    assert_eq!(select(Selection { has_results: Some(true), ..Default::default() }), (false, true));
+    assert_eq!(select(Selection { battle_types: vec![1], ..Default::default() }), (true, true));
+    assert_eq!(select(Selection { vehicles: glob_set(&maps(&["ussr-*"])).unwrap(), ..Default::default() }), (true, true));
+    assert_eq!(select(Selection { vehicles: glob_set(&maps(&["germany-*"])).unwrap(), ..Default::default() }), (false, false));
+    assert_eq!(select(Selection { min_version: Some("1.32.0.1".parse().unwrap()), ..Default::default() }), (false, false));
+    assert_eq!(select(Selection { max_version: Some("1.32".parse().unwrap()), ..Default::default() }), (true, true));
+
+    // A date-only upper bound includes the whole day
+    let bounds = |from: &str, to: &str| Selection { date_from: from.parse().ok(), date_to: to.parse().ok(), ..Default::default() };
+    assert_eq!(select(bounds("2025-02-19", "2025-02-19")), (true, true));
+    assert_eq!(select(bounds("2025-02-19T17:21", "")), (false, false));
+    assert_eq!(select(bounds("", "2025-02-19 17:20")), (false, false));
+}
@@ -0,0 +1,47 @@
+use replays_parser::dedupe::{battle_id, BattleId};
+use replays_parser::events::EventLog;
+use replays_parser::shards::{fnv1a, shard_file_name, shard_key, shard_of};
+use replays_parser::types::{Replay, ReplayHeader};
+
+fn replay(player: &str, battle_results: Option<serde_json::Value>) -> Replay {
+    Replay {
+        header: ReplayHeader { magic: 0x11343212, block_count: 2 },
+        battle_config: serde_json::from_value(serde_json::json!({
+            "playerName": player,
+            "playerVehicle": "ussr-R155_Object_277",
+            "clientVersionFromXml": "1.32.0",
+            "clientVersionFromExe": "1.32.0.0",
+            "dateTime": "19.02.2025 17:20:10",
+            "mapName": "04_himmelsdorf",
+            "gameplayID": "ctf"
+        }))
+        .unwrap(),
+        battle_results,
+        packets_buffer: Vec::new(),
+    }
+}
+
+#[test]
+fn test_fnv1a_reference_values() {
+    assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+}
+
+#[test]
+fn test_shard_key_follows_the_battle() {
+    let log = EventLog::default();
+    let arena = serde_json::json!([{"arenaUniqueID": 3850942432522416u64, "common": {}}, {}]);
+    let key = shard_key(&replay("recorder", Some(arena)), &log);
+    assert_eq!(key, fnv1a(&3850942432522416u64.to_le_bytes()));
+    assert!(shard_of(key, 16) < 16);
+    assert_eq!(shard_of(key, 0), 0);
+
+    // Without an arena ID, perspectives of one battle share the fingerprint's shard
+    let (recorder, enemy) = (replay("recorder", None), replay("enemy", None));
+    assert!(matches!(battle_id(&recorder, &log), BattleId::Fingerprint(_)));
+    assert_eq!(shard_key(&recorder, &log), battle_id(&recorder, &log).shard_key());
+    assert_eq!(shard_key(&recorder, &log), shard_key(&enemy, &log));
+
+    assert_eq!(shard_file_name(3, "parquet"), "shard-00003.parquet");
+}
@@ -0,0 +1,49 @@
+use replays_parser::dedupe::BattleId;
+use replays_parser::inputs::Input;
+use replays_parser::shards::ReplayStatus;
+use replays_parser::state::{definitions_key, Check, State, StateEntry};
+
+#[test]
+fn test_state_skips_unchanged_files() {
+    let dir = std::env::temp_dir().join(format!("replays-parser-state-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (path, copy, store) = (dir.join("a.wotreplay"), dir.join("b.wotreplay"), dir.join("state.json"));
+    std::fs::write(&path, b"replay contents").unwrap();
+    let replay = Input::file(&path);
+    let definitions = definitions_key("1.32.0", 0x1234);
+    assert_eq!(definitions, "1.32.0@0000000000001234");
+
+    let mut state = State::load(&store).unwrap();
+    let Check::Process { size, mtime_ns, hash } = state.check(&replay, &definitions).unwrap() else {
+        panic!("a new file must be processed");
+    };
+    assert_eq!(size, 15);
+    let entry = StateEntry {
+        size,
+        mtime_ns,
+        hash,
+        definitions: definitions.clone(),
+        output: "out.jsonl".to_string(),
+        status: ReplayStatus::Ok,
+        battle_id: Some(BattleId::Arena(42)),
+    };
+    state.record(&path, entry.clone());
+    state.save(&store).unwrap();
+
+    let state = State::load(&store).unwrap();
+    assert_eq!(state.check(&replay, &definitions).unwrap(), Check::Unchanged(entry.clone()));
+    // New definitions for the version reprocess the file
+    assert!(matches!(state.check(&replay, &definitions_key("1.32.0", 0x5678)).unwrap(), Check::Process { .. }));
+
+    // A copy is recognized by its contents
+    std::fs::write(&copy, b"replay contents").unwrap();
+    let copy = Input::file(&copy);
+    let Check::Unchanged(known) = state.check(&copy, &definitions).unwrap() else {
+        panic!("a copy of a processed file must be skipped");
+    };
+    assert_eq!((known.output.as_str(), known.battle_id), ("out.jsonl", Some(BattleId::Arena(42))));
+
+    std::fs::write(copy.path(), b"other contents!").unwrap();
+    assert!(matches!(state.check(&copy, &definitions).unwrap(), Check::Process { .. }));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use common::battle_config;
+use replays_parser::arena::{DeathReason, Period};
+use replays_parser::battle_events::BattleEvent;
+use replays_parser::events::{Event, EventLog, TimedEvent};
+use replays_parser::positions::PositionSource;
+use replays_parser::summary::summarize;
+use replays_parser::types::{Replay, ReplayHeader};
+
+const PLAYER_VEHICLE: u32 = 200;
+const ENEMY_VEHICLE: u32 = 300;
+
+fn replay() -> Replay {
+    Replay {
+        header: ReplayHeader { magic: 0x11343212, block_count: 2 },
+        battle_config: battle_config(),
+        battle_results: Some(serde_json::json!([
+            {
+                "common": {"winnerTeam": 1, "duration": 50},
+                "vehicles": {"200": [{"typeCompDescr": 59393}], "300": [{"typeCompDescr": 60225}]},
+                "personal": {
+                    "avatar": {"avatarDamageDealt": 0},
+                    "60225": {"damageDealt": 0, "lifeTime": 30},
+                    "59393": {
+                        "damageDealt": 300, "damageReceived": 0, "kills": 1, "spotted": 1,
+                        "shots": 2, "mileage": 51, "lifeTime": 50, "damageBlockedByArmor": 0
+                    }
+                }
+            },
+            {},
+            {}
+        ])),
+        packets_buffer: Vec::new(),
+    }
+}
+
+fn position(time: f32, x: f32, z: f32) -> TimedEvent {
+    common::position(time, PLAYER_VEHICLE, x, z, PositionSource::EntityMove)
+}
+
+#[test]
+fn test_summary_cross_checks_battle_results() {
+    let log = EventLog {
+        events: vec![
+            TimedEvent { time: 10.0, event: Event::ArenaPeriod { period: Period::Battle, end_time: 0.0, length: 0.0 } },
+            position(10.0, 0.0, 0.0),
+            position(20.0, 30.0, 40.0),
+            position(25.0, 30.0, 40.0),
+            TimedEvent {
+                time: 28.0,
+                event: Event::BattleEvents {
+                    events: vec![
+                        BattleEvent::Spotted { target: ENEMY_VEHICLE },
+                        BattleEvent::Damage { target: ENEMY_VEHICLE, damage: 300, reason: DeathReason::Shot },
+                    ],
+                },
+            },
+            TimedEvent {
+                time: 30.0,
+                event: Event::VehicleKilled { victim: ENEMY_VEHICLE, killer: PLAYER_VEHICLE, reason: DeathReason::Shot },
+            },
+        ],
+        end_time: 60.0,
+    };
+    let summary = summarize(&replay(), &log);
+
+    assert!(summary.has_battle_results);
+    assert_eq!(summary.damage_dealt.value, 300.0);
+    assert_eq!(summary.damage_dealt.discrepancy, Some(0.0));
+    assert_eq!((summary.kills.value, summary.enemies_spotted.value), (1.0, 1.0));
+    assert_eq!(summary.distance_driven.value, 50.0);
+    assert_eq!(summary.distance_driven.discrepancy, Some(-1.0));
+    assert_eq!(summary.time_stationary.value, 5.0);
+    // lifeTime bounds the times the server has no field for
+    assert_eq!(summary.time_stationary.battle_results, Some(50.0));
+    assert_eq!(summary.time_stationary.discrepancy, Some(0.0));
+    assert_eq!(summary.survival_time.value, 50.0);
+    assert!(summary.survived && !summary.first_death);
+    // No shot events were decoded, but the server counted two
+    assert_eq!(summary.shots.discrepancy, Some(-2.0));
+    assert_eq!(summary.discrepancies, vec!["shots"]);
+
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["shots"], serde_json::json!({"value": 0.0, "battle_results": 2.0, "discrepancy": -2.0}));
+    // Never spotted: an empty sum, serialized without a sign
+    let time_spotted = serde_json::to_string(&summary.time_spotted).unwrap();
+    assert_eq!(time_spotted, r#"{"value":0.0,"battle_results":50.0,"discrepancy":0.0}"#);
+}
+
+#[test]
+fn test_personal_results_belong_to_the_player_vehicle() {
+    let mut replay = replay();
+    assert_eq!(replay.personal_results().unwrap()["lifeTime"], 50);
+    // Results without the player's vehicle are not guessed from another entry
+    replay.battle_results.as_mut().unwrap()[0]["vehicles"].as_object_mut().unwrap().remove("200");
+    assert!(replay.personal_results().is_none());
+}
+
+#[test]
+fn test_validation_report_agreement_rates() {
+    use replays_parser::validate::VersionReport;
+
+    let log = EventLog {
+        events: vec![TimedEvent {
+            time: 30.0,
+            event: Event::VehicleKilled { victim: ENEMY_VEHICLE, killer: PLAYER_VEHICLE, reason: DeathReason::Shot },
+        }],
+        end_time: 60.0,
+    };
+    let mut without_results = replay();
+    without_results.battle_results = None;
+
+    let mut report = VersionReport::default();
+    report.add(&summarize(&replay(), &log));
+    report.add(&summarize(&without_results, &log));
+
+    assert_eq!((report.replays, report.without_results), (2, 1));
+    assert_eq!(report.metrics["kills"].rate(), Some(1.0));
+    // No damage ribbons were decoded: the definitions would be suspect
+    assert_eq!(report.metrics["damage_dealt"].rate(), Some(0.0));
+    assert_eq!(report.worst_rate(), Some(0.0));
+    // Only the validated metrics are reported
+    assert!(!report.metrics.contains_key("distance_driven"));
+}
+
+#[test]
+fn test_episode_labels_look_ahead() {
+    use replays_parser::labels::{episode_labels, BattleOutcome};
+
+    let log = EventLog {
+        events: vec![TimedEvent {
+            time: 40.0,
+            event: Event::VehicleKilled { victim: PLAYER_VEHICLE, killer: ENEMY_VEHICLE, reason: DeathReason::Shot },
+        }],
+        end_time: 60.0,
+    };
+    let labels = episode_labels(&replay(), &log);
+    assert_eq!(labels.outcome, Some(BattleOutcome::Win));
+    // No deathCount in the results: the kill feed decides
+    assert_eq!(labels.survived, Some(false));
+    assert_eq!(labels.final_damage, Some(300));
+    assert_eq!(labels.at(25.0).time_to_death, Some(15.0));
+
+    let mut incomplete = replay();
+    incomplete.battle_results = None;
+    let labels = episode_labels(&incomplete, &EventLog { events: Vec::new(), end_time: 60.0 });
+    assert_eq!(
+        serde_json::to_value(labels.at(0.0)).unwrap(),
+        serde_json::json!({
+            "outcome": null, "survived": null, "final_damage": null, "incomplete": true, "time_to_death": null
+        })
+    );
+}